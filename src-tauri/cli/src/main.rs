@@ -0,0 +1,344 @@
+//! `bokforing`: the bookkeeping of the desktop app's database file from the
+//! command line, for scripts and nightly jobs.
+//!
+//! Results are printed as JSON on stdout. Failures are printed as
+//! `{"error": "..."}` on stderr with a non-zero exit code: 1 when the
+//! command is rejected (e.g. an unbalanced voucher or a locked period), 2 for
+//! invalid arguments, 3 when the database cannot be opened and 4 when the
+//! user cannot be authenticated or may not run the command. A voucher with invalid fields is reported
+//! as `{"error": "Invalid fields", "fields": [{"field": "...", "message": "..."}]}`.
+//!
+//! `bokforing serve` instead answers the same commands over HTTP on
+//! localhost; see [`server`].
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use bokforing_core::db::{self, Role};
+use bokforing_core::ids::{AccountId, ApiTokenId, CompanyId, VoucherId};
+use bokforing_core::models::{
+    ApiTokenIdInput, CreateApiTokenInput, ExportInput, FieldError, LockPeriodInput,
+    ReportLedgerInput, ReportTrialBalanceInput, ReportVoucherListInput, User, VoucherIdInput,
+};
+use bokforing_core::{api_tokens, users, Books};
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+mod server;
+
+const EXIT_REJECTED: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_DATABASE: u8 = 3;
+const EXIT_DENIED: u8 = 4;
+
+#[derive(Parser)]
+#[command(
+    name = "bokforing",
+    version,
+    about = "Headless bookkeeping on the app's database"
+)]
+struct Cli {
+    /// The database file, bokforing.sqlite in the app's data directory.
+    #[arg(long, env = "BOKFORING_DATABASE")]
+    database: PathBuf,
+    /// Passphrase of an encrypted database.
+    #[arg(long, env = "BOKFORING_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+    /// The user the work is done as. Their role decides which commands are
+    /// allowed, as in the app. Required with `--password` for all commands
+    /// but `serve`, unless `--token` is given.
+    #[arg(long, env = "BOKFORING_USER")]
+    user: Option<String>,
+    /// The password of `--user`.
+    #[arg(long, env = "BOKFORING_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+    /// An API token to work as its user instead of `--user` and `--password`.
+    #[arg(
+        long,
+        env = "BOKFORING_TOKEN",
+        hide_env_values = true,
+        conflicts_with = "user"
+    )]
+    token: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the companies in the database.
+    Companies,
+    /// Create a voucher from a JSON file ("-" reads stdin), in the same
+    /// format as the app's create_voucher command.
+    CreateVoucher { file: PathBuf },
+    /// Post a voucher, after which it can only be corrected.
+    PostVoucher { voucher_id: VoucherId },
+    /// Lock a period against new and posted vouchers.
+    LockPeriod {
+        #[arg(long)]
+        company_id: CompanyId,
+        /// First day of the period, YYYY-MM-DD.
+        #[arg(long)]
+        start: NaiveDate,
+        /// Last day of the period, YYYY-MM-DD.
+        #[arg(long)]
+        end: NaiveDate,
+    },
+    /// Vouchers with their totals, by date.
+    VoucherList {
+        #[arg(long)]
+        company_id: CompanyId,
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// Transactions and running balance of one account.
+    Ledger {
+        #[arg(long)]
+        company_id: CompanyId,
+        #[arg(long)]
+        account_id: AccountId,
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// Opening balance, debits, credits and closing balance per account.
+    TrialBalance {
+        #[arg(long)]
+        company_id: CompanyId,
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// Write vouchers.csv and voucher_rows.csv into a directory.
+    ExportCsv {
+        #[arg(long)]
+        company_id: CompanyId,
+        #[arg(long)]
+        target: String,
+    },
+    /// Write an SIE file, or export.sie in a directory.
+    ExportSie {
+        #[arg(long)]
+        company_id: CompanyId,
+        #[arg(long)]
+        target: String,
+    },
+    /// List the tokens of the API server.
+    ApiTokens,
+    /// Issue an API token acting as a user. The secret is printed once.
+    CreateApiToken {
+        /// The user the token acts as; their role applies to its requests.
+        #[arg(long)]
+        username: String,
+        /// What the token is for, e.g. "Webshop".
+        #[arg(long)]
+        name: String,
+    },
+    /// Revoke an API token.
+    RevokeApiToken { token_id: ApiTokenId },
+    /// Serve the vouchers, reports and exports as a JSON API on localhost,
+    /// authenticated with API tokens.
+    Serve {
+        #[arg(long, default_value_t = 8787)]
+        port: u16,
+    },
+}
+
+impl Command {
+    /// The role required and the command name recorded on denials, the same
+    /// as for the app's commands.
+    fn permission(&self) -> (Role, &'static str) {
+        match self {
+            Command::Companies => (Role::Viewer, "list_companies"),
+            Command::CreateVoucher { .. } => (Role::Bookkeeper, "create_voucher"),
+            Command::PostVoucher { .. } => (Role::Approver, "post_voucher"),
+            Command::LockPeriod { .. } => (Role::Approver, "lock_period"),
+            Command::VoucherList { .. } => (Role::Viewer, "report_voucher_list"),
+            Command::Ledger { .. } => (Role::Viewer, "report_ledger_for_account"),
+            Command::TrialBalance { .. } => (Role::Viewer, "report_trial_balance"),
+            Command::ExportCsv { .. } => (Role::Viewer, "export_csv"),
+            Command::ExportSie { .. } => (Role::Viewer, "export_sie_stub"),
+            Command::ApiTokens => (Role::Admin, "list_api_tokens"),
+            Command::CreateApiToken { .. } => (Role::Admin, "create_api_token"),
+            Command::RevokeApiToken { .. } => (Role::Admin, "revoke_api_token"),
+            Command::Serve { .. } => unreachable!("serve authorizes each request by its token"),
+        }
+    }
+}
+
+struct Failure {
+    exit_code: u8,
+    message: String,
+}
+
+impl Failure {
+    fn new(exit_code: u8, message: String) -> Failure {
+        Failure { exit_code, message }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(output) => {
+            println!("{:#}", output);
+            ExitCode::SUCCESS
+        }
+        Err(failure) => {
+            let error = match serde_json::from_str::<Vec<FieldError>>(&failure.message) {
+                Ok(fields) => json!({ "error": "Invalid fields", "fields": fields }),
+                Err(_) => json!({ "error": failure.message }),
+            };
+            eprintln!("{}", error);
+            ExitCode::from(failure.exit_code)
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<Value, Failure> {
+    // Opening a missing file would create a new, seeded database.
+    if !cli.database.is_file() {
+        return Err(Failure::new(
+            EXIT_DATABASE,
+            format!("Database not found: {}", cli.database.display()),
+        ));
+    }
+    let mut conn = db::open_database(&cli.database, cli.passphrase.as_deref())
+        .map_err(|err| Failure::new(EXIT_DATABASE, err.to_string()))?;
+    let documents_dir = db::documents_dir(&cli.database);
+    if let Command::Serve { port } = cli.command {
+        server::serve(&mut conn, &documents_dir, port)
+            .map_err(|err| Failure::new(EXIT_REJECTED, err))?;
+        return Ok(Value::Null);
+    }
+
+    let user = authenticate(&conn, cli.user, cli.password, cli.token)?;
+    let (required, command) = cli.command.permission();
+    users::authorize_user(&conn, &user, required, command)
+        .map_err(|err| Failure::new(EXIT_DENIED, err))?;
+
+    let mut books = Books::new(&mut conn, &user.username, &documents_dir);
+    execute(&mut books, cli.command).map_err(|err| Failure::new(EXIT_REJECTED, err))
+}
+
+/// The user of an API token, or of a username checked against its password.
+fn authenticate(
+    conn: &Connection,
+    username: Option<String>,
+    password: Option<String>,
+    token: Option<String>,
+) -> Result<User, Failure> {
+    if let Some(token) = token {
+        return api_tokens::token_user(conn, token.trim())
+            .map_err(|err| Failure::new(EXIT_DATABASE, err))?
+            .ok_or_else(|| Failure::new(EXIT_DENIED, "Invalid or revoked token".to_string()));
+    }
+    let (Some(username), Some(password)) = (username, password) else {
+        return Err(Failure::new(
+            EXIT_USAGE,
+            "--user and --password (BOKFORING_USER and BOKFORING_PASSWORD) or --token \
+             (BOKFORING_TOKEN) are required"
+                .to_string(),
+        ));
+    };
+    users::authenticate(conn, &username, &password).map_err(|err| Failure::new(EXIT_DENIED, err))
+}
+
+fn execute(books: &mut Books<'_>, command: Command) -> Result<Value, String> {
+    match command {
+        Command::Companies => to_json(books.list_companies()?),
+        Command::CreateVoucher { file } => {
+            let payload = serde_json::from_str(&read_input(&file)?)
+                .map_err(|err| format!("Invalid voucher JSON: {}", err))?;
+            to_json(books.create_voucher(payload)?)
+        }
+        Command::PostVoucher { voucher_id } => {
+            to_json(books.post_voucher(VoucherIdInput { voucher_id })?)
+        }
+        Command::LockPeriod {
+            company_id,
+            start,
+            end,
+        } => to_json(books.lock_period(LockPeriodInput {
+            company_id,
+            period_start: start,
+            period_end: end,
+        })?),
+        Command::VoucherList {
+            company_id,
+            from,
+            to,
+        } => to_json(books.report_voucher_list(ReportVoucherListInput {
+            company_id,
+            from_date: from,
+            to_date: to,
+        })?),
+        Command::Ledger {
+            company_id,
+            account_id,
+            from,
+            to,
+        } => to_json(books.report_ledger_for_account(ReportLedgerInput {
+            company_id,
+            account_id,
+            from_date: from,
+            to_date: to,
+        })?),
+        Command::TrialBalance {
+            company_id,
+            from,
+            to,
+        } => to_json(books.report_trial_balance(ReportTrialBalanceInput {
+            company_id,
+            from_date: from,
+            to_date: to,
+        })?),
+        Command::ExportCsv { company_id, target } => {
+            let message = books.export_csv(ExportInput {
+                company_id,
+                target_path: target,
+            })?;
+            Ok(json!({ "message": message }))
+        }
+        Command::ExportSie { company_id, target } => {
+            let message = books.export_sie_stub(ExportInput {
+                company_id,
+                target_path: target,
+            })?;
+            Ok(json!({ "message": message }))
+        }
+        Command::ApiTokens => to_json(books.list_api_tokens()?),
+        Command::CreateApiToken { username, name } => {
+            to_json(books.create_api_token(CreateApiTokenInput { username, name })?)
+        }
+        Command::RevokeApiToken { token_id } => {
+            to_json(books.revoke_api_token(ApiTokenIdInput { token_id })?)
+        }
+        Command::Serve { .. } => unreachable!("serve is run before any command"),
+    }
+}
+
+fn read_input(file: &Path) -> Result<String, String> {
+    let mut input = String::new();
+    if file == Path::new("-") {
+        io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|err| err.to_string())?;
+    } else {
+        input = fs::read_to_string(file)
+            .map_err(|err| format!("Cannot read {}: {}", file.display(), err))?;
+    }
+    Ok(input)
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|err| err.to_string())
+}
@@ -0,0 +1,406 @@
+//! `bokforing serve`: a JSON API on localhost for other programs in the
+//! office, such as time tracking or a webshop, that push vouchers.
+//!
+//! Every request carries `Authorization: Bearer <token>` with a token from
+//! `bokforing create-api-token`. The request runs as the token's user and
+//! needs the same role as the corresponding app command. Requests are handled
+//! one at a time, so writes never interleave.
+//!
+//! - `GET /api/companies`: `list_companies`
+//! - `GET /api/companies/{id}/vouchers?from=&to=&limit=&after=`: `list_vouchers`,
+//!   newest first; `after` is the id of the last voucher of the previous page
+//! - `POST /api/vouchers`: `create_voucher`
+//! - `GET /api/vouchers/{id}`: `get_voucher`
+//! - `POST /api/vouchers/{id}/post`: `post_voucher`
+//! - `GET /api/companies/{id}/reports/voucher-list?from=&to=`:
+//!   `report_voucher_list`
+//! - `GET /api/companies/{id}/reports/ledger?accountId=&from=&to=`:
+//!   `report_ledger_for_account`
+//! - `GET /api/companies/{id}/reports/trial-balance?from=&to=`:
+//!   `report_trial_balance`
+//! - `GET /api/companies/{id}/exports/csv`: `export_csv`, as
+//!   `{"vouchers": "...", "voucherRows": "..."}`
+//! - `GET /api/companies/{id}/exports/sie`: `export_sie_stub`, as
+//!   `{"sie": "..."}`
+//!
+//! Request bodies are the app commands' JSON payloads. The exports are
+//! returned in the response rather than written, so a caller never chooses a
+//! path on the server's disk. Errors are `{"error": "..."}` with status 400 for
+//! a malformed request, 401 for a missing or unknown token, 403 when the
+//! user's role is too low, 404 for an unknown path and 422 when the books
+//! reject the request (e.g. an unbalanced voucher or a locked period). A
+//! voucher with invalid fields gets 422 with
+//! `{"error": "Invalid fields", "fields": [{"field": "...", "message": "..."}]}`.
+
+use std::fmt::Display;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use bokforing_core::db::Role;
+use bokforing_core::models::{
+    CompanyIdInput, CreateVoucherInput, FieldError, ListVouchersInput, ReportLedgerInput,
+    ReportTrialBalanceInput, ReportVoucherListInput, User, VoucherIdInput,
+};
+use bokforing_core::{api_tokens, users, Books};
+use rusqlite::Connection;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::to_json;
+
+/// Larger bodies are refused; a voucher is a few kilobytes.
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+enum Route {
+    Companies,
+    Vouchers(ListVouchersInput),
+    CreateVoucher(CreateVoucherInput),
+    Voucher(VoucherIdInput),
+    PostVoucher(VoucherIdInput),
+    VoucherList(ReportVoucherListInput),
+    Ledger(ReportLedgerInput),
+    TrialBalance(ReportTrialBalanceInput),
+    ExportCsv(CompanyIdInput),
+    ExportSie(CompanyIdInput),
+}
+
+struct ApiError {
+    status: u16,
+    message: String,
+    fields: Vec<FieldError>,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> ApiError {
+        ApiError {
+            status,
+            message: message.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// The books rejected the request. Their field errors come as a JSON
+    /// array and are passed on as `fields`.
+    fn rejected(message: String) -> ApiError {
+        match serde_json::from_str(&message) {
+            Ok(fields) => ApiError {
+                status: 422,
+                message: "Invalid fields".to_string(),
+                fields,
+            },
+            Err(_) => ApiError::new(422, message),
+        }
+    }
+
+    fn body(&self) -> Value {
+        if self.fields.is_empty() {
+            json!({ "error": &self.message })
+        } else {
+            json!({ "error": &self.message, "fields": &self.fields })
+        }
+    }
+}
+
+/// Serves the API on 127.0.0.1 until the process is stopped. Only returns if
+/// the port cannot be opened.
+pub fn serve(conn: &mut Connection, documents_dir: &Path, port: u16) -> Result<(), String> {
+    let server = Server::http(("127.0.0.1", port)).map_err(|err| err.to_string())?;
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .map_err(|_| "Invalid header")?;
+    eprintln!("Listening on http://127.0.0.1:{}", port);
+
+    for mut request in server.incoming_requests() {
+        let (status, body) = match handle(conn, documents_dir, &mut request) {
+            Ok(result) => result,
+            Err(err) => (err.status, err.body()),
+        };
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        // The client has gone away; nothing to do.
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
+fn handle(
+    conn: &mut Connection,
+    documents_dir: &Path,
+    request: &mut Request,
+) -> Result<(u16, Value), ApiError> {
+    let user = authenticate(conn, request)?;
+    let body = read_body(request)?;
+    let route = route(request.method(), request.url(), &body)?;
+    let (required, command) = route.permission();
+    users::authorize_user(conn, &user, required, command).map_err(|err| ApiError::new(403, err))?;
+
+    let status = match route {
+        Route::CreateVoucher(_) => 201,
+        _ => 200,
+    };
+    let mut books = Books::new(conn, &user.username, documents_dir);
+    let output = execute(&mut books, route).map_err(ApiError::rejected)?;
+    Ok((status, output))
+}
+
+/// The user of the bearer token.
+fn authenticate(conn: &Connection, request: &Request) -> Result<User, ApiError> {
+    let secret = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::new(401, "Missing bearer token"))?;
+    api_tokens::token_user(conn, secret.trim())
+        .map_err(|err| ApiError::new(500, err))?
+        .ok_or_else(|| ApiError::new(401, "Invalid or revoked token"))
+}
+
+fn read_body(request: &mut Request) -> Result<String, ApiError> {
+    if request.body_length().unwrap_or(0) as u64 > MAX_BODY_BYTES {
+        return Err(ApiError::new(413, "Request body is too large"));
+    }
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|err| ApiError::new(400, format!("Cannot read request body: {}", err)))?;
+    Ok(body)
+}
+
+fn route(method: &Method, url: &str, body: &str) -> Result<Route, ApiError> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let route = match (method, segments.as_slice()) {
+        (Method::Get, ["api", "companies"]) => Route::Companies,
+        (Method::Get, ["api", "companies", company_id, "vouchers"]) => {
+            Route::Vouchers(ListVouchersInput {
+                company_id: parse_value("companyId", company_id)?,
+                from_date: query_value(query, "from")?,
+                to_date: query_value(query, "to")?,
+                limit: query_value(query, "limit")?,
+                after: query_value(query, "after")?,
+            })
+        }
+        (Method::Post, ["api", "vouchers"]) => Route::CreateVoucher(parse_body(body)?),
+        (Method::Get, ["api", "vouchers", voucher_id]) => Route::Voucher(VoucherIdInput {
+            voucher_id: parse_value("voucherId", voucher_id)?,
+        }),
+        (Method::Post, ["api", "vouchers", voucher_id, "post"]) => {
+            Route::PostVoucher(VoucherIdInput {
+                voucher_id: parse_value("voucherId", voucher_id)?,
+            })
+        }
+        (Method::Get, ["api", "companies", company_id, "reports", "voucher-list"]) => {
+            Route::VoucherList(ReportVoucherListInput {
+                company_id: parse_value("companyId", company_id)?,
+                from_date: query_value(query, "from")?,
+                to_date: query_value(query, "to")?,
+            })
+        }
+        (Method::Get, ["api", "companies", company_id, "reports", "ledger"]) => {
+            Route::Ledger(ReportLedgerInput {
+                company_id: parse_value("companyId", company_id)?,
+                account_id: query_value(query, "accountId")?
+                    .ok_or_else(|| ApiError::new(400, "accountId is required"))?,
+                from_date: query_value(query, "from")?,
+                to_date: query_value(query, "to")?,
+            })
+        }
+        (Method::Get, ["api", "companies", company_id, "reports", "trial-balance"]) => {
+            Route::TrialBalance(ReportTrialBalanceInput {
+                company_id: parse_value("companyId", company_id)?,
+                from_date: query_value(query, "from")?,
+                to_date: query_value(query, "to")?,
+            })
+        }
+        (Method::Get, ["api", "companies", company_id, "exports", format]) => {
+            let input = CompanyIdInput {
+                company_id: parse_value("companyId", company_id)?,
+            };
+            match *format {
+                "csv" => Route::ExportCsv(input),
+                "sie" => Route::ExportSie(input),
+                _ => return Err(ApiError::new(404, "Not found")),
+            }
+        }
+        _ => return Err(ApiError::new(404, "Not found")),
+    };
+    Ok(route)
+}
+
+impl Route {
+    /// The role required and the command name recorded on denials, the same
+    /// as for the app's commands.
+    fn permission(&self) -> (Role, &'static str) {
+        match self {
+            Route::Companies => (Role::Viewer, "list_companies"),
+            Route::Vouchers(_) => (Role::Viewer, "list_vouchers"),
+            Route::CreateVoucher(_) => (Role::Bookkeeper, "create_voucher"),
+            Route::Voucher(_) => (Role::Viewer, "get_voucher"),
+            Route::PostVoucher(_) => (Role::Approver, "post_voucher"),
+            Route::VoucherList(_) => (Role::Viewer, "report_voucher_list"),
+            Route::Ledger(_) => (Role::Viewer, "report_ledger_for_account"),
+            Route::TrialBalance(_) => (Role::Viewer, "report_trial_balance"),
+            Route::ExportCsv(_) => (Role::Viewer, "export_csv"),
+            Route::ExportSie(_) => (Role::Viewer, "export_sie_stub"),
+        }
+    }
+}
+
+fn execute(books: &mut Books<'_>, route: Route) -> Result<Value, String> {
+    match route {
+        Route::Companies => to_json(books.list_companies()?),
+        Route::Vouchers(payload) => to_json(books.list_vouchers(payload)?),
+        Route::CreateVoucher(payload) => to_json(books.create_voucher(payload)?),
+        Route::Voucher(payload) => to_json(books.get_voucher(payload)?),
+        Route::PostVoucher(payload) => to_json(books.post_voucher(payload)?),
+        Route::VoucherList(payload) => to_json(books.report_voucher_list(payload)?),
+        Route::Ledger(payload) => to_json(books.report_ledger_for_account(payload)?),
+        Route::TrialBalance(payload) => to_json(books.report_trial_balance(payload)?),
+        Route::ExportCsv(payload) => to_json(books.render_csv(payload)?),
+        Route::ExportSie(payload) => Ok(json!({ "sie": books.render_sie_stub(payload)? })),
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(body: &str) -> Result<T, ApiError> {
+    serde_json::from_str(body).map_err(|err| ApiError::new(400, format!("Invalid JSON: {}", err)))
+}
+
+/// A path segment or query value such as an id or a date, named in the error.
+fn parse_value<T: FromStr>(name: &str, value: &str) -> Result<T, ApiError>
+where
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|err| ApiError::new(400, format!("{}: {}", name, err)))
+}
+
+fn query_value<T: FromStr>(query: &str, name: &str) -> Result<Option<T>, ApiError>
+where
+    T::Err: Display,
+{
+    query_param(query, name)
+        .map(|value| parse_value(name, &value))
+        .transpose()
+}
+
+/// The first value of `name` in a query string, percent-decoded. Empty values
+/// count as missing.
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+        .filter(|value| !value.is_empty())
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 2;
+            }
+            (b'+', _) => decoded.push(b' '),
+            (byte, _) => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPANY_ID: &str = "5f0c6a3e-8f1b-4d2a-9c3e-2b7d4e6f8a10";
+
+    fn route_error(method: Method, url: &str, body: &str) -> (u16, String) {
+        match route(&method, url, body) {
+            Ok(_) => panic!("{} {} was routed", method, url),
+            Err(err) => (err.status, err.message),
+        }
+    }
+
+    #[test]
+    fn routes_parse_ids_and_queries() {
+        let url = format!(
+            "/api/companies/{}/vouchers?from=2024-01-01&limit=10&to=",
+            COMPANY_ID
+        );
+        match route(&Method::Get, &url, "") {
+            Ok(Route::Vouchers(input)) => {
+                assert_eq!(input.company_id.as_str(), COMPANY_ID);
+                assert_eq!(input.from_date, "2024-01-01".parse().ok());
+                assert_eq!(input.to_date, None);
+                assert_eq!(input.limit, Some(10));
+            }
+            _ => panic!("not routed to list_vouchers"),
+        }
+        let url = format!("/api/companies/{}/exports/sie/", COMPANY_ID);
+        assert!(matches!(
+            route(&Method::Get, &url, ""),
+            Ok(Route::ExportSie(_))
+        ));
+        assert!(matches!(
+            route(&Method::Get, "/api/companies", ""),
+            Ok(Route::Companies)
+        ));
+    }
+
+    #[test]
+    fn bad_requests_get_their_status() {
+        assert_eq!(
+            route_error(Method::Post, "/api/companies", "").0,
+            404,
+            "wrong method"
+        );
+        assert_eq!(route_error(Method::Get, "/api/unknown", "").0, 404);
+        let url = format!("/api/companies/{}/exports/pdf", COMPANY_ID);
+        assert_eq!(route_error(Method::Get, &url, "").0, 404);
+        assert_eq!(
+            route_error(Method::Get, "/api/vouchers/not-an-id", ""),
+            (400, "voucherId: Invalid id: not-an-id".to_string())
+        );
+        let url = format!("/api/companies/{}/reports/ledger", COMPANY_ID);
+        assert_eq!(
+            route_error(Method::Get, &url, ""),
+            (400, "accountId is required".to_string())
+        );
+        let url = format!("/api/companies/{}/vouchers?from=2024-13-01", COMPANY_ID);
+        assert_eq!(route_error(Method::Get, &url, "").0, 400);
+        let (status, message) = route_error(Method::Post, "/api/vouchers", "{");
+        assert_eq!(status, 400);
+        assert!(message.starts_with("Invalid JSON"), "{}", message);
+    }
+
+    #[test]
+    fn query_params_are_decoded() {
+        let query = "name=Kaffe+%26+kaka&empty=&name=second&bare";
+        assert_eq!(query_param(query, "name").as_deref(), Some("Kaffe & kaka"));
+        assert_eq!(query_param(query, "empty"), None);
+        assert_eq!(query_param(query, "bare"), None);
+        assert_eq!(query_param(query, "missing"), None);
+    }
+
+    #[test]
+    fn percent_decoding_keeps_invalid_escapes() {
+        assert_eq!(percent_decode("r%C3%A4kning"), "räkning");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("a+b%2Bc"), "a b+c");
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::Utc;
+use password_hash::rand_core::{OsRng, RngCore};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use sha2::{Digest, Sha256};
+
+use crate::ids::ApiTokenId;
+use crate::models::{ApiToken, ApiTokenIdInput, CreateApiTokenInput, CreatedApiToken, User};
+use crate::users::fetch_user;
+use crate::{map_sql_error, Books};
+
+const TOKEN_COLUMNS: &str =
+    "t.id, u.username, t.name, t.created_at, t.created_by, t.last_used_at, t.revoked_at";
+/// Prefix of every secret, so that leaked tokens are easy to recognise.
+const SECRET_PREFIX: &str = "bkf_";
+const SECRET_BYTES: usize = 32;
+
+impl Books<'_> {
+    pub fn list_api_tokens(&mut self) -> Result<Vec<ApiToken>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM api_tokens t JOIN users u ON u.id = t.user_id
+                 ORDER BY t.created_at DESC",
+                TOKEN_COLUMNS
+            ))
+            .map_err(map_sql_error)?;
+        let rows = stmt.query_map([], token_from_row).map_err(map_sql_error)?;
+        let mut tokens = Vec::new();
+        for row in rows {
+            tokens.push(row.map_err(map_sql_error)?);
+        }
+        Ok(tokens)
+    }
+
+    /// Issues a token that acts as `payload.username` with that user's role.
+    /// The secret is returned once; only its hash is stored.
+    pub fn create_api_token(
+        &mut self,
+        payload: CreateApiTokenInput,
+    ) -> Result<CreatedApiToken, String> {
+        let conn = &*self.conn;
+        let name = payload.name.trim();
+        if name.is_empty() {
+            return Err("Token name is required".to_string());
+        }
+        let user_id: String = conn
+            .query_row(
+                "SELECT id FROM users WHERE username = ?1 AND is_active = 1",
+                [payload.username.trim()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(map_sql_error)?
+            .ok_or_else(|| format!("Unknown or inactive user: {}", payload.username.trim()))?;
+
+        let mut bytes = [0u8; SECRET_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        let secret = format!("{}{}", SECRET_PREFIX, hex(&bytes));
+        let id = ApiTokenId::generate();
+        conn.execute(
+            "INSERT INTO api_tokens (id, user_id, name, token_hash, created_at, created_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                &id,
+                &user_id,
+                name,
+                hash_secret(&secret),
+                Utc::now().to_rfc3339(),
+                self.user
+            ],
+        )
+        .map_err(map_sql_error)?;
+        Ok(CreatedApiToken {
+            token: fetch_token(conn, &id)?,
+            secret,
+        })
+    }
+
+    pub fn revoke_api_token(&mut self, payload: ApiTokenIdInput) -> Result<ApiToken, String> {
+        let conn = &*self.conn;
+        let token = fetch_token(conn, &payload.token_id)?;
+        if token.revoked_at.is_some() {
+            return Err("Token is already revoked".to_string());
+        }
+        conn.execute(
+            "UPDATE api_tokens SET revoked_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), &token.id],
+        )
+        .map_err(map_sql_error)?;
+        fetch_token(conn, &token.id)
+    }
+}
+
+/// The user a secret acts as, or `None` if the token is unknown, revoked or
+/// belongs to an inactive user. Records the use in `last_used_at`.
+pub fn token_user(conn: &Connection, secret: &str) -> Result<Option<User>, String> {
+    let token: Option<(String, String)> = conn
+        .query_row(
+            "SELECT t.id, t.user_id FROM api_tokens t JOIN users u ON u.id = t.user_id
+             WHERE t.token_hash = ?1 AND t.revoked_at IS NULL AND u.is_active = 1",
+            [hash_secret(secret)],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(map_sql_error)?;
+    let Some((token_id, user_id)) = token else {
+        return Ok(None);
+    };
+    conn.execute(
+        "UPDATE api_tokens SET last_used_at = ?1 WHERE id = ?2",
+        params![Utc::now().to_rfc3339(), token_id],
+    )
+    .map_err(map_sql_error)?;
+    fetch_user(conn, &user_id).map(Some)
+}
+
+fn fetch_token(conn: &Connection, token_id: &str) -> Result<ApiToken, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM api_tokens t JOIN users u ON u.id = t.user_id WHERE t.id = ?1",
+            TOKEN_COLUMNS
+        ),
+        [token_id],
+        token_from_row,
+    )
+    .optional()
+    .map_err(map_sql_error)?
+    .ok_or_else(|| "Token not found".to_string())
+}
+
+fn token_from_row(row: &Row) -> rusqlite::Result<ApiToken> {
+    Ok(ApiToken {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        name: row.get(2)?,
+        created_at: row.get(3)?,
+        created_by: row.get(4)?,
+        last_used_at: row.get(5)?,
+        revoked_at: row.get(6)?,
+    })
+}
+
+/// Secrets are random, so a plain SHA-256 is enough to keep the stored
+/// hashes useless to someone who reads the database.
+fn hash_secret(secret: &str) -> String {
+    hex(&Sha256::digest(secret.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,177 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+
+use crate::ids::{ApprovalId, VoucherId};
+use crate::models::{
+    ApproveVoucherInput, CompanyIdInput, PendingApproval, RejectVoucherInput, VoucherApproval,
+    VoucherIdInput,
+};
+use crate::{insert_audit_entry, map_sql_error, Books};
+
+pub const APPROVED: &str = "approved";
+pub const REJECTED: &str = "rejected";
+
+impl Books<'_> {
+    /// Unposted vouchers that have not been approved or rejected yet, oldest first.
+    pub fn list_pending_approvals(
+        &mut self,
+        payload: CompanyIdInput,
+    ) -> Result<Vec<PendingApproval>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(
+                "SELECT v.id, v.series_id, v.voucher_number, v.date, v.description, v.counterparty,
+                        COALESCE((SELECT SUM(r.debit_cents) FROM voucher_rows r WHERE r.voucher_id = v.id), 0),
+                        v.created_at, v.created_by
+                 FROM vouchers v
+                 WHERE v.company_id = ?1 AND v.posted_at IS NULL
+                   AND NOT EXISTS (SELECT 1 FROM voucher_approvals a WHERE a.voucher_id = v.id)
+                 ORDER BY v.created_at, v.voucher_number",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], |row| {
+                Ok(PendingApproval {
+                    voucher_id: row.get(0)?,
+                    series_id: row.get(1)?,
+                    voucher_number: row.get(2)?,
+                    date: row.get(3)?,
+                    description: row.get(4)?,
+                    counterparty: row.get(5)?,
+                    total: row.get(6)?,
+                    created_at: row.get(7)?,
+                    created_by: row.get(8)?,
+                })
+            })
+            .map_err(map_sql_error)?;
+        let mut pending = Vec::new();
+        for row in rows {
+            pending.push(row.map_err(map_sql_error)?);
+        }
+        Ok(pending)
+    }
+
+    pub fn approve_voucher(
+        &mut self,
+        payload: ApproveVoucherInput,
+    ) -> Result<VoucherApproval, String> {
+        let comment = payload.comment.filter(|comment| !comment.trim().is_empty());
+        decide(self.conn, self.user, &payload.voucher_id, APPROVED, comment)
+    }
+
+    /// Rejects a voucher with a comment for its creator. A rejected voucher can
+    /// no longer be posted; the creator books a new voucher instead.
+    pub fn reject_voucher(
+        &mut self,
+        payload: RejectVoucherInput,
+    ) -> Result<VoucherApproval, String> {
+        if payload.comment.trim().is_empty() {
+            return Err("A comment is required when rejecting a voucher".to_string());
+        }
+        let comment = Some(payload.comment.trim().to_string());
+        decide(self.conn, self.user, &payload.voucher_id, REJECTED, comment)
+    }
+
+    pub fn get_voucher_approval(
+        &mut self,
+        payload: VoucherIdInput,
+    ) -> Result<Option<VoucherApproval>, String> {
+        let conn = &*self.conn;
+        fetch_approval(conn, &payload.voucher_id)
+    }
+}
+
+/// The approval decision for a voucher, if one has been made.
+pub fn approval_decision(conn: &Connection, voucher_id: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT decision FROM voucher_approvals WHERE voucher_id = ?1",
+        [voucher_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(map_sql_error)
+}
+
+fn decide(
+    conn: &mut Connection,
+    user: &str,
+    voucher_id: &VoucherId,
+    decision: &str,
+    comment: Option<String>,
+) -> Result<VoucherApproval, String> {
+    let tx = conn.transaction().map_err(map_sql_error)?;
+    let (company_id, created_by, posted_at): (String, String, Option<String>) = tx
+        .query_row(
+            "SELECT company_id, created_by, posted_at FROM vouchers WHERE id = ?1",
+            [voucher_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(map_sql_error)?;
+    if posted_at.is_some() {
+        return Err("Voucher is already posted".to_string());
+    }
+    if created_by == user {
+        return Err("A voucher must be approved by someone other than its creator".to_string());
+    }
+    if let Some(existing) = approval_decision(&tx, voucher_id)? {
+        return Err(format!("Voucher has already been {}", existing));
+    }
+
+    let approval = VoucherApproval {
+        id: ApprovalId::generate(),
+        voucher_id: voucher_id.clone(),
+        decision: decision.to_string(),
+        comment,
+        decided_at: Utc::now().to_rfc3339(),
+        decided_by: user.to_string(),
+    };
+    tx.execute(
+        "INSERT INTO voucher_approvals (id, voucher_id, decision, comment, decided_at, decided_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            approval.id,
+            approval.voucher_id,
+            approval.decision,
+            approval.comment,
+            approval.decided_at,
+            approval.decided_by
+        ],
+    )
+    .map_err(map_sql_error)?;
+    insert_audit_entry(
+        &tx,
+        user,
+        &company_id,
+        "voucher",
+        voucher_id,
+        if decision == APPROVED {
+            "approve"
+        } else {
+            "reject"
+        },
+        json!({ "comment": &approval.comment, "created_by": created_by }),
+    )?;
+    tx.commit().map_err(map_sql_error)?;
+    Ok(approval)
+}
+
+fn fetch_approval(conn: &Connection, voucher_id: &str) -> Result<Option<VoucherApproval>, String> {
+    conn.query_row(
+        "SELECT id, voucher_id, decision, comment, decided_at, decided_by
+         FROM voucher_approvals WHERE voucher_id = ?1",
+        [voucher_id],
+        |row| {
+            Ok(VoucherApproval {
+                id: row.get(0)?,
+                voucher_id: row.get(1)?,
+                decision: row.get(2)?,
+                comment: row.get(3)?,
+                decided_at: row.get(4)?,
+                decided_by: row.get(5)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(map_sql_error)
+}
@@ -0,0 +1,713 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, Duration, Months, NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::approvals::REJECTED;
+use crate::ids::CompanyId;
+use crate::models::{ArchiveExportResult, ArchiveFile, ExportArchiveInput};
+use crate::money::Money;
+use crate::sie::{self, FIRST_RESULT_ACCOUNT};
+use crate::{insert_audit_entry, map_sql_error, Books};
+
+/// A closed fiscal year as it goes into the archive: posted vouchers only,
+/// and balances for every account that was used.
+pub struct YearBooks {
+    pub company_name: String,
+    pub org_number: Option<String>,
+    pub year_start: NaiveDate,
+    pub year_end: NaiveDate,
+    pub accounts: Vec<YearAccount>,
+    pub vouchers: Vec<YearVoucher>,
+}
+
+pub struct YearAccount {
+    pub id: String,
+    pub number: i64,
+    pub name: String,
+    pub account_type: String,
+    pub opening: Money,
+    pub closing: Money,
+}
+
+pub struct YearVoucher {
+    pub id: String,
+    pub series_code: String,
+    pub voucher_number: i64,
+    pub date: String,
+    pub description: String,
+    pub counterparty: Option<String>,
+    pub created_at: String,
+    pub created_by: String,
+    pub posted_at: String,
+    pub rows: Vec<YearVoucherRow>,
+    pub attachments: Vec<YearAttachment>,
+}
+
+pub struct YearVoucherRow {
+    pub account_id: String,
+    pub account_number: i64,
+    pub description: Option<String>,
+    pub debit: Money,
+    pub credit: Money,
+}
+
+pub struct YearAttachment {
+    pub ref_type: String,
+    pub ref_value: String,
+    pub note: Option<String>,
+    /// Path inside the archive when the attachment is a stored file.
+    pub archived_as: Option<String>,
+}
+
+impl Books<'_> {
+    /// Writes a read-only zip archive of a closed fiscal year that can be read
+    /// without this program, for the seven-year retention in Bokföringslagen:
+    /// SIE 4, the journal (grundbok) and ledger (huvudbok) as HTML, the attached
+    /// files, the audit log and a manifest with SHA-256 hashes of every file.
+    pub fn export_archive(
+        &mut self,
+        payload: ExportArchiveInput,
+    ) -> Result<ArchiveExportResult, String> {
+        let company_id = payload.company_id.clone();
+        let result = self.write_archive(payload)?;
+        self.record_archive_export(&company_id, &result)?;
+        Ok(result)
+    }
+
+    /// The archive of [`export_archive`](Self::export_archive) without the
+    /// audit entry, so it can be written on a read-only connection while other
+    /// commands keep the writer. Record it with
+    /// [`record_archive_export`](Self::record_archive_export).
+    pub fn write_archive(
+        &mut self,
+        payload: ExportArchiveInput,
+    ) -> Result<ArchiveExportResult, String> {
+        let user = self.user;
+        let conn = &*self.conn;
+        let year_start = payload.fiscal_year_start;
+        let company_year_start: NaiveDate = conn
+            .query_row(
+                "SELECT fiscal_year_start FROM companies WHERE id = ?1",
+                [&payload.company_id],
+                |row| row.get(0),
+            )
+            .map_err(map_sql_error)?;
+        if (company_year_start.month(), company_year_start.day()) != (year_start.month(), year_start.day()) {
+            return Err(format!(
+                "{} is not the start of a fiscal year (years start {})",
+                year_start, company_year_start
+            ));
+        }
+        let year_end = year_start
+            .checked_add_months(Months::new(12))
+            .map(|date| date - Duration::days(1))
+            .ok_or("Invalid fiscal year")?;
+        ensure_year_closed(conn, &payload.company_id, year_start, year_end)?;
+
+        let mut books = load_books(conn, &payload.company_id, year_start, year_end)?;
+        let mut attachments: Vec<(String, PathBuf)> = Vec::new();
+        let mut missing: Vec<String> = Vec::new();
+        for voucher in &mut books.vouchers {
+            for (index, attachment) in voucher.attachments.iter_mut().enumerate() {
+                if attachment.ref_type != "Fil" {
+                    continue;
+                }
+                let path = Path::new(&attachment.ref_value);
+                if !path.is_file() {
+                    missing.push(format!(
+                        "{}{}: {}",
+                        voucher.series_code, voucher.voucher_number, attachment.ref_value
+                    ));
+                    continue;
+                }
+                let file_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let name = format!(
+                    "attachments/{}{}/{}-{}",
+                    voucher.series_code,
+                    voucher.voucher_number,
+                    index + 1,
+                    file_name
+                );
+                attachments.push((name.clone(), path.to_path_buf()));
+                attachment.archived_as = Some(name);
+            }
+        }
+        if !missing.is_empty() {
+            return Err(format!(
+                "Attached files are missing, the archive would be incomplete:\n{}",
+                missing.join("\n")
+            ));
+        }
+        let generated = vec![
+            ("sie4.se".to_string(), sie::sie4(&books, user)?),
+            ("journal.html".to_string(), journal_html(&books).into_bytes()),
+            ("ledger.html".to_string(), ledger_html(&books)?.into_bytes()),
+            ("audit_log.csv".to_string(), audit_log_csv(conn, &payload.company_id)?),
+        ];
+        let manifest = json!({
+            "company": &books.company_name,
+            "orgNumber": &books.org_number,
+            "fiscalYearStart": year_start.to_string(),
+            "fiscalYearEnd": year_end.to_string(),
+            "voucherCount": books.vouchers.len(),
+            "createdAt": Utc::now().to_rfc3339(),
+            "createdBy": user,
+        });
+
+        let target = archive_path(&payload.target_path, &books);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let file = File::create_new(&target).map_err(|err| match err.kind() {
+            ErrorKind::AlreadyExists => format!("{} already exists", target.display()),
+            _ => format!("{}: {}", target.display(), err),
+        })?;
+        let manifest_files = write_zip(file, &target, generated, &attachments, manifest)
+            .inspect_err(|_| {
+                // Never leave a partial archive behind.
+                let _ = fs::remove_file(&target);
+            })?;
+        let mut written = File::open(&target).map_err(|err| err.to_string())?;
+        let (_, sha256) =
+            copy_hashed(&mut written, &mut io::sink()).map_err(|err| err.to_string())?;
+
+        Ok(ArchiveExportResult {
+            path: target.to_string_lossy().to_string(),
+            fiscal_year_start: year_start,
+            fiscal_year_end: year_end,
+            voucher_count: books.vouchers.len() as i64,
+            attachment_count: attachments.len() as i64,
+            sha256,
+            files: manifest_files,
+        })
+    }
+
+    /// Logs an archive written by [`write_archive`](Self::write_archive).
+    pub fn record_archive_export(
+        &mut self,
+        company_id: &CompanyId,
+        archive: &ArchiveExportResult,
+    ) -> Result<(), String> {
+        insert_audit_entry(
+            self.conn,
+            self.user,
+            company_id,
+            "fiscal_year",
+            &archive.fiscal_year_start.to_string(),
+            "export_archive",
+            json!({ "path": &archive.path, "sha256": &archive.sha256 }),
+        )
+    }
+}
+
+/// A fiscal year is closed when period locks cover every day of it and all
+/// vouchers in it are posted (or rejected in the approval workflow).
+fn ensure_year_closed(
+    conn: &Connection,
+    company_id: &str,
+    year_start: NaiveDate,
+    year_end: NaiveDate,
+) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT period_start, period_end FROM period_locks
+             WHERE company_id = ?1 AND period_end >= ?2 AND period_start <= ?3
+             ORDER BY period_start",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map(
+            params![company_id, year_start, year_end],
+            |row| Ok((row.get::<_, NaiveDate>(0)?, row.get::<_, NaiveDate>(1)?)),
+        )
+        .map_err(map_sql_error)?;
+    // First day not yet covered by a lock.
+    let mut uncovered = year_start;
+    for row in rows {
+        let (period_start, period_end) = row.map_err(map_sql_error)?;
+        if period_start > uncovered {
+            break;
+        }
+        uncovered = uncovered.max(period_end + Duration::days(1));
+    }
+    if uncovered <= year_end {
+        return Err(format!(
+            "The fiscal year is not closed: {} onwards is not locked",
+            uncovered
+        ));
+    }
+
+    let unposted: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM vouchers v
+             WHERE v.company_id = ?1 AND v.date BETWEEN ?2 AND ?3 AND v.posted_at IS NULL
+               AND NOT EXISTS (SELECT 1 FROM voucher_approvals a
+                               WHERE a.voucher_id = v.id AND a.decision = ?4)",
+            params![company_id, year_start.to_string(), year_end.to_string(), REJECTED],
+            |row| row.get(0),
+        )
+        .map_err(map_sql_error)?;
+    if unposted > 0 {
+        return Err(format!(
+            "The fiscal year is not closed: {} vouchers are not posted",
+            unposted
+        ));
+    }
+    Ok(())
+}
+
+fn load_books(
+    conn: &Connection,
+    company_id: &str,
+    year_start: NaiveDate,
+    year_end: NaiveDate,
+) -> Result<YearBooks, String> {
+    let (company_name, org_number): (String, Option<String>) = conn
+        .query_row(
+            "SELECT name, org_number FROM companies WHERE id = ?1",
+            [company_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(map_sql_error)?;
+    let start = year_start.to_string();
+    let end = year_end.to_string();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT a.id, a.number, a.name, a.type,
+                    COALESCE(SUM(CASE WHEN v.date < ?2 THEN r.debit_cents - r.credit_cents END), 0),
+                    COALESCE(SUM(r.debit_cents - r.credit_cents), 0),
+                    COUNT(CASE WHEN v.date >= ?2 THEN 1 END)
+             FROM accounts a
+             LEFT JOIN voucher_rows r ON r.account_id = a.id
+               AND r.voucher_id IN (SELECT id FROM vouchers
+                                    WHERE company_id = ?1 AND posted_at IS NOT NULL AND date <= ?3)
+             LEFT JOIN vouchers v ON v.id = r.voucher_id
+             WHERE a.company_id = ?1
+             GROUP BY a.id
+             ORDER BY a.number",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map(params![company_id, &start, &end], |row| {
+            Ok((
+                YearAccount {
+                    id: row.get(0)?,
+                    number: row.get(1)?,
+                    name: row.get(2)?,
+                    account_type: row.get(3)?,
+                    opening: row.get(4)?,
+                    closing: row.get(5)?,
+                },
+                row.get::<_, i64>(6)?,
+            ))
+        })
+        .map_err(map_sql_error)?;
+    let mut accounts = Vec::new();
+    for row in rows {
+        let (mut account, rows_in_year) = row.map_err(map_sql_error)?;
+        if account.number >= FIRST_RESULT_ACCOUNT {
+            // Result accounts start every year at zero.
+            account.closing = account
+                .closing
+                .checked_sub(account.opening)
+                .ok_or("Amount is too large")?;
+            account.opening = Money::ZERO;
+        }
+        if rows_in_year > 0 || !account.opening.is_zero() || !account.closing.is_zero() {
+            accounts.push(account);
+        }
+    }
+
+    let mut rows_by_voucher: HashMap<String, Vec<YearVoucherRow>> = HashMap::new();
+    let mut stmt = conn
+        .prepare(
+            "SELECT r.voucher_id, r.account_id, a.number, r.description, r.debit_cents, r.credit_cents
+             FROM voucher_rows r
+             JOIN vouchers v ON v.id = r.voucher_id
+             JOIN accounts a ON a.id = r.account_id
+             WHERE v.company_id = ?1 AND v.posted_at IS NOT NULL AND v.date BETWEEN ?2 AND ?3
+             ORDER BY r.rowid",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map(params![company_id, &start, &end], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                YearVoucherRow {
+                    account_id: row.get(1)?,
+                    account_number: row.get(2)?,
+                    description: row.get(3)?,
+                    debit: row.get(4)?,
+                    credit: row.get(5)?,
+                },
+            ))
+        })
+        .map_err(map_sql_error)?;
+    for row in rows {
+        let (voucher_id, row) = row.map_err(map_sql_error)?;
+        rows_by_voucher.entry(voucher_id).or_default().push(row);
+    }
+
+    let mut attachments_by_voucher: HashMap<String, Vec<YearAttachment>> = HashMap::new();
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.voucher_id, t.ref_type, t.ref_value, t.note
+             FROM attachments t
+             JOIN vouchers v ON v.id = t.voucher_id
+             WHERE v.company_id = ?1 AND v.posted_at IS NOT NULL AND v.date BETWEEN ?2 AND ?3
+             ORDER BY t.created_at",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map(params![company_id, &start, &end], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                YearAttachment {
+                    ref_type: row.get(1)?,
+                    ref_value: row.get(2)?,
+                    note: row.get(3)?,
+                    archived_as: None,
+                },
+            ))
+        })
+        .map_err(map_sql_error)?;
+    for row in rows {
+        let (voucher_id, attachment) = row.map_err(map_sql_error)?;
+        attachments_by_voucher
+            .entry(voucher_id)
+            .or_default()
+            .push(attachment);
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT v.id, s.code, v.voucher_number, v.date, v.description, v.counterparty,
+                    v.created_at, v.created_by, v.posted_at
+             FROM vouchers v
+             JOIN voucher_series s ON s.id = v.series_id
+             WHERE v.company_id = ?1 AND v.posted_at IS NOT NULL AND v.date BETWEEN ?2 AND ?3
+             ORDER BY v.date, s.code, v.voucher_number",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map(params![company_id, &start, &end], |row| {
+            Ok(YearVoucher {
+                id: row.get(0)?,
+                series_code: row.get(1)?,
+                voucher_number: row.get(2)?,
+                date: row.get(3)?,
+                description: row.get(4)?,
+                counterparty: row.get(5)?,
+                created_at: row.get(6)?,
+                created_by: row.get(7)?,
+                posted_at: row.get(8)?,
+                rows: Vec::new(),
+                attachments: Vec::new(),
+            })
+        })
+        .map_err(map_sql_error)?;
+    let mut vouchers = Vec::new();
+    for row in rows {
+        let mut voucher = row.map_err(map_sql_error)?;
+        voucher.rows = rows_by_voucher.remove(&voucher.id).unwrap_or_default();
+        voucher.attachments = attachments_by_voucher.remove(&voucher.id).unwrap_or_default();
+        vouchers.push(voucher);
+    }
+
+    Ok(YearBooks {
+        company_name,
+        org_number,
+        year_start,
+        year_end,
+        accounts,
+        vouchers,
+    })
+}
+
+/// Grundbok: all vouchers in registration order with their rows.
+fn journal_html(books: &YearBooks) -> String {
+    let names: HashMap<&str, &str> = books
+        .accounts
+        .iter()
+        .map(|account| (account.id.as_str(), account.name.as_str()))
+        .collect();
+    let mut body = String::new();
+    for voucher in &books.vouchers {
+        body.push_str(&format!(
+            "<h2>{}{} {} {}</h2>\n<p>{}Registrerad {} av {}, bokförd {}</p>\n",
+            escape(&voucher.series_code),
+            voucher.voucher_number,
+            escape(&voucher.date),
+            escape(&voucher.description),
+            voucher
+                .counterparty
+                .as_deref()
+                .map(|counterparty| format!("Motpart: {}. ", escape(counterparty)))
+                .unwrap_or_default(),
+            escape(&voucher.created_at),
+            escape(&voucher.created_by),
+            escape(&voucher.posted_at)
+        ));
+        body.push_str(
+            "<table>\n<tr><th>Konto</th><th>Benämning</th><th>Text</th><th>Debet</th><th>Kredit</th></tr>\n",
+        );
+        for row in &voucher.rows {
+            body.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"amount\">{}</td><td class=\"amount\">{}</td></tr>\n",
+                row.account_number,
+                escape(names.get(row.account_id.as_str()).copied().unwrap_or_default()),
+                escape(row.description.as_deref().unwrap_or_default()),
+                format_nonzero(row.debit),
+                format_nonzero(row.credit)
+            ));
+        }
+        body.push_str("</table>\n");
+        if !voucher.attachments.is_empty() {
+            body.push_str("<ul>\n");
+            for attachment in &voucher.attachments {
+                let label = escape(attachment.note.as_deref().unwrap_or(&attachment.ref_value));
+                let item = match &attachment.archived_as {
+                    Some(path) => format!("<a href=\"{}\">{}</a>", escape(path), label),
+                    None if attachment.ref_type == "URL" => {
+                        format!("<a href=\"{}\">{}</a>", escape(&attachment.ref_value), label)
+                    }
+                    None => format!("{} ({})", label, escape(&attachment.ref_value)),
+                };
+                body.push_str(&format!("<li>{}</li>\n", item));
+            }
+            body.push_str("</ul>\n");
+        }
+    }
+    html_page(books, "Grundbok", &body)
+}
+
+/// Huvudbok: every account with its opening balance, the year's rows and the
+/// running balance.
+fn ledger_html(books: &YearBooks) -> Result<String, String> {
+    let mut rows_by_account: HashMap<&str, Vec<(&YearVoucher, &YearVoucherRow)>> = HashMap::new();
+    for voucher in &books.vouchers {
+        for row in &voucher.rows {
+            rows_by_account
+                .entry(row.account_id.as_str())
+                .or_default()
+                .push((voucher, row));
+        }
+    }
+    let mut body = String::new();
+    for account in &books.accounts {
+        body.push_str(&format!(
+            "<h2>{} {}</h2>\n<table>\n<tr><th>Datum</th><th>Ver</th><th>Text</th><th>Debet</th><th>Kredit</th><th>Saldo</th></tr>\n",
+            account.number,
+            escape(&account.name)
+        ));
+        let mut balance = account.opening;
+        body.push_str(&format!(
+            "<tr><td></td><td></td><td>Ingående balans</td><td></td><td></td><td class=\"amount\">{}</td></tr>\n",
+            balance.to_swedish()
+        ));
+        for (voucher, row) in rows_by_account.remove(account.id.as_str()).unwrap_or_default() {
+            balance = balance.checked_post(row.debit, row.credit)?;
+            body.push_str(&format!(
+                "<tr><td>{}</td><td>{}{}</td><td>{}</td><td class=\"amount\">{}</td><td class=\"amount\">{}</td><td class=\"amount\">{}</td></tr>\n",
+                escape(&voucher.date),
+                escape(&voucher.series_code),
+                voucher.voucher_number,
+                escape(row.description.as_deref().unwrap_or(&voucher.description)),
+                format_nonzero(row.debit),
+                format_nonzero(row.credit),
+                balance.to_swedish()
+            ));
+        }
+        body.push_str(&format!(
+            "<tr><td></td><td></td><td>Utgående saldo</td><td></td><td></td><td class=\"amount\">{}</td></tr>\n</table>\n",
+            balance.to_swedish()
+        ));
+    }
+    Ok(html_page(books, "Huvudbok", &body))
+}
+
+fn html_page(books: &YearBooks, title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"sv\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title} {start} – {end}</title>\n<style>\nbody {{ font-family: sans-serif; }}\ntable {{ border-collapse: collapse; margin-bottom: 1em; }}\nth, td {{ border: 1px solid #999; padding: 2px 6px; }}\n.amount {{ text-align: right; white-space: nowrap; }}\n</style>\n</head>\n<body>\n<h1>{title} {company}{org}</h1>\n<p>Räkenskapsår {start} – {end}</p>\n{body}</body>\n</html>\n",
+        title = title,
+        company = escape(&books.company_name),
+        org = books
+            .org_number
+            .as_deref()
+            .map(|org_number| format!(" ({})", escape(org_number)))
+            .unwrap_or_default(),
+        start = books.year_start,
+        end = books.year_end,
+        body = body
+    )
+}
+
+fn audit_log_csv(conn: &Connection, company_id: &str) -> Result<Vec<u8>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT created_at, created_by, entity_type, entity_id, action, payload_json
+             FROM audit_log WHERE company_id = ?1 ORDER BY created_at",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([company_id], |row| {
+            Ok([
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+            ])
+        })
+        .map_err(map_sql_error)?;
+    let mut csv = String::from("created_at,created_by,entity_type,entity_id,action,payload_json\n");
+    for row in rows {
+        let fields = row.map_err(map_sql_error)?;
+        let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&line.join(","));
+        csv.push('\n');
+    }
+    Ok(csv.into_bytes())
+}
+
+/// Writes the `generated` files into `file` at `target`, then streams each
+/// attachment into the zip and hashes it while copying, so no attachment is
+/// held in memory. `manifest` gets the list of files and is written last,
+/// followed by `SHA256SUMS`.
+fn write_zip(
+    file: File,
+    target: &Path,
+    generated: Vec<(String, Vec<u8>)>,
+    attachments: &[(String, PathBuf)],
+    mut manifest: serde_json::Value,
+) -> Result<Vec<ArchiveFile>, String> {
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .unix_permissions(0o444);
+    let mut files = Vec::new();
+    for (name, data) in generated {
+        zip.start_file(name.as_str(), options)
+            .map_err(|err| err.to_string())?;
+        zip.write_all(&data).map_err(|err| err.to_string())?;
+        files.push(ArchiveFile {
+            path: name,
+            size_bytes: data.len() as i64,
+            sha256: sha256_hex(&data),
+        });
+    }
+    for (name, path) in attachments {
+        zip.start_file(name.as_str(), options)
+            .map_err(|err| err.to_string())?;
+        let (size_bytes, sha256) = File::open(path)
+            .and_then(|mut attachment| copy_hashed(&mut attachment, &mut zip))
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        files.push(ArchiveFile {
+            path: name.clone(),
+            size_bytes,
+            sha256,
+        });
+    }
+
+    let checksums: String = files
+        .iter()
+        .map(|file| format!("{}  {}\n", file.sha256, file.path))
+        .collect();
+    manifest["files"] = json!(&files);
+    zip.start_file("manifest.json", options)
+        .map_err(|err| err.to_string())?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|err| err.to_string())?;
+    zip.start_file("SHA256SUMS", options)
+        .map_err(|err| err.to_string())?;
+    zip.write_all(checksums.as_bytes()).map_err(|err| err.to_string())?;
+
+    zip.finish().map_err(|err| err.to_string())?;
+    let mut permissions = fs::metadata(target)
+        .map_err(|err| err.to_string())?
+        .permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(target, permissions).map_err(|err| err.to_string())?;
+    Ok(files)
+}
+
+/// `target_path` is either the zip file or a directory to put it in.
+fn archive_path(target_path: &str, books: &YearBooks) -> PathBuf {
+    let path = PathBuf::from(target_path);
+    if path.extension().and_then(|ext| ext.to_str()) == Some("zip") {
+        return path;
+    }
+    let org_number = books
+        .org_number
+        .as_deref()
+        .map(|org_number| org_number.replace('-', ""))
+        .unwrap_or_else(|| "bokforing".to_string());
+    path.join(format!(
+        "arkiv-{}-{}-{}.zip",
+        org_number,
+        books.year_start.format("%Y%m%d"),
+        books.year_end.format("%Y%m%d")
+    ))
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+/// Copies `reader` into `writer` and returns the number of bytes and their
+/// SHA-256.
+fn copy_hashed(reader: &mut impl Read, writer: &mut impl Write) -> io::Result<(i64, String)> {
+    let mut hasher = Sha256::new();
+    let mut size_bytes = 0;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read])?;
+        size_bytes += read as i64;
+    }
+    Ok((size_bytes, hex(&hasher.finalize())))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Swedish formatting, with zero shown as an empty cell.
+fn format_nonzero(amount: Money) -> String {
+    if amount.is_zero() {
+        String::new()
+    } else {
+        amount.to_swedish()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
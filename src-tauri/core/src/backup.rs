@@ -0,0 +1,512 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rusqlite::backup::Backup;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::db::{self, DATABASE_FILE, SCHEMA_VERSION};
+use crate::models::{
+    BackupFile, BackupResult, BackupSettings, RestoreBackupInput, RestoreResult,
+    UpdateBackupSettingsInput,
+};
+use crate::{map_error, map_sql_error, Books};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const DOCUMENTS_DIR: &str = "documents";
+/// Automatic backups are named with this prefix; only they are pruned.
+const AUTO_BACKUP_PREFIX: &str = "bokforing-auto-";
+const MANUAL_BACKUP_PREFIX: &str = "bokforing-backup-";
+
+/// Written as `manifest.json` at the root of every backup archive.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    created_at: String,
+    schema_version: i64,
+    encrypted: bool,
+    attachment_count: i64,
+}
+
+impl Books<'_> {
+    pub fn get_backup_settings(&mut self) -> Result<BackupSettings, String> {
+        fetch_settings(self.conn)
+    }
+
+    pub fn update_backup_settings(
+        &mut self,
+        payload: UpdateBackupSettingsInput,
+    ) -> Result<BackupSettings, String> {
+        if payload.interval_hours < 1 {
+            return Err("Backup interval must be at least one hour".to_string());
+        }
+        if payload.retention_count < 1 {
+            return Err("At least one automatic backup must be kept".to_string());
+        }
+        let directory = payload
+            .directory
+            .map(|directory| directory.trim().to_string())
+            .filter(|directory| !directory.is_empty());
+        self.conn
+            .execute(
+                "INSERT INTO backup_settings (id, enabled, interval_hours, retention_count, directory, include_attachments)
+                 VALUES (1, ?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(id) DO UPDATE SET enabled = ?1, interval_hours = ?2, retention_count = ?3,
+                   directory = ?4, include_attachments = ?5",
+                params![
+                    payload.enabled as i64,
+                    payload.interval_hours,
+                    payload.retention_count,
+                    directory,
+                    payload.include_attachments as i64
+                ],
+            )
+            .map_err(map_sql_error)?;
+        fetch_settings(self.conn)
+    }
+}
+
+/// Where backups go unless another directory is given or configured.
+pub fn default_backup_dir(database_path: &Path) -> PathBuf {
+    database_path.with_file_name("backups")
+}
+
+/// A new, timestamped backup file name in `directory`. Backups made within
+/// the same second get a counter, as in `bokforing-backup-20240101-120000-2.zip`.
+pub fn backup_path(directory: &Path, automatic: bool) -> PathBuf {
+    let prefix = if automatic {
+        AUTO_BACKUP_PREFIX
+    } else {
+        MANUAL_BACKUP_PREFIX
+    };
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut path = directory.join(format!("{}{}.zip", prefix, timestamp));
+    let mut counter = 2;
+    while path.exists() {
+        path = directory.join(format!("{}{}-{}.zip", prefix, timestamp, counter));
+        counter += 1;
+    }
+    path
+}
+
+pub fn list_backups(directory: &Path) -> Result<Vec<BackupFile>, String> {
+    let mut backups = Vec::new();
+    for path in backup_files(directory, "")? {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        backups.push(BackupFile {
+            path: path.to_string_lossy().to_string(),
+            automatic: file_name.starts_with(AUTO_BACKUP_PREFIX),
+            file_name,
+            size_bytes: file_size(&path)?,
+        });
+    }
+    Ok(backups)
+}
+
+/// Whether an automatic backup should be made now.
+pub fn backup_due(settings: &BackupSettings) -> Result<bool, String> {
+    if !settings.enabled {
+        return Ok(false);
+    }
+    let Some(last_backup_at) = &settings.last_backup_at else {
+        return Ok(true);
+    };
+    let last_backup_at =
+        DateTime::parse_from_rfc3339(last_backup_at).map_err(|err| err.to_string())?;
+    Ok(Utc::now().signed_duration_since(last_backup_at)
+        >= chrono::Duration::hours(settings.interval_hours))
+}
+
+/// Records the outcome of an automatic backup in `backup_settings`; failures
+/// are shown in the settings and retried at the next check.
+pub fn record_backup_outcome(
+    conn: &Connection,
+    outcome: &Result<BackupResult, String>,
+) -> Result<(), String> {
+    match outcome {
+        Ok(backup) => conn.execute(
+            "UPDATE backup_settings SET last_backup_at = ?1, last_error = NULL WHERE id = 1",
+            [&backup.created_at],
+        ),
+        Err(err) => conn.execute(
+            "UPDATE backup_settings SET last_error = ?1 WHERE id = 1",
+            [err],
+        ),
+    }
+    .map_err(map_sql_error)?;
+    Ok(())
+}
+
+/// A consistent copy of the database next to the backup target, made by
+/// [`snapshot_database`] and packed by [`write_backup_archive`].
+#[derive(Debug)]
+pub struct Snapshot {
+    path: PathBuf,
+    created_at: String,
+    encrypted: bool,
+}
+
+/// First step of a backup: a consistent copy of the database, taken with
+/// SQLite's online backup API. `conn` is only read from, so a read-only
+/// connection will do and writers are not held up. `passphrase` is the key
+/// of the open database, if it is encrypted.
+pub fn snapshot_database(
+    conn: &Connection,
+    passphrase: Option<&str>,
+    target: &Path,
+) -> Result<Snapshot, String> {
+    if let Some(directory) = target.parent() {
+        fs::create_dir_all(directory).map_err(|err| err.to_string())?;
+    }
+    let created_at = Utc::now().to_rfc3339();
+
+    // SQLCipher only backs up into a database with the same key.
+    let path = target.with_extension("sqlite.tmp");
+    if target.exists() || path.exists() {
+        return Err(format!("{} already exists", target.display()));
+    }
+    let copied = (|| {
+        let mut destination = Connection::open(&path)?;
+        if let Some(passphrase) = passphrase {
+            destination.pragma_update(None, "key", passphrase)?;
+        }
+        let backup = Backup::new(conn, &mut destination)?;
+        backup.run_to_completion(256, Duration::ZERO, None)
+    })();
+    if let Err(err) = copied {
+        // The copy is closed by now; never leave a partial one behind.
+        let _ = fs::remove_file(&path);
+        return Err(map_sql_error(err));
+    }
+    Ok(Snapshot {
+        path,
+        created_at,
+        encrypted: passphrase.is_some(),
+    })
+}
+
+/// Packs a snapshot and optionally the stored documents into the archive at
+/// `target`. Needs no database connection; the snapshot is removed after.
+pub fn write_backup_archive(
+    snapshot: Snapshot,
+    documents_dir: &Path,
+    target: &Path,
+    include_attachments: bool,
+) -> Result<BackupResult, String> {
+    let documents = if include_attachments {
+        let mut documents = Vec::new();
+        collect_files(documents_dir, &mut documents)?;
+        documents
+    } else {
+        Vec::new()
+    };
+    let manifest = Manifest {
+        created_at: snapshot.created_at.clone(),
+        schema_version: SCHEMA_VERSION,
+        encrypted: snapshot.encrypted,
+        attachment_count: documents.len() as i64,
+    };
+    let archived = write_archive(target, &manifest, &snapshot.path, documents_dir, &documents);
+    fs::remove_file(&snapshot.path).map_err(|err| err.to_string())?;
+    archived?;
+
+    Ok(BackupResult {
+        path: target.to_string_lossy().to_string(),
+        created_at: snapshot.created_at,
+        size_bytes: file_size(target)?,
+        encrypted: manifest.encrypted,
+        attachment_count: manifest.attachment_count,
+    })
+}
+
+/// Replaces the database at `database_path`, open as `conn`, with the one in
+/// a backup archive. The archive is verified with `PRAGMA integrity_check`
+/// and its schema version before the swap, and the current database is kept
+/// next to it as `bokforing.before-restore.sqlite`. An encrypted backup is
+/// opened with the payload's passphrase or else `current_passphrase`.
+///
+/// Returns the passphrase of the restored database along with the result.
+pub fn restore_backup(
+    conn: &mut Connection,
+    database_path: &Path,
+    documents_dir: &Path,
+    payload: RestoreBackupInput,
+    current_passphrase: Option<String>,
+) -> Result<(RestoreResult, Option<String>), String> {
+    let mut archive = ZipArchive::new(File::open(&payload.path).map_err(|err| err.to_string())?)
+        .map_err(|err| err.to_string())?;
+    let manifest: Manifest = serde_json::from_reader(
+        archive
+            .by_name(MANIFEST_FILE)
+            .map_err(|_| "Not a backup archive: manifest.json is missing")?,
+    )
+    .map_err(|err| err.to_string())?;
+    if manifest.schema_version > SCHEMA_VERSION {
+        return Err(format!(
+            "The backup has schema version {} but this version of the app supports up to {}",
+            manifest.schema_version, SCHEMA_VERSION
+        ));
+    }
+    let passphrase = if manifest.encrypted {
+        Some(
+            payload
+                .passphrase
+                .filter(|passphrase| !passphrase.is_empty())
+                .or_else(|| current_passphrase.clone())
+                .ok_or("The backup is encrypted; enter its passphrase")?,
+        )
+    } else {
+        None
+    };
+
+    let restore_path = database_path.with_extension("restore.sqlite");
+    {
+        let mut entry = archive
+            .by_name(DATABASE_FILE)
+            .map_err(|_| "Not a backup archive: the database is missing")?;
+        let mut file = File::create(&restore_path).map_err(|err| err.to_string())?;
+        io::copy(&mut entry, &mut file).map_err(|err| err.to_string())?;
+    }
+    if let Err(err) = verify_database(&restore_path, passphrase.as_deref()) {
+        fs::remove_file(&restore_path).map_err(|err| err.to_string())?;
+        return Err(err);
+    }
+
+    let previous_path = database_path.with_extension("before-restore.sqlite");
+    db::swap_database(
+        conn,
+        database_path,
+        &restore_path,
+        Some(&previous_path),
+        passphrase.as_deref(),
+        current_passphrase.as_deref(),
+    )
+    .map_err(map_error)?;
+    db::migrate(conn).map_err(map_error)?;
+    let attachment_count = extract_documents(&mut archive, documents_dir)?;
+
+    let result = RestoreResult {
+        restored_from: payload.path,
+        backup_created_at: manifest.created_at,
+        schema_version: manifest.schema_version,
+        attachment_count,
+        previous_database_path: previous_path.to_string_lossy().to_string(),
+    };
+    Ok((result, passphrase))
+}
+
+fn write_archive(
+    target: &Path,
+    manifest: &Manifest,
+    database: &Path,
+    documents_dir: &Path,
+    documents: &[PathBuf],
+) -> Result<(), String> {
+    // Never overwrite an existing backup.
+    let file = File::create_new(target)
+        .map_err(|err| format!("{}: {}", target.display(), err))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file(MANIFEST_FILE, options)
+        .map_err(|err| err.to_string())?;
+    let manifest_json = serde_json::to_vec_pretty(manifest).map_err(|err| err.to_string())?;
+    zip.write_all(&manifest_json)
+        .map_err(|err| err.to_string())?;
+
+    zip.start_file(DATABASE_FILE, options)
+        .map_err(|err| err.to_string())?;
+    let mut database = File::open(database).map_err(|err| err.to_string())?;
+    io::copy(&mut database, &mut zip).map_err(|err| err.to_string())?;
+
+    for path in documents {
+        let relative = path
+            .strip_prefix(documents_dir)
+            .map_err(|err| err.to_string())?;
+        let name = Path::new(DOCUMENTS_DIR).join(relative);
+        // Zip entry names always use forward slashes.
+        let name = name
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        zip.start_file(name, options)
+            .map_err(|err| err.to_string())?;
+        let mut document = File::open(path).map_err(|err| err.to_string())?;
+        io::copy(&mut document, &mut zip).map_err(|err| err.to_string())?;
+    }
+    zip.finish().map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Checks that a restored file opens with the given key, passes
+/// `PRAGMA integrity_check` and has a schema this app can migrate.
+fn verify_database(path: &Path, passphrase: Option<&str>) -> Result<(), String> {
+    let conn = db::open_connection(path, passphrase).map_err(map_error)?;
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(map_sql_error)?;
+    if integrity != "ok" {
+        return Err(format!("The backup database is damaged: {}", integrity));
+    }
+    let schema_version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(map_sql_error)?;
+    if schema_version > SCHEMA_VERSION {
+        return Err(format!(
+            "The backup database has schema version {} but this version of the app supports up to {}",
+            schema_version, SCHEMA_VERSION
+        ));
+    }
+    let companies: Option<String> = conn
+        .query_row(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'companies'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(map_sql_error)?;
+    if companies.is_none() {
+        return Err("The backup does not contain a bookkeeping database".to_string());
+    }
+    Ok(())
+}
+
+/// Writes the archived documents into the documents directory, keeping files
+/// that are not in the archive.
+fn extract_documents<R: io::Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    documents_dir: &Path,
+) -> Result<i64, String> {
+    let mut count = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(|err| err.to_string())?;
+        if entry.is_dir() {
+            continue;
+        }
+        // `enclosed_name` rejects absolute paths and `..` components.
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        let Ok(relative) = name.strip_prefix(DOCUMENTS_DIR) else {
+            continue;
+        };
+        let target = documents_dir.join(relative);
+        if let Some(directory) = target.parent() {
+            fs::create_dir_all(directory).map_err(|err| err.to_string())?;
+        }
+        let mut file = File::create(&target).map_err(|err| err.to_string())?;
+        io::copy(&mut entry, &mut file).map_err(|err| err.to_string())?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Deletes the oldest automatic backups so that `keep` remain.
+pub fn prune_backups(directory: &Path, keep: i64) -> Result<(), String> {
+    let backups = backup_files(directory, AUTO_BACKUP_PREFIX)?;
+    for path in backups.iter().skip(keep.max(1) as usize) {
+        fs::remove_file(path).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+/// Backup archives in `directory` whose name starts with `prefix`, newest
+/// first. The names contain a sortable timestamp.
+fn backup_files(directory: &Path, prefix: &str) -> Result<Vec<PathBuf>, String> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.to_string()),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|err| err.to_string())?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let is_backup =
+            name.starts_with(AUTO_BACKUP_PREFIX) || name.starts_with(MANUAL_BACKUP_PREFIX);
+        if is_backup && name.starts_with(prefix) && name.ends_with(".zip") {
+            files.push((name, path));
+        }
+    }
+    // Automatic and manual backups share the timestamp format, so sort on it.
+    files.sort_by(|(a, _), (b, _)| backup_timestamp(b).cmp(backup_timestamp(a)));
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// The timestamp and counter of a backup name. Without `.zip`, a name with
+/// a counter sorts after the one without.
+fn backup_timestamp(name: &str) -> &str {
+    name.trim_start_matches(AUTO_BACKUP_PREFIX)
+        .trim_start_matches(MANUAL_BACKUP_PREFIX)
+        .trim_end_matches(".zip")
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.to_string()),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        paths.push(entry.map_err(|err| err.to_string())?.path());
+    }
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn file_size(path: &Path) -> Result<i64, String> {
+    fs::metadata(path)
+        .map(|metadata| metadata.len() as i64)
+        .map_err(|err| err.to_string())
+}
+
+pub fn fetch_settings(conn: &Connection) -> Result<BackupSettings, String> {
+    let settings = conn
+        .query_row(
+            "SELECT enabled, interval_hours, retention_count, directory, include_attachments, last_backup_at, last_error
+             FROM backup_settings WHERE id = 1",
+            [],
+            |row| {
+                Ok(BackupSettings {
+                    enabled: row.get::<_, i64>(0)? == 1,
+                    interval_hours: row.get(1)?,
+                    retention_count: row.get(2)?,
+                    directory: row.get(3)?,
+                    include_attachments: row.get::<_, i64>(4)? == 1,
+                    last_backup_at: row.get(5)?,
+                    last_error: row.get(6)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(map_sql_error)?;
+    Ok(settings.unwrap_or(BackupSettings {
+        enabled: false,
+        interval_hours: 24,
+        retention_count: 7,
+        directory: None,
+        include_attachments: true,
+        last_backup_at: None,
+        last_error: None,
+    }))
+}
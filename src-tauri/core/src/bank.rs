@@ -0,0 +1,903 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{NaiveDate, Utc};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde_json::json;
+
+use crate::models::{
+    BankImportResult, BankMatchSuggestions, BankRule, BankTransaction, BankTransactionIdInput,
+    BankVoucherMatch, CompanyIdInput, CreateVoucherFromBankRuleInput, CreateVoucherInput,
+    CreateVoucherRowInput, ImportBankStatementInput, ListBankTransactionsInput,
+    MatchBankTransactionInput, UpsertBankRuleInput, Voucher,
+};
+use crate::ids::{AccountId, BankRuleId, BankTransactionId, CompanyId, VoucherId};
+use crate::money::Money;
+use crate::{ensure_company, insert_audit_entry, insert_voucher, map_sql_error, Books};
+
+/// BAS account used for the business bank account.
+pub const BANK_ACCOUNT_NUMBER: i64 = 1930;
+
+/// How many days a voucher date may differ from the bank booking date and
+/// still be suggested as a match.
+const MATCH_WINDOW_DAYS: i64 = 7;
+
+pub const BANK_TRANSACTION_COLUMNS: &str = "id, company_id, account_id, booking_date, value_date, amount_cents, description, reference, counterparty, source_format, imported_at, imported_by, voucher_id, matched_at";
+
+const DATE_HEADERS: &[&str] = &[
+    "bokföringsdatum",
+    "bokföringsdag",
+    "bokfdag",
+    "bokförd",
+    "reskontradatum",
+    "transaktionsdatum",
+    "transdag",
+    "datum",
+];
+const VALUE_DATE_HEADERS: &[&str] = &["valutadatum", "valutadag"];
+const AMOUNT_HEADERS: &[&str] = &["belopp", "belopp sek", "amount"];
+const TEXT_HEADERS: &[&str] = &[
+    "text/mottagare",
+    "beskrivning",
+    "transaktion",
+    "rubrik",
+    "text",
+    "meddelande",
+];
+const REFERENCE_HEADERS: &[&str] = &["referens", "verifikationsnummer", "ocr"];
+const COUNTERPARTY_HEADERS: &[&str] = &["namn", "mottagare", "avsändare"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StatementFormat {
+    Csv,
+    Camt053,
+}
+
+impl StatementFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            StatementFormat::Csv => "csv",
+            StatementFormat::Camt053 => "camt053",
+        }
+    }
+}
+
+/// A statement line as read from the bank file, before it is stored.
+#[derive(Debug, Default)]
+struct StatementLine {
+    booking_date: NaiveDate,
+    value_date: Option<NaiveDate>,
+    amount: Money,
+    description: String,
+    reference: Option<String>,
+    counterparty: Option<String>,
+    external_id: Option<String>,
+}
+
+impl Books<'_> {
+    pub fn import_bank_statement(
+        &mut self,
+        payload: ImportBankStatementInput,
+    ) -> Result<BankImportResult, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let bytes = std::fs::read(&payload.file_path).map_err(|err| err.to_string())?;
+        let content = decode_statement(&bytes);
+        let format = detect_format(payload.format.as_deref(), &payload.file_path, &content)?;
+        let lines = match format {
+            StatementFormat::Csv => parse_bank_csv(&content)?,
+            StatementFormat::Camt053 => parse_camt053(&content)?,
+        };
+        if lines.is_empty() {
+            return Err("No transactions found in statement".to_string());
+        }
+
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let account_id = bank_account_id(&tx, &payload.company_id)?;
+        let now = Utc::now().to_rfc3339();
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        let mut imported = Vec::new();
+        let mut skipped_duplicates = 0;
+        for line in lines {
+            let fingerprint = match &line.external_id {
+                Some(external_id) => format!("ref:{}", external_id),
+                None => {
+                    // Identical lines on the same day are legitimate, so the position
+                    // among identical lines is part of the fingerprint.
+                    let key = format!("{}|{}|{}", line.booking_date, line.amount.cents(), line.description);
+                    let occurrence = occurrences.entry(key.clone()).or_insert(0);
+                    *occurrence += 1;
+                    format!("{}|{}", key, occurrence)
+                }
+            };
+
+            let id = BankTransactionId::generate();
+            let inserted = tx
+                .execute(
+                    "INSERT OR IGNORE INTO bank_transactions (id, company_id, account_id, booking_date, value_date, amount_cents, description, reference, counterparty, fingerprint, source_format, imported_at, imported_by)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                    params![
+                        id,
+                        &payload.company_id,
+                        &account_id,
+                        line.booking_date,
+                        line.value_date,
+                        line.amount,
+                        &line.description,
+                        &line.reference,
+                        &line.counterparty,
+                        fingerprint,
+                        format.as_str(),
+                        now,
+                        user
+                    ],
+                )
+                .map_err(map_sql_error)?;
+            if inserted == 0 {
+                skipped_duplicates += 1;
+                continue;
+            }
+
+            imported.push(BankTransaction {
+                id,
+                company_id: payload.company_id.clone(),
+                account_id: account_id.clone(),
+                booking_date: line.booking_date,
+                value_date: line.value_date,
+                amount: line.amount,
+                description: line.description,
+                reference: line.reference,
+                counterparty: line.counterparty,
+                source_format: format.as_str().to_string(),
+                imported_at: now.clone(),
+                imported_by: user.to_string(),
+                voucher_id: None,
+                matched_at: None,
+            });
+        }
+
+        insert_audit_entry(
+            &tx,
+            user,
+            &payload.company_id,
+            "bank_statement",
+            &account_id,
+            "import",
+            json!({
+                "file_path": &payload.file_path,
+                "format": format.as_str(),
+                "imported": imported.len(),
+                "skipped_duplicates": skipped_duplicates
+            }),
+        )?;
+        tx.commit().map_err(map_sql_error)?;
+
+        Ok(BankImportResult {
+            imported,
+            skipped_duplicates,
+        })
+    }
+
+    pub fn list_bank_transactions(
+        &mut self,
+        payload: ListBankTransactionsInput,
+    ) -> Result<Vec<BankTransaction>, String> {
+        let conn = &*self.conn;
+        let mut query = format!(
+            "SELECT {} FROM bank_transactions WHERE company_id = ?1",
+            BANK_TRANSACTION_COLUMNS
+        );
+        if payload.unmatched_only.unwrap_or(false) {
+            query.push_str(" AND voucher_id IS NULL");
+        }
+        if payload.from_date.is_some() {
+            query.push_str(" AND booking_date >= ?2");
+        }
+        if payload.to_date.is_some() {
+            query.push_str(if payload.from_date.is_some() { " AND booking_date <= ?3" } else { " AND booking_date <= ?2" });
+        }
+        query.push_str(" ORDER BY booking_date ASC, imported_at ASC");
+
+        let mut stmt = conn.prepare(&query).map_err(map_sql_error)?;
+        let mut params_vec: Vec<&dyn rusqlite::ToSql> = vec![&payload.company_id];
+        if let Some(ref value) = payload.from_date {
+            params_vec.push(value);
+        }
+        if let Some(ref value) = payload.to_date {
+            params_vec.push(value);
+        }
+        let rows = stmt
+            .query_map(params_vec.as_slice(), bank_transaction_from_row)
+            .map_err(map_sql_error)?;
+        let mut transactions = Vec::new();
+        for row in rows {
+            transactions.push(row.map_err(map_sql_error)?);
+        }
+        Ok(transactions)
+    }
+
+    /// Suggests existing vouchers with the same net amount on 1930 close to the
+    /// booking date, and active rules whose match text occurs in the description.
+    pub fn suggest_bank_matches(
+        &mut self,
+        payload: BankTransactionIdInput,
+    ) -> Result<BankMatchSuggestions, String> {
+        let conn = &*self.conn;
+        let transaction = fetch_bank_transaction(conn, &payload.bank_transaction_id)?;
+        let booking_date = transaction.booking_date;
+        let from_date = booking_date - chrono::Duration::days(MATCH_WINDOW_DAYS);
+        let to_date = booking_date + chrono::Duration::days(MATCH_WINDOW_DAYS);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT v.id, v.voucher_number, v.date, v.description, SUM(r.debit_cents - r.credit_cents)
+                 FROM vouchers v
+                 JOIN voucher_rows r ON r.voucher_id = v.id
+                 WHERE v.company_id = ?1 AND r.account_id = ?2 AND v.date BETWEEN ?3 AND ?4
+                   AND NOT EXISTS (SELECT 1 FROM bank_transactions b WHERE b.voucher_id = v.id)
+                 GROUP BY v.id
+                 HAVING SUM(r.debit_cents - r.credit_cents) = ?5",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map(
+                params![
+                    &transaction.company_id,
+                    &transaction.account_id,
+                    from_date,
+                    to_date,
+                    transaction.amount
+                ],
+                |row| {
+                    Ok((
+                        row.get::<_, VoucherId>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, NaiveDate>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, Money>(4)?,
+                    ))
+                },
+            )
+            .map_err(map_sql_error)?;
+
+        let mut vouchers = Vec::new();
+        for row in rows {
+            let (voucher_id, voucher_number, date, description, amount) = row.map_err(map_sql_error)?;
+            let day_difference = (date - booking_date).num_days().abs();
+            vouchers.push(BankVoucherMatch {
+                voucher_id,
+                voucher_number,
+                date,
+                description,
+                amount,
+                day_difference,
+            });
+        }
+        vouchers.sort_by_key(|candidate| (candidate.day_difference, candidate.voucher_number));
+
+        let description = transaction.description.to_lowercase();
+        let rules = fetch_bank_rules(conn, &transaction.company_id)?
+            .into_iter()
+            .filter(|rule| rule.is_active && description.contains(&rule.match_text.to_lowercase()))
+            .collect();
+
+        Ok(BankMatchSuggestions {
+            bank_transaction_id: transaction.id,
+            vouchers,
+            rules,
+        })
+    }
+
+    pub fn match_bank_transaction(
+        &mut self,
+        payload: MatchBankTransactionInput,
+    ) -> Result<BankTransaction, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let transaction = fetch_bank_transaction(&tx, &payload.bank_transaction_id)?;
+        if transaction.voucher_id.is_some() {
+            return Err("Bank transaction is already matched".to_string());
+        }
+
+        let (company_id, bank_amount): (CompanyId, Option<Money>) = tx
+            .query_row(
+                "SELECT v.company_id,
+                        (SELECT SUM(r.debit_cents - r.credit_cents) FROM voucher_rows r
+                         WHERE r.voucher_id = v.id AND r.account_id = ?2)
+                 FROM vouchers v WHERE v.id = ?1",
+                params![&payload.voucher_id, &transaction.account_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(map_sql_error)?;
+        if company_id != transaction.company_id {
+            return Err("Voucher does not belong to company".to_string());
+        }
+        if bank_amount != Some(transaction.amount) {
+            return Err("Voucher amount on the bank account does not match the transaction".to_string());
+        }
+
+        let now = Utc::now().to_rfc3339();
+        link_voucher(&tx, user, &transaction, &payload.voucher_id, &now)?;
+        let transaction = fetch_bank_transaction(&tx, &payload.bank_transaction_id)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(transaction)
+    }
+
+    pub fn list_bank_rules(&mut self, payload: CompanyIdInput) -> Result<Vec<BankRule>, String> {
+        let conn = &*self.conn;
+        fetch_bank_rules(conn, &payload.company_id)
+    }
+
+    pub fn upsert_bank_rule(&mut self, payload: UpsertBankRuleInput) -> Result<BankRule, String> {
+        let conn = &mut *self.conn;
+        if payload.match_text.trim().is_empty() {
+            return Err("Match text is required".to_string());
+        }
+        if payload.vat_rate_percent.is_some() != payload.vat_account_id.is_some() {
+            return Err("VAT rate and VAT account must be set together".to_string());
+        }
+        if let Some(rate) = payload.vat_rate_percent {
+            if !(0..=100).contains(&rate) {
+                return Err("VAT rate must be between 0 and 100".to_string());
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let rule_id = payload
+            .id
+            .clone()
+            .unwrap_or_else(BankRuleId::generate);
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        ensure_company(&tx, "bank_rules", &rule_id, &payload.company_id, "Rule")?;
+        tx.execute(
+            "INSERT INTO bank_rules (id, company_id, name, match_text, counter_account_id, vat_account_id, vat_rate_percent, is_active, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(id) DO UPDATE SET
+               name = excluded.name,
+               match_text = excluded.match_text,
+               counter_account_id = excluded.counter_account_id,
+               vat_account_id = excluded.vat_account_id,
+               vat_rate_percent = excluded.vat_rate_percent,
+               is_active = excluded.is_active",
+            params![
+                rule_id,
+                &payload.company_id,
+                &payload.name,
+                &payload.match_text,
+                &payload.counter_account_id,
+                &payload.vat_account_id,
+                payload.vat_rate_percent,
+                if payload.is_active { 1 } else { 0 },
+                now
+            ],
+        )
+        .map_err(map_sql_error)?;
+        tx.commit().map_err(map_sql_error)?;
+
+        Ok(BankRule {
+            id: rule_id,
+            company_id: payload.company_id,
+            name: payload.name,
+            match_text: payload.match_text,
+            counter_account_id: payload.counter_account_id,
+            vat_account_id: payload.vat_account_id,
+            vat_rate_percent: payload.vat_rate_percent,
+            is_active: payload.is_active,
+            created_at: now,
+        })
+    }
+
+    /// Books an unmatched bank transaction against the rule's counter account,
+    /// splitting out VAT when the rule has a rate, and links the new voucher.
+    pub fn create_voucher_from_bank_rule(
+        &mut self,
+        payload: CreateVoucherFromBankRuleInput,
+    ) -> Result<Voucher, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let transaction = fetch_bank_transaction(&tx, &payload.bank_transaction_id)?;
+        if transaction.voucher_id.is_some() {
+            return Err("Bank transaction is already matched".to_string());
+        }
+        let rule = fetch_bank_rules(&tx, &transaction.company_id)?
+            .into_iter()
+            .find(|rule| rule.id == payload.rule_id)
+            .ok_or_else(|| "Rule not found".to_string())?;
+
+        let total = transaction
+            .amount
+            .checked_abs()
+            .ok_or_else(|| "Amount is too large".to_string())?;
+        // The VAT included in the gross amount.
+        let vat = match rule.vat_rate_percent {
+            Some(rate) => total
+                .checked_mul_ratio(rate, 100 + rate)
+                .ok_or_else(|| "Amount is too large".to_string())?,
+            None => Money::ZERO,
+        };
+        let incoming = transaction.amount.is_positive();
+        let side = |amount: Money, debit: bool| {
+            if debit {
+                (amount, Money::ZERO)
+            } else {
+                (Money::ZERO, amount)
+            }
+        };
+
+        let mut rows = Vec::new();
+        let (debit, credit) = side(total, incoming);
+        rows.push(CreateVoucherRowInput {
+            account_id: transaction.account_id.clone(),
+            description: Some(transaction.description.clone()),
+            debit,
+            credit,
+            vat_code: None,
+        });
+        let net = total
+            .checked_sub(vat)
+            .ok_or_else(|| "Amount is too large".to_string())?;
+        let (debit, credit) = side(net, !incoming);
+        rows.push(CreateVoucherRowInput {
+            account_id: rule.counter_account_id.clone(),
+            description: None,
+            debit,
+            credit,
+            vat_code: None,
+        });
+        if let (Some(vat_account_id), true) = (&rule.vat_account_id, vat.is_positive()) {
+            let (debit, credit) = side(vat, !incoming);
+            rows.push(CreateVoucherRowInput {
+                account_id: vat_account_id.clone(),
+                description: None,
+                debit,
+                credit,
+                vat_code: None,
+            });
+        }
+
+        let voucher = insert_voucher(
+            &tx,
+            user,
+            &CreateVoucherInput {
+                company_id: transaction.company_id.clone(),
+                series_id: payload.series_id,
+                date: transaction.booking_date,
+                description: payload
+                    .description
+                    .unwrap_or_else(|| transaction.description.clone()),
+                counterparty: transaction.counterparty.clone(),
+                counterparty_id: None,
+                rows,
+                attachments: None,
+            },
+        )?;
+        let now = Utc::now().to_rfc3339();
+        link_voucher(&tx, user, &transaction, &voucher.id, &now)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(voucher)
+    }
+}
+
+fn link_voucher(
+    conn: &Connection,
+    user: &str,
+    transaction: &BankTransaction,
+    voucher_id: &VoucherId,
+    now: &str,
+) -> Result<(), String> {
+    // A voucher linked twice would be counted twice in the reconciliation.
+    let linked: Option<BankTransactionId> = conn
+        .query_row(
+            "SELECT id FROM bank_transactions WHERE voucher_id = ?1",
+            [voucher_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(map_sql_error)?;
+    if linked.is_some() {
+        return Err("Voucher is already matched to another bank transaction".to_string());
+    }
+    conn.execute(
+        "UPDATE bank_transactions SET voucher_id = ?1, matched_at = ?2 WHERE id = ?3",
+        params![voucher_id, now, &transaction.id],
+    )
+    .map_err(map_sql_error)?;
+    insert_audit_entry(
+        conn,
+        user,
+        &transaction.company_id,
+        "bank_transaction",
+        &transaction.id,
+        "match",
+        json!({ "voucher_id": voucher_id, "amount_cents": transaction.amount.cents() }),
+    )
+}
+
+/// Looks up the company's 1930 account, which all statement lines are booked on.
+pub fn bank_account_id(conn: &Connection, company_id: &str) -> Result<AccountId, String> {
+    conn.query_row(
+        "SELECT id FROM accounts WHERE company_id = ?1 AND number = ?2",
+        params![company_id, BANK_ACCOUNT_NUMBER],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(map_sql_error)?
+    .ok_or_else(|| format!("Account {} is missing", BANK_ACCOUNT_NUMBER))
+}
+
+fn fetch_bank_transaction(conn: &Connection, id: &str) -> Result<BankTransaction, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM bank_transactions WHERE id = ?1", BANK_TRANSACTION_COLUMNS),
+        [id],
+        bank_transaction_from_row,
+    )
+    .map_err(map_sql_error)
+}
+
+fn fetch_bank_rules(conn: &Connection, company_id: &str) -> Result<Vec<BankRule>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, company_id, name, match_text, counter_account_id, vat_account_id, vat_rate_percent, is_active, created_at
+             FROM bank_rules WHERE company_id = ?1 ORDER BY name",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([company_id], |row| {
+            Ok(BankRule {
+                id: row.get(0)?,
+                company_id: row.get(1)?,
+                name: row.get(2)?,
+                match_text: row.get(3)?,
+                counter_account_id: row.get(4)?,
+                vat_account_id: row.get(5)?,
+                vat_rate_percent: row.get(6)?,
+                is_active: row.get::<_, i64>(7)? == 1,
+                created_at: row.get(8)?,
+            })
+        })
+        .map_err(map_sql_error)?;
+    let mut rules = Vec::new();
+    for row in rows {
+        rules.push(row.map_err(map_sql_error)?);
+    }
+    Ok(rules)
+}
+
+pub fn bank_transaction_from_row(row: &Row) -> rusqlite::Result<BankTransaction> {
+    Ok(BankTransaction {
+        id: row.get(0)?,
+        company_id: row.get(1)?,
+        account_id: row.get(2)?,
+        booking_date: row.get(3)?,
+        value_date: row.get(4)?,
+        amount: row.get(5)?,
+        description: row.get(6)?,
+        reference: row.get(7)?,
+        counterparty: row.get(8)?,
+        source_format: row.get(9)?,
+        imported_at: row.get(10)?,
+        imported_by: row.get(11)?,
+        voucher_id: row.get(12)?,
+        matched_at: row.get(13)?,
+    })
+}
+
+/// Swedish bank exports are often Latin-1 rather than UTF-8.
+fn decode_statement(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+fn detect_format(
+    requested: Option<&str>,
+    file_path: &str,
+    content: &str,
+) -> Result<StatementFormat, String> {
+    match requested.map(|value| value.to_ascii_lowercase()).as_deref() {
+        Some("csv") => return Ok(StatementFormat::Csv),
+        Some("camt053") | Some("camt.053") | Some("xml") => return Ok(StatementFormat::Camt053),
+        Some(other) => return Err(format!("Unsupported statement format: {}", other)),
+        None => {}
+    }
+    let is_xml = Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("xml"))
+        .unwrap_or(false);
+    if is_xml || content.trim_start().starts_with('<') {
+        Ok(StatementFormat::Camt053)
+    } else {
+        Ok(StatementFormat::Csv)
+    }
+}
+
+/// Column positions found in a CSV header row.
+struct CsvLayout {
+    delimiter: char,
+    date: usize,
+    value_date: Option<usize>,
+    amount: usize,
+    text: Option<usize>,
+    reference: Option<usize>,
+    counterparty: Option<usize>,
+}
+
+impl CsvLayout {
+    fn from_header(line: &str) -> Option<CsvLayout> {
+        let delimiter = detect_delimiter(line);
+        let headers: Vec<String> = split_csv_line(line, delimiter)
+            .iter()
+            .map(|field| field.trim().to_lowercase())
+            .collect();
+        let find = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| headers.iter().position(|header| header == name))
+        };
+        Some(CsvLayout {
+            delimiter,
+            date: find(DATE_HEADERS)?,
+            value_date: find(VALUE_DATE_HEADERS),
+            amount: find(AMOUNT_HEADERS)?,
+            text: find(TEXT_HEADERS),
+            reference: find(REFERENCE_HEADERS),
+            counterparty: find(COUNTERPARTY_HEADERS),
+        })
+    }
+}
+
+/// Parses the CSV exports of the common Swedish banks. The header row is
+/// located by its column names, so preamble lines (account number, period)
+/// and trailing summary lines are skipped. A row with a malformed date fails
+/// the import rather than being left out.
+fn parse_bank_csv(content: &str) -> Result<Vec<StatementLine>, String> {
+    let lines: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).collect();
+    let (header_index, layout) = lines
+        .iter()
+        .enumerate()
+        .find_map(|(index, line)| CsvLayout::from_header(line).map(|layout| (index, layout)))
+        .ok_or_else(|| "Could not find a header row with date and amount columns".to_string())?;
+
+    let mut statement = Vec::new();
+    for (index, line) in lines.iter().enumerate().skip(header_index + 1) {
+        let fields = split_csv_line(line, layout.delimiter);
+        let field = |position: Option<usize>| {
+            position
+                .and_then(|position| fields.get(position))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        // Summary lines have no date or a label such as "Summa" in its place.
+        let date = match field(Some(layout.date)) {
+            Some(date) if date.starts_with(|c: char| c.is_ascii_digit()) => date,
+            _ => continue,
+        };
+        let booking_date = normalize_date(&date)
+            .ok_or_else(|| format!("Row {}: invalid date '{}'", index + 1, date))?;
+        let amount = field(Some(layout.amount)).unwrap_or_default();
+        let amount = parse_amount(&amount)
+            .ok_or_else(|| format!("Row {}: invalid amount '{}'", index + 1, amount))?;
+        let reference = field(layout.reference);
+        let counterparty = field(layout.counterparty);
+        let description = field(layout.text)
+            .or_else(|| counterparty.clone())
+            .or_else(|| reference.clone())
+            .unwrap_or_default();
+
+        statement.push(StatementLine {
+            booking_date,
+            value_date: field(layout.value_date).and_then(|value| normalize_date(&value)),
+            amount,
+            description,
+            reference,
+            counterparty,
+            external_id: None,
+        });
+    }
+    Ok(statement)
+}
+
+fn detect_delimiter(line: &str) -> char {
+    [';', '\t', ',']
+        .into_iter()
+        .max_by_key(|delimiter| line.matches(*delimiter).count())
+        .unwrap_or(';')
+}
+
+fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ch if ch == delimiter && !in_quotes => fields.push(std::mem::take(&mut current)),
+            ch => current.push(ch),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+/// Parses an ISO 20022 camt.053 bank-to-customer statement. Only booked
+/// entries (`Ntry`) are read; balances are handled by reconciliation.
+fn parse_camt053(content: &str) -> Result<Vec<StatementLine>, String> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(true);
+
+    let mut path: Vec<String> = Vec::new();
+    let mut statement = Vec::new();
+    let mut entry: Option<StatementLine> = None;
+    let mut booking_date: Option<NaiveDate> = None;
+    let mut credit_debit = String::new();
+    let mut debtor_name: Option<String> = None;
+    let mut creditor_name: Option<String> = None;
+    let mut remittance: Vec<String> = Vec::new();
+    let mut additional_info: Option<String> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).to_string();
+                if name == "Ntry" {
+                    entry = Some(StatementLine::default());
+                    booking_date = None;
+                    credit_debit.clear();
+                    debtor_name = None;
+                    creditor_name = None;
+                    remittance.clear();
+                    additional_info = None;
+                }
+                path.push(name);
+            }
+            Ok(Event::End(_)) => {
+                if path.pop().as_deref() == Some("Ntry") {
+                    let mut line = entry.take().ok_or("Malformed camt.053 entry")?;
+                    line.booking_date = booking_date
+                        .take()
+                        .ok_or("camt.053 entry without booking date")?;
+                    match credit_debit.as_str() {
+                        "CRDT" => line.counterparty = debtor_name.take(),
+                        "DBIT" => {
+                            line.amount = line
+                                .amount
+                                .checked_neg()
+                                .ok_or("Amount is too large")?;
+                            line.counterparty = creditor_name.take();
+                        }
+                        other => {
+                            return Err(format!("camt.053 entry with unknown indicator '{}'", other))
+                        }
+                    }
+                    line.description = if remittance.is_empty() {
+                        additional_info
+                            .take()
+                            .or_else(|| line.counterparty.clone())
+                            .unwrap_or_default()
+                    } else {
+                        remittance.join(" ")
+                    };
+                    statement.push(line);
+                }
+            }
+            Ok(Event::Text(text)) => {
+                let Some(line) = entry.as_mut() else {
+                    continue;
+                };
+                let value = text.unescape().map_err(|err| err.to_string())?.trim().to_string();
+                let ends_with = |suffix: &[&str]| {
+                    path.len() >= suffix.len()
+                        && path[path.len() - suffix.len()..]
+                            .iter()
+                            .zip(suffix)
+                            .all(|(segment, expected)| segment == expected)
+                };
+                if ends_with(&["Ntry", "Amt"]) {
+                    line.amount = parse_amount(&value)
+                        .ok_or_else(|| format!("Invalid camt.053 amount '{}'", value))?;
+                } else if ends_with(&["Ntry", "CdtDbtInd"]) {
+                    credit_debit = value;
+                } else if ends_with(&["Ntry", "BookgDt", "Dt"]) || ends_with(&["Ntry", "BookgDt", "DtTm"]) {
+                    booking_date = Some(
+                        normalize_date(&value)
+                            .ok_or_else(|| format!("Invalid camt.053 date '{}'", value))?,
+                    );
+                } else if ends_with(&["Ntry", "ValDt", "Dt"]) || ends_with(&["Ntry", "ValDt", "DtTm"]) {
+                    line.value_date = normalize_date(&value);
+                } else if ends_with(&["Ntry", "AcctSvcrRef"]) {
+                    line.external_id = Some(value);
+                } else if ends_with(&["Refs", "EndToEndId"]) && value != "NOTPROVIDED" {
+                    line.reference = Some(value);
+                } else if ends_with(&["RmtInf", "Ustrd"]) || ends_with(&["CdtrRefInf", "Ref"]) {
+                    remittance.push(value);
+                } else if ends_with(&["Ntry", "AddtlNtryInf"]) {
+                    additional_info = Some(value);
+                } else if path.iter().any(|segment| segment == "RltdPties") && ends_with(&["Nm"]) {
+                    if path.iter().any(|segment| segment == "Dbtr") {
+                        debtor_name.get_or_insert(value);
+                    } else if path.iter().any(|segment| segment == "Cdtr") {
+                        creditor_name.get_or_insert(value);
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(err) => return Err(format!("Invalid camt.053 file: {}", err)),
+        }
+    }
+    Ok(statement)
+}
+
+fn normalize_date(value: &str) -> Option<NaiveDate> {
+    let value = value.split_once('T').map(|(date, _)| date).unwrap_or(value);
+    // chrono's `%Y` also takes two digits and would read "24-01-05" as year
+    // 24, so the format is picked by the number of leading digits.
+    let formats: &[&str] = match value.chars().take_while(char::is_ascii_digit).count() {
+        8 => &["%Y%m%d"],
+        4 => &["%Y-%m-%d", "%Y/%m/%d"],
+        2 => &["%y-%m-%d"],
+        _ => &[],
+    };
+    formats
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+/// Parses amounts like `-1 234,50`, `1.234,50`, `1234.5` or `−200`. Unlike
+/// [`Money`]'s own parser this accepts `.` and `,` as thousands separators:
+/// the last `,` or `.` followed by at most two digits is the decimal separator.
+fn parse_amount(raw: &str) -> Option<Money> {
+    let cleaned: String = raw
+        .trim()
+        .trim_end_matches("kr")
+        .trim_end_matches("SEK")
+        .chars()
+        .filter(|ch| !ch.is_whitespace() && *ch != '"' && *ch != '\'')
+        .map(|ch| if ch == '\u{2212}' { '-' } else { ch })
+        .collect();
+    let (negative, unsigned) = match cleaned.strip_prefix('-') {
+        Some(rest) => (true, rest.to_string()),
+        None => match cleaned.strip_suffix('-') {
+            Some(rest) => (true, rest.to_string()),
+            None => (false, cleaned.trim_start_matches('+').to_string()),
+        },
+    };
+    if unsigned.is_empty() {
+        return None;
+    }
+
+    let decimal_position = unsigned
+        .rfind([',', '.'])
+        .filter(|position| unsigned.len() - position - 1 <= 2);
+    let (integer_part, fraction_part) = match decimal_position {
+        Some(position) => (&unsigned[..position], &unsigned[position + 1..]),
+        None => (unsigned.as_str(), ""),
+    };
+    let integer_digits: String = integer_part.chars().filter(|ch| *ch != '.' && *ch != ',').collect();
+    if !integer_digits.chars().all(|ch| ch.is_ascii_digit())
+        || !fraction_part.chars().all(|ch| ch.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let integer: i64 = if integer_digits.is_empty() { 0 } else { integer_digits.parse().ok()? };
+    let fraction: i64 = match fraction_part.len() {
+        0 => 0,
+        1 => fraction_part.parse::<i64>().ok()? * 10,
+        _ => fraction_part.parse().ok()?,
+    };
+    let cents = integer.checked_mul(100)?.checked_add(fraction)?;
+    Some(Money::from_cents(if negative { -cents } else { cents }))
+}
@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde_json::json;
+
+use crate::db::{
+    accounting_method, bas_account_id, default_series_id, CASH_METHOD,
+};
+use crate::ids::{AccountId, CashYearEndBookingId, InvoiceId};
+use crate::invoicing::{cash_share, revenue_amounts, RECEIVABLES_ACCOUNT_NUMBER};
+use crate::models::{
+    CashYearEndBooking, CashYearEndInput, CashYearEndResult, CompanyIdInput, CreateVoucherInput,
+    CreateVoucherRowInput,
+};
+use crate::money::Money;
+use crate::suppliers::{expense_amounts, PAYABLES_ACCOUNT_NUMBER};
+use crate::{insert_audit_entry, insert_voucher, map_error, map_sql_error, Books};
+
+const BOOKING_COLUMNS: &str = "id, company_id, year_end_date, voucher_id, reversal_voucher_id, receivables_cents, payables_cents, created_at, created_by";
+
+impl Books<'_> {
+    /// Year-end closing under kontantmetoden: books customer and supplier invoices
+    /// that are unpaid on the balance sheet date as receivables and payables, and
+    /// reverses the booking on the first day of the next fiscal year so that the
+    /// payments can be booked as usual.
+    pub fn close_cash_method_year(
+        &mut self,
+        payload: CashYearEndInput,
+    ) -> Result<CashYearEndResult, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let year_end = payload.year_end_date;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        if accounting_method(&tx, &payload.company_id).map_err(map_error)? != CASH_METHOD {
+            return Err("Company does not use the cash method".to_string());
+        }
+        let fiscal_year_end: NaiveDate = tx
+            .query_row(
+                "SELECT fiscal_year_end FROM companies WHERE id = ?1",
+                [&payload.company_id],
+                |row| row.get(0),
+            )
+            .map_err(map_sql_error)?;
+        if (fiscal_year_end.month(), fiscal_year_end.day()) != (year_end.month(), year_end.day()) {
+            return Err(format!(
+                "{} is not a fiscal year end (year ends {})",
+                year_end, fiscal_year_end
+            ));
+        }
+        let existing: Option<String> = tx
+            .query_row(
+                "SELECT id FROM cash_year_end_bookings WHERE company_id = ?1 AND year_end_date = ?2",
+                params![&payload.company_id, year_end],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(map_sql_error)?;
+        if existing.is_some() {
+            return Err(format!("Year end {} is already booked", year_end));
+        }
+
+        // Signed amounts per account, debit positive.
+        let mut amounts: BTreeMap<AccountId, Money> = BTreeMap::new();
+
+        let customer_invoice_ids = unpaid_invoice_ids(&tx, "customer", &payload.company_id, year_end)?;
+        let mut receivables = Money::ZERO;
+        for invoice_id in &customer_invoice_ids {
+            let invoice = crate::invoicing::fetch_invoice(&tx, invoice_id)?;
+            let paid = paid_as_of(
+                invoice
+                    .payments
+                    .iter()
+                    .flatten()
+                    .map(|payment| (payment.payment_date, payment.amount)),
+                year_end,
+            )?;
+            let lines = invoice.lines.as_deref().unwrap_or_default();
+            let revenue = revenue_amounts(&tx, user, &invoice.company_id, lines)?;
+            let open = cash_share(&revenue, invoice.total, paid, invoice.total)?;
+            for (account_id, amount) in open {
+                let balance = amounts.entry(account_id).or_default();
+                *balance = balance.checked_post(Money::ZERO, amount)?;
+            }
+            receivables = receivables.checked_post(invoice.total, paid)?;
+        }
+        if !receivables.is_zero() {
+            let account_id =
+                bas_account_id(&tx, user, &payload.company_id, RECEIVABLES_ACCOUNT_NUMBER)
+                    .map_err(map_error)?;
+            let balance = amounts.entry(account_id).or_default();
+            *balance = balance.checked_post(receivables, Money::ZERO)?;
+        }
+
+        let supplier_invoice_ids = unpaid_invoice_ids(&tx, "supplier", &payload.company_id, year_end)?;
+        let mut payables = Money::ZERO;
+        for invoice_id in &supplier_invoice_ids {
+            let invoice = crate::suppliers::fetch_invoice(&tx, invoice_id)?;
+            let paid = paid_as_of(
+                invoice
+                    .payments
+                    .iter()
+                    .flatten()
+                    .map(|payment| (payment.payment_date, payment.amount)),
+                year_end,
+            )?;
+            let lines = invoice.lines.as_deref().unwrap_or_default();
+            let expenses = expense_amounts(&tx, user, &invoice.company_id, lines)?;
+            let open = cash_share(&expenses, invoice.total, paid, invoice.total)?;
+            for (account_id, amount) in open {
+                let balance = amounts.entry(account_id).or_default();
+                *balance = balance.checked_post(amount, Money::ZERO)?;
+            }
+            payables = payables.checked_post(invoice.total, paid)?;
+        }
+        if !payables.is_zero() {
+            let account_id =
+                bas_account_id(&tx, user, &payload.company_id, PAYABLES_ACCOUNT_NUMBER)
+                    .map_err(map_error)?;
+            let balance = amounts.entry(account_id).or_default();
+            *balance = balance.checked_post(Money::ZERO, payables)?;
+        }
+
+        amounts.retain(|_, amount| !amount.is_zero());
+        if amounts.is_empty() {
+            return Err(format!("No unpaid invoices at {}", year_end));
+        }
+
+        let series_id = match &payload.series_id {
+            Some(series_id) => series_id.clone(),
+            None => default_series_id(&tx, &payload.company_id).map_err(map_error)?,
+        };
+        let voucher = insert_voucher(
+            &tx,
+            user,
+            &CreateVoucherInput {
+                company_id: payload.company_id.clone(),
+                series_id: series_id.clone(),
+                date: year_end,
+                description: format!("Obetalda fakturor vid bokslut {}", year_end),
+                counterparty: None,
+                counterparty_id: None,
+                rows: voucher_rows(&amounts, 1)?,
+                attachments: None,
+            },
+        )?;
+        let reversal_date = year_end + Duration::days(1);
+        let reversal = insert_voucher(
+            &tx,
+            user,
+            &CreateVoucherInput {
+                company_id: payload.company_id.clone(),
+                series_id,
+                date: reversal_date,
+                description: format!("Återföring obetalda fakturor vid bokslut {}", year_end),
+                counterparty: None,
+                counterparty_id: None,
+                rows: voucher_rows(&amounts, -1)?,
+                attachments: None,
+            },
+        )?;
+
+        let booking = CashYearEndBooking {
+            id: CashYearEndBookingId::generate(),
+            company_id: payload.company_id.clone(),
+            year_end_date: year_end,
+            voucher_id: voucher.id.clone(),
+            reversal_voucher_id: reversal.id.clone(),
+            receivables,
+            payables,
+            created_at: Utc::now().to_rfc3339(),
+            created_by: user.to_string(),
+        };
+        tx.execute(
+            "INSERT INTO cash_year_end_bookings (id, company_id, year_end_date, voucher_id, reversal_voucher_id, receivables_cents, payables_cents, created_at, created_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                booking.id,
+                booking.company_id,
+                booking.year_end_date,
+                booking.voucher_id,
+                booking.reversal_voucher_id,
+                booking.receivables,
+                booking.payables,
+                booking.created_at,
+                booking.created_by
+            ],
+        )
+        .map_err(map_sql_error)?;
+        insert_audit_entry(
+            &tx,
+            user,
+            &payload.company_id,
+            "cash_year_end",
+            &booking.id,
+            "create",
+            json!({
+                "year_end_date": &booking.year_end_date,
+                "voucher_id": &voucher.id,
+                "reversal_voucher_id": &reversal.id,
+                "customer_invoice_ids": &customer_invoice_ids,
+                "supplier_invoice_ids": &supplier_invoice_ids
+            }),
+        )?;
+        tx.commit().map_err(map_sql_error)?;
+
+        Ok(CashYearEndResult {
+            booking,
+            voucher,
+            reversal,
+            customer_invoice_ids,
+            supplier_invoice_ids,
+        })
+    }
+
+    pub fn list_cash_year_end_bookings(
+        &mut self,
+        payload: CompanyIdInput,
+    ) -> Result<Vec<CashYearEndBooking>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM cash_year_end_bookings WHERE company_id = ?1 ORDER BY year_end_date DESC",
+                BOOKING_COLUMNS
+            ))
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], booking_from_row)
+            .map_err(map_sql_error)?;
+        let mut bookings = Vec::new();
+        for row in rows {
+            bookings.push(row.map_err(map_sql_error)?);
+        }
+        Ok(bookings)
+    }
+}
+
+/// Invoices booked under the cash method (no invoice voucher) that were issued
+/// on or before `year_end` and not fully paid by then.
+fn unpaid_invoice_ids(
+    conn: &Connection,
+    kind: &str,
+    company_id: &str,
+    year_end: NaiveDate,
+) -> Result<Vec<InvoiceId>, String> {
+    let query = format!(
+        "SELECT i.id FROM {kind}_invoices i
+         WHERE i.company_id = ?1 AND i.voucher_id IS NULL AND i.invoice_date <= ?2
+           AND i.total_cents > COALESCE((SELECT SUM(p.amount_cents) FROM {kind}_payments p
+                                         WHERE p.invoice_id = i.id AND p.payment_date <= ?2), 0)
+         ORDER BY i.invoice_date, i.created_at",
+        kind = kind
+    );
+    let mut stmt = conn.prepare(&query).map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map(params![company_id, year_end], |row| row.get(0))
+        .map_err(map_sql_error)?;
+    let mut ids = Vec::new();
+    for row in rows {
+        ids.push(row.map_err(map_sql_error)?);
+    }
+    Ok(ids)
+}
+
+fn paid_as_of(
+    payments: impl Iterator<Item = (NaiveDate, Money)>,
+    year_end: NaiveDate,
+) -> Result<Money, String> {
+    Money::checked_sum(
+        payments
+            .filter(|(payment_date, _)| *payment_date <= year_end)
+            .map(|(_, amount)| amount),
+    )
+}
+
+/// Voucher rows from signed amounts (debit positive); `sign` -1 reverses them.
+fn voucher_rows(
+    amounts: &BTreeMap<AccountId, Money>,
+    sign: i64,
+) -> Result<Vec<CreateVoucherRowInput>, String> {
+    amounts
+        .iter()
+        .map(|(account_id, amount)| {
+            let negated = amount.checked_neg().ok_or("Amount is too large")?;
+            let (debit, credit) = if sign < 0 { (negated, *amount) } else { (*amount, negated) };
+            Ok(CreateVoucherRowInput {
+                account_id: account_id.clone(),
+                description: None,
+                debit: debit.max(Money::ZERO),
+                credit: credit.max(Money::ZERO),
+                vat_code: None,
+            })
+        })
+        .collect()
+}
+
+fn booking_from_row(row: &Row) -> rusqlite::Result<CashYearEndBooking> {
+    Ok(CashYearEndBooking {
+        id: row.get(0)?,
+        company_id: row.get(1)?,
+        year_end_date: row.get(2)?,
+        voucher_id: row.get(3)?,
+        reversal_voucher_id: row.get(4)?,
+        receivables: row.get(5)?,
+        payables: row.get(6)?,
+        created_at: row.get(7)?,
+        created_by: row.get(8)?,
+    })
+}
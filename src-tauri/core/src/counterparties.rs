@@ -0,0 +1,350 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde_json::json;
+
+use crate::invoicing::luhn_check_digit;
+use crate::ids::CounterpartyId;
+use crate::models::{
+    CompanyIdInput, Counterparty, CounterpartyReport, CounterpartyReportInput,
+    SearchCounterpartiesInput, UpsertCounterpartyInput, VoucherListItem,
+};
+use crate::money::Money;
+use crate::{ensure_company, insert_audit_entry, map_sql_error, Books};
+
+const COUNTERPARTY_COLUMNS: &str = "id, company_id, name, org_number, vat_number, address, default_revenue_account_id, default_expense_account_id, is_active, created_at";
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+
+impl Books<'_> {
+    pub fn list_counterparties(
+        &mut self,
+        payload: CompanyIdInput,
+    ) -> Result<Vec<Counterparty>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM counterparties WHERE company_id = ?1 ORDER BY name COLLATE NOCASE",
+                COUNTERPARTY_COLUMNS
+            ))
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], counterparty_from_row)
+            .map_err(map_sql_error)?;
+        let mut counterparties = Vec::new();
+        for row in rows {
+            counterparties.push(row.map_err(map_sql_error)?);
+        }
+        Ok(counterparties)
+    }
+
+    /// Creates or updates a counterparty. Organisation and personal numbers are
+    /// Luhn-checked and stored as `NNNNNN-NNNN` (or `YYYYMMDD-NNNN`).
+    pub fn upsert_counterparty(
+        &mut self,
+        payload: UpsertCounterpartyInput,
+    ) -> Result<Counterparty, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let name = payload.name.trim().to_string();
+        if name.is_empty() {
+            return Err("Counterparty name is required".to_string());
+        }
+        let org_number = match payload.org_number.as_deref().map(str::trim) {
+            Some(value) if !value.is_empty() => Some(normalize_org_number(value)?),
+            _ => None,
+        };
+        let vat_number = match payload.vat_number.as_deref().map(str::trim) {
+            Some(value) if !value.is_empty() => Some(normalize_vat_number(value, org_number.as_deref())?),
+            _ => None,
+        };
+
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        for account_id in [
+            &payload.default_revenue_account_id,
+            &payload.default_expense_account_id,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let exists: Option<i64> = tx
+                .query_row(
+                    "SELECT 1 FROM accounts WHERE id = ?1 AND company_id = ?2",
+                    params![account_id, &payload.company_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(map_sql_error)?;
+            if exists.is_none() {
+                return Err("Default account does not belong to company".to_string());
+            }
+        }
+        if let Some(org_number) = &org_number {
+            let duplicate: Option<String> = tx
+                .query_row(
+                    "SELECT name FROM counterparties WHERE company_id = ?1 AND org_number = ?2 AND id != ?3",
+                    params![&payload.company_id, org_number, payload.id.as_deref().unwrap_or_default()],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(map_sql_error)?;
+            if let Some(existing) = duplicate {
+                return Err(format!("{} is already registered for {}", org_number, existing));
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let counterparty_id = payload
+            .id
+            .clone()
+            .unwrap_or_else(CounterpartyId::generate);
+        ensure_company(&tx, "counterparties", &counterparty_id, &payload.company_id, "Counterparty")?;
+        tx.execute(
+            "INSERT INTO counterparties (id, company_id, name, org_number, vat_number, address, default_revenue_account_id, default_expense_account_id, is_active, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT(id) DO UPDATE SET
+               name = excluded.name,
+               org_number = excluded.org_number,
+               vat_number = excluded.vat_number,
+               address = excluded.address,
+               default_revenue_account_id = excluded.default_revenue_account_id,
+               default_expense_account_id = excluded.default_expense_account_id,
+               is_active = excluded.is_active",
+            params![
+                counterparty_id,
+                &payload.company_id,
+                &name,
+                &org_number,
+                &vat_number,
+                &payload.address,
+                &payload.default_revenue_account_id,
+                &payload.default_expense_account_id,
+                if payload.is_active.unwrap_or(true) { 1 } else { 0 },
+                now
+            ],
+        )
+        .map_err(map_sql_error)?;
+        insert_audit_entry(
+            &tx,
+            user,
+            &payload.company_id,
+            "counterparty",
+            &counterparty_id,
+            if payload.id.is_some() { "update" } else { "create" },
+            json!({ "name": &name, "org_number": &org_number }),
+        )?;
+        let counterparty = fetch_counterparty(&tx, &counterparty_id)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(counterparty)
+    }
+
+    /// Autocomplete: active counterparties whose name or org number matches the
+    /// query, prefix matches first.
+    pub fn search_counterparties(
+        &mut self,
+        payload: SearchCounterpartiesInput,
+    ) -> Result<Vec<Counterparty>, String> {
+        let conn = &*self.conn;
+        let query = payload.query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let escaped = query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let digits: String = query.chars().filter(|ch| ch.is_ascii_digit()).collect();
+        let digits_pattern = if digits.is_empty() {
+            String::new()
+        } else {
+            format!("%{}%", digits)
+        };
+        let limit = payload.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, 100);
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM counterparties
+                 WHERE company_id = ?1 AND is_active = 1
+                   AND (name LIKE ?2 ESCAPE '\\' OR (?3 != '' AND REPLACE(org_number, '-', '') LIKE ?3))
+                 ORDER BY name LIKE ?4 ESCAPE '\\' DESC, name COLLATE NOCASE
+                 LIMIT ?5",
+                COUNTERPARTY_COLUMNS
+            ))
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map(
+                params![
+                    payload.company_id,
+                    format!("%{}%", escaped),
+                    digits_pattern,
+                    format!("{}%", escaped),
+                    limit
+                ],
+                counterparty_from_row,
+            )
+            .map_err(map_sql_error)?;
+        let mut counterparties = Vec::new();
+        for row in rows {
+            counterparties.push(row.map_err(map_sql_error)?);
+        }
+        Ok(counterparties)
+    }
+
+    /// All vouchers linked to a counterparty, optionally limited to a date range.
+    pub fn report_vouchers_by_counterparty(
+        &mut self,
+        payload: CounterpartyReportInput,
+    ) -> Result<CounterpartyReport, String> {
+        let conn = &*self.conn;
+        let counterparty = fetch_counterparty(conn, &payload.counterparty_id)?;
+        if counterparty.company_id != payload.company_id {
+            return Err("Counterparty does not belong to company".to_string());
+        }
+
+        let mut query = String::from(
+            "SELECT v.id, v.voucher_number, v.date, v.description, SUM(r.debit_cents)
+             FROM vouchers v
+             JOIN voucher_rows r ON r.voucher_id = v.id
+             WHERE v.company_id = ?1 AND v.counterparty_id = ?2",
+        );
+        if payload.from_date.is_some() {
+            query.push_str(" AND v.date >= ?3");
+        }
+        if payload.to_date.is_some() {
+            query.push_str(if payload.from_date.is_some() { " AND v.date <= ?4" } else { " AND v.date <= ?3" });
+        }
+        query.push_str(" GROUP BY v.id ORDER BY v.date ASC, v.voucher_number ASC");
+
+        let mut stmt = conn.prepare(&query).map_err(map_sql_error)?;
+        let mut params_vec: Vec<&dyn rusqlite::ToSql> = vec![&payload.company_id, &payload.counterparty_id];
+        if let Some(ref value) = payload.from_date {
+            params_vec.push(value);
+        }
+        if let Some(ref value) = payload.to_date {
+            params_vec.push(value);
+        }
+        let rows = stmt
+            .query_map(params_vec.as_slice(), |row| {
+                Ok(VoucherListItem {
+                    id: row.get(0)?,
+                    voucher_number: row.get(1)?,
+                    date: row.get(2)?,
+                    description: row.get(3)?,
+                    total: row.get(4)?,
+                })
+            })
+            .map_err(map_sql_error)?;
+        let mut vouchers = Vec::new();
+        for row in rows {
+            vouchers.push(row.map_err(map_sql_error)?);
+        }
+
+        Ok(CounterpartyReport {
+            counterparty,
+            voucher_count: vouchers.len() as i64,
+            total: Money::checked_sum(vouchers.iter().map(|voucher| voucher.total))?,
+            vouchers,
+        })
+    }
+}
+
+/// Name of an active counterparty in the company, for vouchers linking to it.
+pub fn active_counterparty_name(
+    conn: &Connection,
+    company_id: &str,
+    counterparty_id: &str,
+) -> Result<String, String> {
+    let counterparty = company_counterparty(conn, company_id, counterparty_id)?;
+    if !counterparty.is_active {
+        return Err(format!("Counterparty {} is inactive", counterparty.name));
+    }
+    Ok(counterparty.name)
+}
+
+/// A counterparty of the company, active or not.
+pub fn company_counterparty(
+    conn: &Connection,
+    company_id: &str,
+    counterparty_id: &str,
+) -> Result<Counterparty, String> {
+    let counterparty = fetch_counterparty(conn, counterparty_id)?;
+    if *counterparty.company_id != *company_id {
+        return Err("Counterparty does not belong to company".to_string());
+    }
+    Ok(counterparty)
+}
+
+/// Validates a Swedish organisation or personal number (10 or 12 digits,
+/// optionally with `-` or `+` before the last four) with the Luhn check.
+pub fn normalize_org_number(value: &str) -> Result<String, String> {
+    let compact: String = value.chars().filter(|ch| !ch.is_whitespace()).collect();
+    let separator = match compact.len().checked_sub(5).and_then(|index| compact.get(index..index + 1)) {
+        Some("+") => "+",
+        _ => "-",
+    };
+    let digits: String = compact.chars().filter(|ch| *ch != '-' && *ch != '+').collect();
+    if !digits.chars().all(|ch| ch.is_ascii_digit()) || (digits.len() != 10 && digits.len() != 12) {
+        return Err(format!("Invalid organisation number: {}", value));
+    }
+    let last_ten = &digits[digits.len() - 10..];
+    if luhn_check_digit(&last_ten[..9]) != last_ten[9..].parse::<u32>().unwrap_or(10) {
+        return Err(format!("Invalid check digit in organisation number: {}", value));
+    }
+    let split = digits.len() - 4;
+    Ok(format!("{}{}{}", &digits[..split], separator, &digits[split..]))
+}
+
+/// Normalizes a VAT registration number. Swedish numbers (`SE` + org number +
+/// `01`) are Luhn-checked and must match the org number when both are given.
+fn normalize_vat_number(value: &str, org_number: Option<&str>) -> Result<String, String> {
+    let compact: String = value
+        .chars()
+        .filter(|ch| !ch.is_whitespace() && *ch != '-')
+        .collect::<String>()
+        .to_uppercase();
+    let invalid = || format!("Invalid VAT number: {}", value);
+    let (country, number) = compact.split_at_checked(2).ok_or_else(invalid)?;
+    if !country.chars().all(|ch| ch.is_ascii_alphabetic())
+        || number.is_empty()
+        || !number.chars().all(|ch| ch.is_ascii_alphanumeric())
+    {
+        return Err(invalid());
+    }
+    if country == "SE" {
+        if number.len() != 12 || !number.ends_with("01") {
+            return Err(invalid());
+        }
+        let org_digits = normalize_org_number(&number[..10])?.replace('-', "");
+        if let Some(org_number) = org_number {
+            let expected: String = org_number.chars().filter(|ch| ch.is_ascii_digit()).collect();
+            if !expected.ends_with(&org_digits) {
+                return Err("VAT number does not match the organisation number".to_string());
+            }
+        }
+    }
+    Ok(compact)
+}
+
+fn fetch_counterparty(conn: &Connection, counterparty_id: &str) -> Result<Counterparty, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM counterparties WHERE id = ?1", COUNTERPARTY_COLUMNS),
+        [counterparty_id],
+        counterparty_from_row,
+    )
+    .optional()
+    .map_err(map_sql_error)?
+    .ok_or_else(|| "Counterparty not found".to_string())
+}
+
+fn counterparty_from_row(row: &Row) -> rusqlite::Result<Counterparty> {
+    Ok(Counterparty {
+        id: row.get(0)?,
+        company_id: row.get(1)?,
+        name: row.get(2)?,
+        org_number: row.get(3)?,
+        vat_number: row.get(4)?,
+        address: row.get(5)?,
+        default_revenue_account_id: row.get(6)?,
+        default_expense_account_id: row.get(7)?,
+        is_active: row.get::<_, i64>(8)? == 1,
+        created_at: row.get(9)?,
+    })
+}
@@ -0,0 +1,944 @@
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use thiserror::Error;
+
+use crate::ids::{AccountId, SeriesId};
+
+/// Recorded as `created_by` for data written by the app itself, e.g. seeding.
+const SYSTEM_USER: &str = "system";
+
+pub const DATABASE_FILE: &str = "bokforing.sqlite";
+/// Stored in `PRAGMA user_version`. Bump when a migration is added so that
+/// backups from newer app versions are refused on restore.
+pub const SCHEMA_VERSION: i64 = 5;
+/// First bytes of every unencrypted SQLite file. SQLCipher encrypts the
+/// header too, so anything else means the file is encrypted.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+/// How long a reader waits on a lock, e.g. while the writer checkpoints the
+/// WAL, before giving up with "database is locked".
+const READER_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MIGRATIONS: &str = r#"
+CREATE TABLE IF NOT EXISTS companies (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  org_number TEXT,
+  fiscal_year_start TEXT NOT NULL,
+  fiscal_year_end TEXT NOT NULL,
+  created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS accounts (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  number INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  type TEXT NOT NULL,
+  vat_code TEXT,
+  is_active INTEGER NOT NULL,
+  created_at TEXT NOT NULL,
+  FOREIGN KEY(company_id) REFERENCES companies(id)
+);
+
+CREATE TABLE IF NOT EXISTS voucher_series (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  code TEXT NOT NULL,
+  description TEXT NOT NULL,
+  next_number INTEGER NOT NULL,
+  FOREIGN KEY(company_id) REFERENCES companies(id)
+);
+
+CREATE TABLE IF NOT EXISTS vouchers (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  series_id TEXT NOT NULL,
+  voucher_number INTEGER NOT NULL,
+  date TEXT NOT NULL,
+  description TEXT NOT NULL,
+  counterparty TEXT,
+  created_at TEXT NOT NULL,
+  created_by TEXT NOT NULL,
+  posted_at TEXT,
+  corrected_voucher_id TEXT,
+  FOREIGN KEY(company_id) REFERENCES companies(id),
+  FOREIGN KEY(series_id) REFERENCES voucher_series(id)
+);
+
+CREATE TABLE IF NOT EXISTS voucher_rows (
+  id TEXT PRIMARY KEY,
+  voucher_id TEXT NOT NULL,
+  account_id TEXT NOT NULL,
+  description TEXT,
+  debit_cents INTEGER NOT NULL,
+  credit_cents INTEGER NOT NULL,
+  vat_code TEXT,
+  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
+);
+
+CREATE TABLE IF NOT EXISTS attachments (
+  id TEXT PRIMARY KEY,
+  voucher_id TEXT NOT NULL,
+  ref_type TEXT NOT NULL,
+  ref_value TEXT NOT NULL,
+  note TEXT,
+  created_at TEXT NOT NULL,
+  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
+);
+
+CREATE TABLE IF NOT EXISTS period_locks (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  period_start TEXT NOT NULL,
+  period_end TEXT NOT NULL,
+  locked_at TEXT NOT NULL,
+  locked_by TEXT NOT NULL,
+  FOREIGN KEY(company_id) REFERENCES companies(id)
+);
+
+CREATE TABLE IF NOT EXISTS inbox_documents (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  file_name TEXT NOT NULL,
+  stored_path TEXT NOT NULL,
+  size_bytes INTEGER NOT NULL,
+  note TEXT,
+  received_at TEXT NOT NULL,
+  received_by TEXT NOT NULL,
+  voucher_id TEXT,
+  linked_at TEXT,
+  FOREIGN KEY(company_id) REFERENCES companies(id),
+  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
+);
+
+CREATE TABLE IF NOT EXISTS bank_transactions (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  account_id TEXT NOT NULL,
+  booking_date TEXT NOT NULL,
+  value_date TEXT,
+  amount_cents INTEGER NOT NULL,
+  description TEXT NOT NULL,
+  reference TEXT,
+  counterparty TEXT,
+  fingerprint TEXT NOT NULL,
+  source_format TEXT NOT NULL,
+  imported_at TEXT NOT NULL,
+  imported_by TEXT NOT NULL,
+  voucher_id TEXT,
+  matched_at TEXT,
+  FOREIGN KEY(company_id) REFERENCES companies(id),
+  FOREIGN KEY(account_id) REFERENCES accounts(id),
+  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_bank_transactions_fingerprint
+  ON bank_transactions(account_id, fingerprint);
+
+CREATE TABLE IF NOT EXISTS bank_rules (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  match_text TEXT NOT NULL,
+  counter_account_id TEXT NOT NULL,
+  vat_account_id TEXT,
+  vat_rate_percent INTEGER,
+  is_active INTEGER NOT NULL,
+  created_at TEXT NOT NULL,
+  FOREIGN KEY(company_id) REFERENCES companies(id),
+  FOREIGN KEY(counter_account_id) REFERENCES accounts(id)
+);
+
+CREATE TABLE IF NOT EXISTS bank_reconciliations (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  account_id TEXT NOT NULL,
+  period TEXT NOT NULL,
+  statement_date TEXT NOT NULL,
+  statement_balance_cents INTEGER NOT NULL,
+  ledger_balance_cents INTEGER NOT NULL,
+  difference_cents INTEGER NOT NULL,
+  unmatched_ledger_count INTEGER NOT NULL,
+  unmatched_statement_count INTEGER NOT NULL,
+  note TEXT,
+  signed_off_at TEXT NOT NULL,
+  signed_off_by TEXT NOT NULL,
+  UNIQUE(company_id, account_id, period),
+  FOREIGN KEY(company_id) REFERENCES companies(id),
+  FOREIGN KEY(account_id) REFERENCES accounts(id)
+);
+
+CREATE TABLE IF NOT EXISTS voucher_templates (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  description TEXT NOT NULL,
+  is_active INTEGER NOT NULL,
+  created_at TEXT NOT NULL,
+  FOREIGN KEY(company_id) REFERENCES companies(id)
+);
+
+CREATE TABLE IF NOT EXISTS voucher_template_rows (
+  id TEXT PRIMARY KEY,
+  template_id TEXT NOT NULL,
+  sort_order INTEGER NOT NULL,
+  account_id TEXT NOT NULL,
+  description TEXT,
+  side TEXT NOT NULL,
+  percent_basis_points INTEGER,
+  fixed_cents INTEGER,
+  vat_code TEXT,
+  FOREIGN KEY(template_id) REFERENCES voucher_templates(id),
+  FOREIGN KEY(account_id) REFERENCES accounts(id)
+);
+
+CREATE TABLE IF NOT EXISTS recurring_schedules (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  template_id TEXT NOT NULL,
+  series_id TEXT NOT NULL,
+  total_cents INTEGER NOT NULL,
+  day_of_month INTEGER NOT NULL,
+  next_run_date TEXT NOT NULL,
+  end_date TEXT,
+  is_active INTEGER NOT NULL,
+  created_at TEXT NOT NULL,
+  FOREIGN KEY(company_id) REFERENCES companies(id),
+  FOREIGN KEY(template_id) REFERENCES voucher_templates(id),
+  FOREIGN KEY(series_id) REFERENCES voucher_series(id)
+);
+
+CREATE TABLE IF NOT EXISTS customers (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  customer_number INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  org_number TEXT,
+  email TEXT,
+  address TEXT,
+  payment_terms_days INTEGER NOT NULL,
+  created_at TEXT NOT NULL,
+  UNIQUE(company_id, customer_number),
+  FOREIGN KEY(company_id) REFERENCES companies(id)
+);
+
+CREATE TABLE IF NOT EXISTS customer_invoices (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  customer_id TEXT NOT NULL,
+  invoice_number INTEGER NOT NULL,
+  invoice_date TEXT NOT NULL,
+  due_date TEXT NOT NULL,
+  ocr_number TEXT NOT NULL,
+  net_cents INTEGER NOT NULL,
+  vat_cents INTEGER NOT NULL,
+  total_cents INTEGER NOT NULL,
+  voucher_id TEXT,
+  created_at TEXT NOT NULL,
+  created_by TEXT NOT NULL,
+  UNIQUE(company_id, invoice_number),
+  FOREIGN KEY(company_id) REFERENCES companies(id),
+  FOREIGN KEY(customer_id) REFERENCES customers(id),
+  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
+);
+
+CREATE TABLE IF NOT EXISTS customer_invoice_lines (
+  id TEXT PRIMARY KEY,
+  invoice_id TEXT NOT NULL,
+  sort_order INTEGER NOT NULL,
+  description TEXT NOT NULL,
+  quantity REAL NOT NULL,
+  unit_price_cents INTEGER NOT NULL,
+  vat_rate_percent INTEGER NOT NULL,
+  revenue_account_id TEXT NOT NULL,
+  net_cents INTEGER NOT NULL,
+  vat_cents INTEGER NOT NULL,
+  FOREIGN KEY(invoice_id) REFERENCES customer_invoices(id),
+  FOREIGN KEY(revenue_account_id) REFERENCES accounts(id)
+);
+
+CREATE TABLE IF NOT EXISTS customer_payments (
+  id TEXT PRIMARY KEY,
+  invoice_id TEXT NOT NULL,
+  payment_date TEXT NOT NULL,
+  amount_cents INTEGER NOT NULL,
+  voucher_id TEXT,
+  created_at TEXT NOT NULL,
+  created_by TEXT NOT NULL,
+  FOREIGN KEY(invoice_id) REFERENCES customer_invoices(id),
+  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
+);
+
+CREATE TABLE IF NOT EXISTS suppliers (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  supplier_number INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  org_number TEXT,
+  bankgiro TEXT,
+  default_expense_account_id TEXT,
+  payment_terms_days INTEGER NOT NULL,
+  created_at TEXT NOT NULL,
+  UNIQUE(company_id, supplier_number),
+  FOREIGN KEY(company_id) REFERENCES companies(id)
+);
+
+CREATE TABLE IF NOT EXISTS supplier_invoices (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  supplier_id TEXT NOT NULL,
+  invoice_number TEXT NOT NULL,
+  invoice_date TEXT NOT NULL,
+  due_date TEXT NOT NULL,
+  payment_reference TEXT,
+  net_cents INTEGER NOT NULL,
+  vat_cents INTEGER NOT NULL,
+  total_cents INTEGER NOT NULL,
+  voucher_id TEXT,
+  created_at TEXT NOT NULL,
+  created_by TEXT NOT NULL,
+  UNIQUE(supplier_id, invoice_number),
+  FOREIGN KEY(company_id) REFERENCES companies(id),
+  FOREIGN KEY(supplier_id) REFERENCES suppliers(id),
+  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
+);
+
+CREATE TABLE IF NOT EXISTS supplier_invoice_lines (
+  id TEXT PRIMARY KEY,
+  invoice_id TEXT NOT NULL,
+  sort_order INTEGER NOT NULL,
+  description TEXT,
+  expense_account_id TEXT NOT NULL,
+  net_cents INTEGER NOT NULL,
+  vat_cents INTEGER NOT NULL,
+  FOREIGN KEY(invoice_id) REFERENCES supplier_invoices(id),
+  FOREIGN KEY(expense_account_id) REFERENCES accounts(id)
+);
+
+CREATE TABLE IF NOT EXISTS supplier_payments (
+  id TEXT PRIMARY KEY,
+  invoice_id TEXT NOT NULL,
+  payment_date TEXT NOT NULL,
+  amount_cents INTEGER NOT NULL,
+  voucher_id TEXT,
+  created_at TEXT NOT NULL,
+  created_by TEXT NOT NULL,
+  FOREIGN KEY(invoice_id) REFERENCES supplier_invoices(id),
+  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
+);
+
+CREATE TABLE IF NOT EXISTS counterparties (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  org_number TEXT,
+  vat_number TEXT,
+  address TEXT,
+  default_revenue_account_id TEXT,
+  default_expense_account_id TEXT,
+  is_active INTEGER NOT NULL DEFAULT 1,
+  created_at TEXT NOT NULL,
+  UNIQUE(company_id, org_number),
+  FOREIGN KEY(company_id) REFERENCES companies(id),
+  FOREIGN KEY(default_revenue_account_id) REFERENCES accounts(id),
+  FOREIGN KEY(default_expense_account_id) REFERENCES accounts(id)
+);
+
+CREATE TABLE IF NOT EXISTS cash_year_end_bookings (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  year_end_date TEXT NOT NULL,
+  voucher_id TEXT NOT NULL,
+  reversal_voucher_id TEXT NOT NULL,
+  receivables_cents INTEGER NOT NULL,
+  payables_cents INTEGER NOT NULL,
+  created_at TEXT NOT NULL,
+  created_by TEXT NOT NULL,
+  UNIQUE(company_id, year_end_date),
+  FOREIGN KEY(company_id) REFERENCES companies(id),
+  FOREIGN KEY(voucher_id) REFERENCES vouchers(id),
+  FOREIGN KEY(reversal_voucher_id) REFERENCES vouchers(id)
+);
+
+CREATE TABLE IF NOT EXISTS users (
+  id TEXT PRIMARY KEY,
+  username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+  display_name TEXT NOT NULL,
+  password_hash TEXT NOT NULL,
+  is_active INTEGER NOT NULL DEFAULT 1,
+  created_at TEXT NOT NULL,
+  created_by TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_sessions (
+  id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  logged_in_at TEXT NOT NULL,
+  logged_out_at TEXT,
+  FOREIGN KEY(user_id) REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS permission_denials (
+  id TEXT PRIMARY KEY,
+  username TEXT NOT NULL,
+  role TEXT NOT NULL,
+  command TEXT NOT NULL,
+  required_role TEXT NOT NULL,
+  created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS voucher_approvals (
+  id TEXT PRIMARY KEY,
+  voucher_id TEXT NOT NULL UNIQUE,
+  decision TEXT NOT NULL,
+  comment TEXT,
+  decided_at TEXT NOT NULL,
+  decided_by TEXT NOT NULL,
+  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
+);
+
+CREATE TABLE IF NOT EXISTS api_tokens (
+  id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  created_at TEXT NOT NULL,
+  created_by TEXT NOT NULL,
+  last_used_at TEXT,
+  revoked_at TEXT,
+  FOREIGN KEY(user_id) REFERENCES users(id)
+);
+
+CREATE TABLE IF NOT EXISTS backup_settings (
+  id INTEGER PRIMARY KEY CHECK (id = 1),
+  enabled INTEGER NOT NULL,
+  interval_hours INTEGER NOT NULL,
+  retention_count INTEGER NOT NULL,
+  directory TEXT,
+  include_attachments INTEGER NOT NULL,
+  last_backup_at TEXT,
+  last_error TEXT
+);
+
+CREATE TABLE IF NOT EXISTS audit_log (
+  id TEXT PRIMARY KEY,
+  company_id TEXT NOT NULL,
+  entity_type TEXT NOT NULL,
+  entity_id TEXT NOT NULL,
+  action TEXT NOT NULL,
+  payload_json TEXT NOT NULL,
+  created_at TEXT NOT NULL,
+  created_by TEXT NOT NULL,
+  FOREIGN KEY(company_id) REFERENCES companies(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_voucher_rows_voucher ON voucher_rows(voucher_id);
+CREATE INDEX IF NOT EXISTS idx_vouchers_company_date ON vouchers(company_id, date);
+CREATE INDEX IF NOT EXISTS idx_accounts_company_number ON accounts(company_id, number);
+"#;
+
+/// Columns added to tables after their first release. `CREATE TABLE IF NOT
+/// EXISTS` leaves existing tables alone, so these are added when missing.
+const COLUMN_MIGRATIONS: &[(&str, &str, &str)] = &[
+    ("companies", "accounting_method", "TEXT NOT NULL DEFAULT 'accrual'"),
+    ("vouchers", "counterparty_id", "TEXT REFERENCES counterparties(id)"),
+    // Users created before roles existed keep full access.
+    ("users", "role", "TEXT NOT NULL DEFAULT 'admin'"),
+    ("companies", "requires_approval", "INTEGER NOT NULL DEFAULT 0"),
+    ("companies", "allow_zero_rows", "INTEGER NOT NULL DEFAULT 0"),
+    ("recurring_schedules", "start_date", "TEXT"),
+    ("recurring_schedules", "last_run_date", "TEXT"),
+];
+
+/// Fills `recurring_schedules.last_run_date`, added in schema version 4, from
+/// the runs recorded in the audit log.
+const BACKFILL_LAST_RUN_DATE: &str = r#"
+UPDATE recurring_schedules SET last_run_date = (
+  SELECT MAX(json_extract(payload_json, '$.date')) FROM audit_log
+  WHERE entity_type = 'recurring_schedule' AND entity_id = recurring_schedules.id AND action = 'run'
+) WHERE last_run_date IS NULL;
+"#;
+
+/// Date columns, rewritten as `YYYY-MM-DD` in schema version 5 so that they
+/// compare correctly as text, e.g. in `BETWEEN`.
+const DATE_COLUMNS: &[(&str, &str)] = &[
+    ("companies", "fiscal_year_start"),
+    ("companies", "fiscal_year_end"),
+    ("vouchers", "date"),
+    ("period_locks", "period_start"),
+    ("period_locks", "period_end"),
+    ("bank_transactions", "booking_date"),
+    ("bank_transactions", "value_date"),
+    ("bank_reconciliations", "statement_date"),
+    ("recurring_schedules", "next_run_date"),
+    ("recurring_schedules", "end_date"),
+    ("recurring_schedules", "start_date"),
+    ("recurring_schedules", "last_run_date"),
+    ("customer_invoices", "invoice_date"),
+    ("customer_invoices", "due_date"),
+    ("customer_payments", "payment_date"),
+    ("supplier_invoices", "invoice_date"),
+    ("supplier_invoices", "due_date"),
+    ("supplier_payments", "payment_date"),
+    ("cash_year_end_bookings", "year_end_date"),
+];
+
+/// Faktureringsmetoden: invoices are booked on the invoice date.
+pub const ACCRUAL_METHOD: &str = "accrual";
+/// Kontantmetoden: invoices are booked when paid and unpaid invoices are
+/// booked at year end.
+pub const CASH_METHOD: &str = "cash";
+
+#[derive(Debug, Error)]
+pub enum DbError {
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid operation: {0}")]
+    Invalid(String),
+    #[error("The database is encrypted; enter the passphrase to open it")]
+    PassphraseRequired,
+    #[error("Wrong database passphrase")]
+    WrongPassphrase,
+}
+
+/// User roles, each including the permissions of the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Reports and exports only.
+    Viewer,
+    /// Registers vouchers, invoices, payments and bank transactions.
+    Bookkeeper,
+    /// Posts vouchers, locks periods and signs off reconciliations.
+    Approver,
+    /// Manages users, companies, the chart of accounts and period unlocks.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Bookkeeper => "bookkeeper",
+            Role::Approver => "approver",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Role, String> {
+        match value {
+            "viewer" => Ok(Role::Viewer),
+            "bookkeeper" => Ok(Role::Bookkeeper),
+            "approver" => Ok(Role::Approver),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("Unknown role: {}", value)),
+        }
+    }
+}
+
+/// Opens the database at `path`, creating it if needed, and brings the schema
+/// up to date. `passphrase` is the SQLCipher key of an encrypted file.
+pub fn open_database(path: &Path, passphrase: Option<&str>) -> Result<Connection, DbError> {
+    let mut conn = open_connection(path, passphrase)?;
+    init_schema(&mut conn)?;
+    Ok(conn)
+}
+
+/// Migrates `conn` and seeds the demo company into an empty database. Works
+/// on any connection, e.g. `Connection::open_in_memory()` in tests.
+pub fn init_schema(conn: &mut Connection) -> Result<(), DbError> {
+    migrate(conn)?;
+    seed_if_needed(conn)
+}
+
+/// Imported documents are stored in a directory next to the database.
+pub fn documents_dir(database_path: &Path) -> PathBuf {
+    database_path.with_file_name("documents")
+}
+
+/// Creates missing tables and columns and records the schema version.
+pub fn migrate(conn: &Connection) -> Result<(), DbError> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    conn.execute_batch(MIGRATIONS)?;
+    apply_column_migrations(conn)?;
+    if version < 4 {
+        conn.execute_batch(BACKFILL_LAST_RUN_DATE)?;
+    }
+    if version < 5 {
+        normalize_dates(conn)?;
+    }
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    Ok(())
+}
+
+/// Rewrites the `DATE_COLUMNS` as `YYYY-MM-DD`, e.g. "2024-9-30" as
+/// "2024-09-30". Nothing is changed if a value is not a date; the error lists
+/// every such row so that it can be corrected by hand.
+fn normalize_dates(conn: &Connection) -> Result<(), DbError> {
+    let tx = conn.unchecked_transaction()?;
+    let mut invalid = Vec::new();
+    for (table, column) in DATE_COLUMNS {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT rowid, {} FROM {} WHERE {} IS NOT NULL",
+            column, table, column
+        ))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(stmt);
+        for (rowid, value) in rows {
+            match NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+                Ok(date) => {
+                    let normalized = date.format("%Y-%m-%d").to_string();
+                    if normalized != value {
+                        tx.execute(
+                            &format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", table, column),
+                            params![normalized, rowid],
+                        )?;
+                    }
+                }
+                Err(_) => {
+                    invalid.push(format!("{}.{} row {}: '{}'", table, column, rowid, value))
+                }
+            }
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(DbError::Invalid(format!(
+            "The database has dates that are not valid; correct them and open it again:\n{}",
+            invalid.join("\n")
+        )));
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Closes `conn`, moves `replacement` into place at `path` and reopens it
+/// there with `passphrase`. The current file is kept at `previous` when
+/// given, otherwise it is overwritten.
+///
+/// On failure the original file is put back and reopened with
+/// `current_passphrase`, so `conn` is never left on a placeholder.
+pub fn swap_database(
+    conn: &mut Connection,
+    path: &Path,
+    replacement: &Path,
+    previous: Option<&Path>,
+    passphrase: Option<&str>,
+    current_passphrase: Option<&str>,
+) -> Result<(), DbError> {
+    let old = std::mem::replace(conn, Connection::open_in_memory()?);
+    // Closing the last connection checkpoints the WAL into the main file.
+    if let Err((old, err)) = old.close() {
+        *conn = old;
+        return Err(err.into());
+    }
+    // Without `previous` the original is kept here until the swap succeeded.
+    let kept = previous
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| path.with_extension("swap.sqlite"));
+    let mut moved = false;
+    let mut replaced = false;
+    let swapped = (|| {
+        for suffix in ["-wal", "-shm"] {
+            let mut sidecar = path.as_os_str().to_owned();
+            sidecar.push(suffix);
+            let sidecar = PathBuf::from(sidecar);
+            if sidecar.exists() {
+                fs::remove_file(&sidecar)?;
+            }
+        }
+        fs::rename(path, &kept)?;
+        moved = true;
+        fs::rename(replacement, path)?;
+        replaced = true;
+        open_connection(path, passphrase)
+    })();
+
+    match swapped {
+        Ok(swapped) => {
+            *conn = swapped;
+            if previous.is_none() {
+                fs::remove_file(&kept)?;
+            }
+            Ok(())
+        }
+        Err(err) => {
+            let reopened = (|| {
+                if replaced {
+                    fs::rename(path, replacement)?;
+                }
+                if moved {
+                    fs::rename(&kept, path)?;
+                }
+                open_connection(path, current_passphrase)
+            })();
+            match reopened {
+                Ok(reopened) => {
+                    *conn = reopened;
+                    Err(err)
+                }
+                Err(reopen_err) => Err(DbError::Invalid(format!(
+                    "{}; reopening the original database also failed: {}",
+                    err, reopen_err
+                ))),
+            }
+        }
+    }
+}
+
+/// Whether the database file is encrypted with SQLCipher. A missing or empty
+/// file is not.
+pub fn is_encrypted(path: &Path) -> Result<bool, DbError> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+    let mut header = [0u8; 16];
+    let mut read = 0;
+    while read < header.len() {
+        match file.read(&mut header[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read > 0 && header[..read] != SQLITE_HEADER[..read])
+}
+
+/// Opens a connection, applying the SQLCipher key first when given. SQLCipher
+/// only notices a wrong key on the first read, so the schema is read here.
+pub fn open_connection(path: &Path, passphrase: Option<&str>) -> Result<Connection, DbError> {
+    let conn = Connection::open(path)?;
+    if let Some(passphrase) = passphrase {
+        conn.pragma_update(None, "key", passphrase)?;
+    }
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)) {
+        Ok(_) => {}
+        Err(rusqlite::Error::SqliteFailure(err, _)) if err.code == ErrorCode::NotADatabase => {
+            return Err(if passphrase.is_some() {
+                DbError::WrongPassphrase
+            } else {
+                DbError::PassphraseRequired
+            });
+        }
+        Err(err) => return Err(err.into()),
+    }
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    Ok(conn)
+}
+
+/// Opens a read-only connection next to the writer. The database is in WAL
+/// mode, so readers see the last committed state without waiting for the
+/// writer, and `query_only` turns any write through them into an error.
+pub fn open_reader(path: &Path, passphrase: Option<&str>) -> Result<Connection, DbError> {
+    let conn = open_connection(path, passphrase)?;
+    conn.pragma_update(None, "query_only", true)?;
+    conn.busy_timeout(READER_BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+fn apply_column_migrations(conn: &Connection) -> Result<(), DbError> {
+    for (table, column, definition) in COLUMN_MIGRATIONS {
+        let exists: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1", table),
+            [column],
+            |row| row.get(0),
+        )?;
+        if exists == 0 {
+            conn.execute_batch(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, definition
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+fn seed_if_needed(conn: &mut Connection) -> Result<(), DbError> {
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM companies", [], |row| row.get(0))?;
+    if count > 0 {
+        return Ok(());
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let company_id = uuid::Uuid::new_v4().to_string();
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO companies (id, name, org_number, fiscal_year_start, fiscal_year_end, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            company_id,
+            "Demo AB",
+            Option::<String>::None,
+            "2024-01-01",
+            "2024-12-31",
+            now
+        ],
+    )?;
+
+    tx.execute(
+        "INSERT INTO voucher_series (id, company_id, code, description, next_number)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            company_id,
+            "A",
+            "Main series",
+            1
+        ],
+    )?;
+
+    let accounts = vec![
+        ("1930", "Bankkonto", "Asset"),
+        ("3010", "Försäljning", "Income"),
+        ("4010", "Varuinköp", "Expense"),
+        ("2641", "Ingående moms", "Asset"),
+        ("2611", "Utgående moms", "Liability"),
+    ];
+
+    for (number, name, account_type) in accounts {
+        tx.execute(
+            "INSERT INTO accounts (id, company_id, number, name, type, vat_code, is_active, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                uuid::Uuid::new_v4().to_string(),
+                company_id,
+                number.parse::<i64>().unwrap_or_default(),
+                name,
+                account_type,
+                Option::<String>::None,
+                1,
+                now
+            ],
+        )?;
+    }
+
+    tx.execute(
+        "INSERT INTO audit_log (id, company_id, entity_type, entity_id, action, payload_json, created_at, created_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            company_id,
+            "company",
+            company_id,
+            "seed",
+            "{}",
+            now,
+            SYSTEM_USER
+        ],
+    )?;
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// BAS accounts the app books on automatically, created on first use if the
+/// company's chart of accounts lacks them.
+const BAS_ACCOUNTS: &[(i64, &str, &str)] = &[
+    (1510, "Kundfordringar", "Asset"),
+    (1930, "Bankkonto", "Asset"),
+    (2440, "Leverantörsskulder", "Liability"),
+    (2611, "Utgående moms 25 %", "Liability"),
+    (2621, "Utgående moms 12 %", "Liability"),
+    (2631, "Utgående moms 6 %", "Liability"),
+    (2641, "Ingående moms", "Asset"),
+];
+
+pub fn bas_account_id(
+    conn: &Connection,
+    user: &str,
+    company_id: &str,
+    number: i64,
+) -> Result<AccountId, DbError> {
+    let existing: Option<AccountId> = conn
+        .query_row(
+            "SELECT id FROM accounts WHERE company_id = ?1 AND number = ?2",
+            params![company_id, number],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    let (_, name, account_type) = BAS_ACCOUNTS
+        .iter()
+        .find(|(bas_number, _, _)| *bas_number == number)
+        .ok_or_else(|| DbError::Invalid(format!("Account {} is missing", number)))?;
+    let id = AccountId::generate();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO accounts (id, company_id, number, name, type, vat_code, is_active, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![id, company_id, number, name, account_type, Option::<String>::None, 1, now],
+    )?;
+    conn.execute(
+        "INSERT INTO audit_log (id, company_id, entity_type, entity_id, action, payload_json, created_at, created_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            company_id,
+            "account",
+            id,
+            "create",
+            serde_json::json!({ "number": number, "automatic": true }).to_string(),
+            now,
+            user
+        ],
+    )?;
+    Ok(id)
+}
+
+/// The series vouchers are created in when the caller does not choose one.
+pub fn default_series_id(conn: &Connection, company_id: &str) -> Result<SeriesId, DbError> {
+    conn.query_row(
+        "SELECT id FROM voucher_series WHERE company_id = ?1 ORDER BY code LIMIT 1",
+        [company_id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| DbError::Invalid("Company has no voucher series".to_string()))
+}
+
+pub fn is_period_locked(conn: &Connection, company_id: &str, date: NaiveDate) -> Result<bool, DbError> {
+    // Dates are stored as YYYY-MM-DD since schema version 5, so they compare
+    // correctly as text.
+    let locked: i64 = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM period_locks
+         WHERE company_id = ?1 AND ?2 BETWEEN period_start AND period_end)",
+        params![company_id, date],
+        |row| row.get(0),
+    )?;
+    Ok(locked == 1)
+}
+
+/// Whether the company's vouchers may have rows without an amount, e.g. a
+/// row that only notes an account.
+pub fn allows_zero_rows(conn: &Connection, company_id: &str) -> Result<bool, DbError> {
+    conn.query_row(
+        "SELECT allow_zero_rows FROM companies WHERE id = ?1",
+        [company_id],
+        |row| row.get::<_, i64>(0),
+    )
+    .optional()?
+    .map(|value| value == 1)
+    .ok_or_else(|| DbError::Invalid("Company not found".to_string()))
+}
+
+pub fn accounting_method(conn: &Connection, company_id: &str) -> Result<String, DbError> {
+    conn.query_row(
+        "SELECT accounting_method FROM companies WHERE id = ?1",
+        [company_id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| DbError::Invalid("Company not found".to_string()))
+}
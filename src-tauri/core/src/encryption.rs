@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::db;
+use crate::models::ChangeDatabasePassphraseInput;
+use crate::{map_error, map_sql_error};
+
+const MIN_PASSPHRASE_LENGTH: usize = 8;
+
+/// Encrypts the database at `path`, open as `conn`, changes its passphrase
+/// or, without a new passphrase, decrypts it. The database is exported to a
+/// new file with the new key which then replaces the current one, so the old
+/// file never holds a mix of keys.
+///
+/// Returns the passphrase now in use.
+pub fn change_passphrase(
+    conn: &mut Connection,
+    path: &Path,
+    payload: &ChangeDatabasePassphraseInput,
+) -> Result<Option<String>, String> {
+    let encrypted = db::is_encrypted(path).map_err(map_error)?;
+    let current_passphrase = if encrypted {
+        let current = payload
+            .current_passphrase
+            .as_deref()
+            .ok_or("The current passphrase is required")?;
+        db::open_connection(path, Some(current)).map_err(map_error)?;
+        Some(current)
+    } else {
+        None
+    };
+    let new_passphrase = payload
+        .new_passphrase
+        .as_deref()
+        .filter(|passphrase| !passphrase.is_empty());
+    match new_passphrase {
+        Some(passphrase) if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH => {
+            return Err(format!(
+                "Passphrase must be at least {} characters",
+                MIN_PASSPHRASE_LENGTH
+            ));
+        }
+        None if !encrypted => return Err("The database is not encrypted".to_string()),
+        _ => {}
+    }
+
+    let rekeyed_path = path.with_extension("sqlite.rekey");
+    if rekeyed_path.exists() {
+        fs::remove_file(&rekeyed_path).map_err(|err| err.to_string())?;
+    }
+    export_with_key(conn, &rekeyed_path, new_passphrase.unwrap_or(""))?;
+
+    db::swap_database(
+        conn,
+        path,
+        &rekeyed_path,
+        None,
+        new_passphrase,
+        current_passphrase,
+    )
+    .map_err(map_error)?;
+    Ok(new_passphrase.map(str::to_string))
+}
+
+/// Copies the whole database into a new file encrypted with `key`; an empty
+/// key writes a plain SQLite file.
+fn export_with_key(conn: &Connection, target: &Path, key: &str) -> Result<(), String> {
+    let target = target.to_str().ok_or("Database path is not valid UTF-8")?;
+    conn.execute("ATTACH DATABASE ?1 AS rekeyed KEY ?2", params![target, key])
+        .map_err(map_sql_error)?;
+    let exported = conn
+        .query_row("SELECT sqlcipher_export('rekeyed')", [], |_| Ok(()))
+        .and_then(|_| {
+            // sqlcipher_export does not copy the schema version.
+            let user_version: i64 =
+                conn.query_row("PRAGMA main.user_version", [], |row| row.get(0))?;
+            conn.execute_batch(&format!("PRAGMA rekeyed.user_version = {}", user_version))
+        });
+    conn.execute("DETACH DATABASE rekeyed", [])
+        .map_err(map_sql_error)?;
+    exported.map_err(map_sql_error)
+}
@@ -0,0 +1,132 @@
+//! Ids of the records in the books, one type per kind of record so that a
+//! voucher id cannot be passed where an account id is expected.
+//!
+//! Ids are UUIDs. Deserializing or parsing checks the format and normalizes it
+//! to the lowercase hyphenated form stored in the database; ids read from the
+//! database are taken as they are.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+macro_rules! id_type {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(String);
+
+        impl $name {
+            /// A new random id.
+            pub fn generate() -> $name {
+                $name(Uuid::new_v4().to_string())
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(value: &str) -> Result<$name, String> {
+                parse_uuid(value).map($name)
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<$name, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                value.parse().map_err(serde::de::Error::custom)
+            }
+        }
+
+        impl ToSql for $name {
+            fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+                self.0.to_sql()
+            }
+        }
+
+        impl FromSql for $name {
+            fn column_result(value: ValueRef<'_>) -> FromSqlResult<$name> {
+                String::column_result(value).map($name)
+            }
+        }
+    };
+}
+
+fn parse_uuid(value: &str) -> Result<String, String> {
+    Uuid::parse_str(value.trim())
+        .map(|uuid| uuid.hyphenated().to_string())
+        .map_err(|_| format!("Invalid id: {}", value))
+}
+
+id_type!(CompanyId);
+id_type!(UserId);
+id_type!(PermissionDenialId);
+id_type!(AccountId);
+id_type!(SeriesId);
+id_type!(VoucherId);
+id_type!(VoucherRowId);
+id_type!(AttachmentId);
+id_type!(ApprovalId);
+id_type!(PeriodLockId);
+id_type!(CounterpartyId);
+id_type!(InboxDocumentId);
+id_type!(BankTransactionId);
+id_type!(BankRuleId);
+id_type!(ReconciliationId);
+id_type!(TemplateId);
+id_type!(TemplateRowId);
+id_type!(ScheduleId);
+id_type!(CustomerId);
+id_type!(SupplierId);
+id_type!(
+    /// A customer or a supplier invoice.
+    InvoiceId
+);
+id_type!(InvoiceLineId);
+id_type!(PaymentId);
+id_type!(
+    /// A customer or a supplier, e.g. in an aging report.
+    PartyId
+);
+id_type!(CashYearEndBookingId);
+id_type!(ApiTokenId);
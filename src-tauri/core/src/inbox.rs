@@ -0,0 +1,257 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Row};
+use serde_json::json;
+
+use crate::ids::InboxDocumentId;
+use crate::models::{
+    CreateVoucherFromInboxInput, CompanyIdInput, ImportInboxDocumentsInput, InboxDocument,
+    ListInboxDocumentsInput, UnlinkedDocumentItem, Voucher,
+};
+use crate::{insert_attachment, insert_audit_entry, insert_voucher, map_sql_error, Books};
+
+const INBOX_COLUMNS: &str = "id, company_id, file_name, stored_path, size_bytes, note, received_at, received_by, voucher_id, linked_at";
+
+impl Books<'_> {
+    /// Copies receipts into the documents directory and registers them as pending
+    /// inbox items until they are booked.
+    pub fn import_inbox_documents(
+        &mut self,
+        payload: ImportInboxDocumentsInput,
+    ) -> Result<Vec<InboxDocument>, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        if payload.file_paths.is_empty() {
+            return Err("No files to import".to_string());
+        }
+        let company_exists: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM companies WHERE id = ?1",
+                [&payload.company_id],
+                |row| row.get(0),
+            )
+            .map_err(map_sql_error)?;
+        if company_exists == 0 {
+            return Err("Company not found".to_string());
+        }
+
+        let company_dir = self.documents_dir.join(payload.company_id.as_str());
+        fs::create_dir_all(&company_dir).map_err(|err| err.to_string())?;
+
+        // Files copied so far, removed again if the import fails so that no
+        // document is left behind without its inbox row.
+        let mut copied = Vec::new();
+        let result = (|| {
+            let tx = conn.transaction().map_err(map_sql_error)?;
+            let now = Utc::now().to_rfc3339();
+            let mut documents = Vec::new();
+            for file_path in &payload.file_paths {
+                let source = Path::new(file_path);
+                let file_name = source
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .ok_or_else(|| format!("Invalid file path: {}", file_path))?
+                    .to_string();
+                let id = InboxDocumentId::generate();
+                let target = company_dir.join(format!("{}-{}", id, file_name));
+                copied.push(target.clone());
+                let size_bytes = fs::copy(source, &target)
+                    .map_err(|err| format!("Could not import {}: {}", file_path, err))?;
+                let stored_path = target.to_string_lossy().to_string();
+
+                tx.execute(
+                    "INSERT INTO inbox_documents (id, company_id, file_name, stored_path, size_bytes, note, received_at, received_by)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        id,
+                        &payload.company_id,
+                        &file_name,
+                        &stored_path,
+                        size_bytes as i64,
+                        &payload.note,
+                        now,
+                        user
+                    ],
+                )
+                .map_err(map_sql_error)?;
+                insert_audit_entry(
+                    &tx,
+                    user,
+                    &payload.company_id,
+                    "inbox_document",
+                    &id,
+                    "import",
+                    json!({ "file_name": &file_name, "source_path": file_path }),
+                )?;
+
+                documents.push(InboxDocument {
+                    id,
+                    company_id: payload.company_id.clone(),
+                    file_name,
+                    stored_path,
+                    size_bytes: size_bytes as i64,
+                    note: payload.note.clone(),
+                    received_at: now.clone(),
+                    received_by: user.to_string(),
+                    voucher_id: None,
+                    linked_at: None,
+                });
+            }
+
+            tx.commit().map_err(map_sql_error)?;
+            Ok(documents)
+        })();
+        if result.is_err() {
+            for path in &copied {
+                let _ = fs::remove_file(path);
+            }
+        }
+        result
+    }
+
+    pub fn list_inbox_documents(
+        &mut self,
+        payload: ListInboxDocumentsInput,
+    ) -> Result<Vec<InboxDocument>, String> {
+        let conn = &*self.conn;
+        let mut query = format!(
+            "SELECT {} FROM inbox_documents WHERE company_id = ?1",
+            INBOX_COLUMNS
+        );
+        if !payload.include_linked.unwrap_or(false) {
+            query.push_str(" AND voucher_id IS NULL");
+        }
+        query.push_str(" ORDER BY received_at DESC");
+
+        let mut stmt = conn.prepare(&query).map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], inbox_document_from_row)
+            .map_err(map_sql_error)?;
+        let mut documents = Vec::new();
+        for row in rows {
+            documents.push(row.map_err(map_sql_error)?);
+        }
+        Ok(documents)
+    }
+
+    /// Books an inbox document: creates the voucher and attaches the stored file
+    /// to it in the same transaction.
+    pub fn create_voucher_from_inbox(
+        &mut self,
+        payload: CreateVoucherFromInboxInput,
+    ) -> Result<Voucher, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let document = fetch_inbox_document(&tx, &payload.inbox_document_id)?;
+        if document.voucher_id.is_some() {
+            return Err("Document is already linked to a voucher".to_string());
+        }
+        if document.company_id != payload.voucher.company_id {
+            return Err("Document does not belong to company".to_string());
+        }
+
+        let mut voucher = insert_voucher(&tx, user, &payload.voucher)?;
+        let now = Utc::now().to_rfc3339();
+        let attachment = insert_attachment(
+            &tx,
+            &voucher.id,
+            "Fil",
+            &document.stored_path,
+            Some(document.note.as_deref().unwrap_or(&document.file_name)),
+            &now,
+        )?;
+        tx.execute(
+            "UPDATE inbox_documents SET voucher_id = ?1, linked_at = ?2 WHERE id = ?3",
+            params![&voucher.id, now, &document.id],
+        )
+        .map_err(map_sql_error)?;
+        insert_audit_entry(
+            &tx,
+            user,
+            &document.company_id,
+            "inbox_document",
+            &document.id,
+            "link",
+            json!({ "voucher_id": &voucher.id, "voucher_number": voucher.voucher_number }),
+        )?;
+        tx.commit().map_err(map_sql_error)?;
+
+        if let Some(attachments) = voucher.attachments.as_mut() {
+            attachments.push(attachment);
+        }
+        Ok(voucher)
+    }
+
+    /// Lists documents that were imported but never linked to a voucher, oldest
+    /// first, so forgotten receipts are easy to spot.
+    pub fn report_unlinked_documents(
+        &mut self,
+        payload: CompanyIdInput,
+    ) -> Result<Vec<UnlinkedDocumentItem>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, file_name, stored_path, note, received_at
+                 FROM inbox_documents
+                 WHERE company_id = ?1 AND voucher_id IS NULL
+                 ORDER BY received_at ASC",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], |row| {
+                Ok((
+                    row.get::<_, InboxDocumentId>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })
+            .map_err(map_sql_error)?;
+
+        let now = Utc::now();
+        let mut report = Vec::new();
+        for row in rows {
+            let (id, file_name, stored_path, note, received_at) = row.map_err(map_sql_error)?;
+            let days_pending = DateTime::parse_from_rfc3339(&received_at)
+                .map(|received| (now - received.with_timezone(&Utc)).num_days())
+                .unwrap_or_default();
+            report.push(UnlinkedDocumentItem {
+                id,
+                file_name,
+                stored_path,
+                note,
+                received_at,
+                days_pending,
+            });
+        }
+        Ok(report)
+    }
+}
+
+fn fetch_inbox_document(conn: &Connection, document_id: &str) -> Result<InboxDocument, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM inbox_documents WHERE id = ?1", INBOX_COLUMNS),
+        [document_id],
+        inbox_document_from_row,
+    )
+    .map_err(map_sql_error)
+}
+
+fn inbox_document_from_row(row: &Row) -> rusqlite::Result<InboxDocument> {
+    Ok(InboxDocument {
+        id: row.get(0)?,
+        company_id: row.get(1)?,
+        file_name: row.get(2)?,
+        stored_path: row.get(3)?,
+        size_bytes: row.get(4)?,
+        note: row.get(5)?,
+        received_at: row.get(6)?,
+        received_by: row.get(7)?,
+        voucher_id: row.get(8)?,
+        linked_at: row.get(9)?,
+    })
+}
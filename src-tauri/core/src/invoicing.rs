@@ -0,0 +1,767 @@
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDate, Utc};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde_json::json;
+
+use crate::db::{
+    accounting_method, bas_account_id, default_series_id, CASH_METHOD,
+};
+use crate::models::{
+    AgingReport, AgingReportInput, AgingRow, CompanyIdInput, CreateCustomerInvoiceInput,
+    CreateVoucherInput, CreateVoucherRowInput, Customer, CustomerInvoice, CustomerInvoiceLine,
+    InvoiceIdInput, InvoicePayment, ListInvoicesInput, RegisterPaymentInput, UpsertCustomerInput,
+};
+use crate::ids::{AccountId, CustomerId, InvoiceId, InvoiceLineId, PaymentId};
+use crate::money::Money;
+use crate::{ensure_company, insert_audit_entry, insert_voucher, map_error, map_sql_error, Books};
+
+pub const RECEIVABLES_ACCOUNT_NUMBER: i64 = 1510;
+const DEFAULT_PAYMENT_TERMS_DAYS: i64 = 30;
+const MAX_PAYMENT_TERMS_DAYS: i64 = 365;
+const SUPPORTED_VAT_RATES: &[i64] = &[0, 6, 12, 25];
+
+const INVOICE_QUERY: &str = "SELECT i.id, i.company_id, i.customer_id, c.name, i.invoice_number, i.invoice_date, i.due_date, i.ocr_number, i.net_cents, i.vat_cents, i.total_cents,
+        COALESCE((SELECT SUM(p.amount_cents) FROM customer_payments p WHERE p.invoice_id = i.id), 0),
+        i.voucher_id, i.created_at, i.created_by
+ FROM customer_invoices i
+ JOIN customers c ON c.id = i.customer_id";
+
+impl Books<'_> {
+    pub fn list_customers(&mut self, payload: CompanyIdInput) -> Result<Vec<Customer>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, company_id, customer_number, name, org_number, email, address, payment_terms_days, created_at
+                 FROM customers WHERE company_id = ?1 ORDER BY customer_number",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], customer_from_row)
+            .map_err(map_sql_error)?;
+        let mut customers = Vec::new();
+        for row in rows {
+            customers.push(row.map_err(map_sql_error)?);
+        }
+        Ok(customers)
+    }
+
+    pub fn upsert_customer(&mut self, payload: UpsertCustomerInput) -> Result<Customer, String> {
+        let conn = &mut *self.conn;
+        if payload.name.trim().is_empty() {
+            return Err("Customer name is required".to_string());
+        }
+        let payment_terms_days = payload.payment_terms_days.unwrap_or(DEFAULT_PAYMENT_TERMS_DAYS);
+        if payment_terms_days < 0 {
+            return Err("Payment terms cannot be negative".to_string());
+        }
+        if payment_terms_days > MAX_PAYMENT_TERMS_DAYS {
+            return Err(format!("Payment terms cannot exceed {} days", MAX_PAYMENT_TERMS_DAYS));
+        }
+
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let now = Utc::now().to_rfc3339();
+        let customer_id = payload
+            .id
+            .clone()
+            .unwrap_or_else(CustomerId::generate);
+        ensure_company(&tx, "customers", &customer_id, &payload.company_id, "Customer")?;
+        let next_number: i64 = tx
+            .query_row(
+                "SELECT COALESCE(MAX(customer_number), 0) + 1 FROM customers WHERE company_id = ?1",
+                [&payload.company_id],
+                |row| row.get(0),
+            )
+            .map_err(map_sql_error)?;
+        tx.execute(
+            "INSERT INTO customers (id, company_id, customer_number, name, org_number, email, address, payment_terms_days, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(id) DO UPDATE SET
+               name = excluded.name,
+               org_number = excluded.org_number,
+               email = excluded.email,
+               address = excluded.address,
+               payment_terms_days = excluded.payment_terms_days",
+            params![
+                customer_id,
+                &payload.company_id,
+                next_number,
+                &payload.name,
+                &payload.org_number,
+                &payload.email,
+                &payload.address,
+                payment_terms_days,
+                now
+            ],
+        )
+        .map_err(map_sql_error)?;
+        let customer = tx
+            .query_row(
+                "SELECT id, company_id, customer_number, name, org_number, email, address, payment_terms_days, created_at
+                 FROM customers WHERE id = ?1",
+                [&customer_id],
+                customer_from_row,
+            )
+            .map_err(map_sql_error)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(customer)
+    }
+
+    /// Creates a customer invoice and books the sales voucher: 1510 against the
+    /// revenue accounts and output VAT per rate.
+    pub fn create_customer_invoice(
+        &mut self,
+        payload: CreateCustomerInvoiceInput,
+    ) -> Result<CustomerInvoice, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        if payload.lines.is_empty() {
+            return Err("Invoice must have lines".to_string());
+        }
+        let invoice_date = payload.invoice_date;
+
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let customer = tx
+            .query_row(
+                "SELECT id, company_id, customer_number, name, org_number, email, address, payment_terms_days, created_at
+                 FROM customers WHERE id = ?1",
+                [&payload.customer_id],
+                customer_from_row,
+            )
+            .map_err(map_sql_error)?;
+        if customer.company_id != payload.company_id {
+            return Err("Customer does not belong to company".to_string());
+        }
+        let due_date = match payload.due_date {
+            Some(due_date) => due_date,
+            None => Duration::try_days(customer.payment_terms_days)
+                .and_then(|terms| invoice_date.checked_add_signed(terms))
+                .ok_or("Payment terms are too long")?,
+        };
+        if due_date < invoice_date {
+            return Err("Due date is before invoice date".to_string());
+        }
+
+        let invoice_id = InvoiceId::generate();
+        let mut lines = Vec::new();
+        for (index, line) in payload.lines.iter().enumerate() {
+            if !SUPPORTED_VAT_RATES.contains(&line.vat_rate_percent) {
+                return Err(format!("Row {}: unsupported VAT rate {}%", index + 1, line.vat_rate_percent));
+            }
+            if !line.quantity.is_finite() {
+                return Err(format!("Row {}: invalid quantity", index + 1));
+            }
+            revenue_account_number(&tx, &payload.company_id, &line.revenue_account_id)
+                .map_err(|err| format!("Row {}: {}", index + 1, err))?;
+            let net = line
+                .unit_price
+                .checked_mul_quantity(line.quantity)
+                .ok_or_else(|| format!("Row {}: amount is too large", index + 1))?;
+            let vat = net
+                .percent(line.vat_rate_percent)
+                .ok_or_else(|| format!("Row {}: amount is too large", index + 1))?;
+            lines.push(CustomerInvoiceLine {
+                id: InvoiceLineId::generate(),
+                invoice_id: invoice_id.clone(),
+                description: line.description.clone(),
+                quantity: line.quantity,
+                unit_price: line.unit_price,
+                vat_rate_percent: line.vat_rate_percent,
+                revenue_account_id: line.revenue_account_id.clone(),
+                net,
+                vat,
+            });
+        }
+        let net = Money::checked_sum(lines.iter().map(|line| line.net))?;
+        let vat = Money::checked_sum(lines.iter().map(|line| line.vat))?;
+        let total = Money::checked_sum([net, vat])?;
+        if !total.is_positive() {
+            return Err("Invoice total must be positive".to_string());
+        }
+
+        let invoice_number: i64 = tx
+            .query_row(
+                "SELECT COALESCE(MAX(invoice_number), 0) + 1 FROM customer_invoices WHERE company_id = ?1",
+                [&payload.company_id],
+                |row| row.get(0),
+            )
+            .map_err(map_sql_error)?;
+        let series_id = match &payload.series_id {
+            Some(series_id) => series_id.clone(),
+            None => default_series_id(&tx, &payload.company_id).map_err(map_error)?,
+        };
+
+        // Under kontantmetoden nothing is booked until the invoice is paid.
+        let now = Utc::now().to_rfc3339();
+        let voucher_id = if accounting_method(&tx, &payload.company_id).map_err(map_error)? == CASH_METHOD {
+            None
+        } else {
+            let mut rows = vec![CreateVoucherRowInput {
+                account_id: bas_account_id(&tx, user, &payload.company_id, RECEIVABLES_ACCOUNT_NUMBER)
+                    .map_err(map_error)?,
+                description: None,
+                debit: total,
+                credit: Money::ZERO,
+                vat_code: None,
+            }];
+            for (account_id, amount) in revenue_amounts(&tx, user, &payload.company_id, &lines)? {
+                rows.push(credit_row(account_id, amount)?);
+            }
+            rows.retain(|row| !row.debit.is_zero() || !row.credit.is_zero());
+
+            let voucher = insert_voucher(
+                &tx,
+                user,
+                &CreateVoucherInput {
+                    company_id: payload.company_id.clone(),
+                    series_id,
+                    date: invoice_date,
+                    description: format!("Kundfaktura {} {}", invoice_number, customer.name),
+                    counterparty: Some(customer.name.clone()),
+                    counterparty_id: None,
+                    rows,
+                    attachments: None,
+                },
+            )?;
+            Some(voucher.id)
+        };
+
+        tx.execute(
+            "INSERT INTO customer_invoices (id, company_id, customer_id, invoice_number, invoice_date, due_date, ocr_number, net_cents, vat_cents, total_cents, voucher_id, created_at, created_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                invoice_id,
+                &payload.company_id,
+                &customer.id,
+                invoice_number,
+                invoice_date,
+                due_date,
+                ocr_number(invoice_number),
+                net,
+                vat,
+                total,
+                &voucher_id,
+                now,
+                user
+            ],
+        )
+        .map_err(map_sql_error)?;
+        for (index, line) in lines.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO customer_invoice_lines (id, invoice_id, sort_order, description, quantity, unit_price_cents, vat_rate_percent, revenue_account_id, net_cents, vat_cents)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    line.id,
+                    invoice_id,
+                    index as i64,
+                    line.description,
+                    line.quantity,
+                    line.unit_price,
+                    line.vat_rate_percent,
+                    line.revenue_account_id,
+                    line.net,
+                    line.vat
+                ],
+            )
+            .map_err(map_sql_error)?;
+        }
+        insert_audit_entry(
+            &tx,
+            user,
+            &payload.company_id,
+            "customer_invoice",
+            &invoice_id,
+            "create",
+            json!({
+                "invoice_number": invoice_number,
+                "total_cents": total.cents(),
+                "voucher_id": &voucher_id
+            }),
+        )?;
+
+        let invoice = fetch_invoice(&tx, &invoice_id)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(invoice)
+    }
+
+    pub fn list_customer_invoices(
+        &mut self,
+        payload: ListInvoicesInput,
+    ) -> Result<Vec<CustomerInvoice>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(&format!(
+                "{} WHERE i.company_id = ?1 ORDER BY i.invoice_number DESC",
+                INVOICE_QUERY
+            ))
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], invoice_from_row)
+            .map_err(map_sql_error)?;
+        let open_only = payload.open_only.unwrap_or(false);
+        let mut invoices = Vec::new();
+        for row in rows {
+            let invoice = row.map_err(map_sql_error)?;
+            if !open_only || !invoice.open.is_zero() {
+                invoices.push(invoice);
+            }
+        }
+        Ok(invoices)
+    }
+
+    pub fn get_customer_invoice(
+        &mut self,
+        payload: InvoiceIdInput,
+    ) -> Result<CustomerInvoice, String> {
+        let conn = &*self.conn;
+        fetch_invoice(conn, &payload.invoice_id)
+    }
+
+    /// Registers a (partial) payment and books 1930 against 1510.
+    pub fn register_customer_payment(
+        &mut self,
+        payload: RegisterPaymentInput,
+    ) -> Result<CustomerInvoice, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let payment_date = payload.payment_date;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let invoice = fetch_invoice(&tx, &payload.invoice_id)?;
+        if !payload.amount.is_positive() {
+            return Err("Payment amount must be positive".to_string());
+        }
+        if payload.amount > invoice.open {
+            return Err(format!(
+                "Payment exceeds the open balance of {}",
+                invoice.open
+            ));
+        }
+
+        let series_id = match &payload.series_id {
+            Some(series_id) => series_id.clone(),
+            None => default_series_id(&tx, &invoice.company_id).map_err(map_error)?,
+        };
+        let bank_account_id =
+            bas_account_id(&tx, user, &invoice.company_id, crate::bank::BANK_ACCOUNT_NUMBER)
+                .map_err(map_error)?;
+        let mut rows = vec![CreateVoucherRowInput {
+            account_id: bank_account_id,
+            description: None,
+            debit: payload.amount,
+            credit: Money::ZERO,
+            vat_code: None,
+        }];
+        if invoice.voucher_id.is_some() {
+            rows.push(credit_row(
+                bas_account_id(&tx, user, &invoice.company_id, RECEIVABLES_ACCOUNT_NUMBER)
+                    .map_err(map_error)?,
+                payload.amount,
+            )?);
+        } else {
+            // Invoice booked under kontantmetoden: the payment books its share of
+            // revenue and output VAT.
+            let lines = invoice.lines.as_deref().unwrap_or_default();
+            let amounts = revenue_amounts(&tx, user, &invoice.company_id, lines)?;
+            let paid = invoice
+                .paid
+                .checked_add(payload.amount)
+                .ok_or("Amount is too large")?;
+            let shares = cash_share(&amounts, invoice.total, invoice.paid, paid)?;
+            for (account_id, amount) in shares {
+                rows.push(credit_row(account_id, amount)?);
+            }
+            rows.retain(|row| !row.debit.is_zero() || !row.credit.is_zero());
+        }
+        let voucher = insert_voucher(
+            &tx,
+            user,
+            &CreateVoucherInput {
+                company_id: invoice.company_id.clone(),
+                series_id,
+                date: payment_date,
+                description: format!("Inbetalning kundfaktura {}", invoice.invoice_number),
+                counterparty: Some(invoice.customer_name.clone()),
+                counterparty_id: None,
+                rows,
+                attachments: None,
+            },
+        )?;
+
+        let payment_id = PaymentId::generate();
+        tx.execute(
+            "INSERT INTO customer_payments (id, invoice_id, payment_date, amount_cents, voucher_id, created_at, created_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                payment_id,
+                &invoice.id,
+                payment_date,
+                payload.amount,
+                &voucher.id,
+                &voucher.created_at,
+                user
+            ],
+        )
+        .map_err(map_sql_error)?;
+        insert_audit_entry(
+            &tx,
+            user,
+            &invoice.company_id,
+            "customer_invoice",
+            &invoice.id,
+            "payment",
+            json!({
+                "payment_id": payment_id,
+                "amount_cents": payload.amount.cents(),
+                "voucher_id": &voucher.id
+            }),
+        )?;
+
+        let invoice = fetch_invoice(&tx, &invoice.id)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(invoice)
+    }
+
+    /// Accounts receivable (kundreskontra) aging per `as_of_date`, default today.
+    pub fn report_accounts_receivable(
+        &mut self,
+        payload: AgingReportInput,
+    ) -> Result<AgingReport, String> {
+        let conn = &*self.conn;
+        let as_of_date = payload.as_of_date.unwrap_or_else(|| Utc::now().date_naive());
+        let mut stmt = conn
+            .prepare(
+                "SELECT i.id, i.customer_id, c.name, CAST(i.invoice_number AS TEXT), i.invoice_date, i.due_date, i.total_cents,
+                        i.total_cents - COALESCE((SELECT SUM(p.amount_cents) FROM customer_payments p
+                                                  WHERE p.invoice_id = i.id AND p.payment_date <= ?2), 0)
+                 FROM customer_invoices i
+                 JOIN customers c ON c.id = i.customer_id
+                 WHERE i.company_id = ?1 AND i.invoice_date <= ?2
+                 ORDER BY i.due_date ASC, i.invoice_number ASC",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map(
+                params![payload.company_id, as_of_date],
+                |row| aging_row_from_row(row, as_of_date),
+            )
+            .map_err(map_sql_error)?;
+        let mut aging_rows = Vec::new();
+        for row in rows {
+            aging_rows.push(row.map_err(map_sql_error)?);
+        }
+        summarize_aging(as_of_date, aging_rows)
+    }
+}
+
+/// Checks that the account belongs to the company and is a revenue account
+/// (3000–3999) and returns its number.
+fn revenue_account_number(conn: &Connection, company_id: &str, account_id: &str) -> Result<i64, String> {
+    let number: i64 = conn
+        .query_row(
+            "SELECT number FROM accounts WHERE id = ?1 AND company_id = ?2",
+            params![account_id, company_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(map_sql_error)?
+        .ok_or_else(|| "Account not found".to_string())?;
+    if !(3000..=3999).contains(&number) {
+        return Err(format!("Account {} is not a revenue account (3000-3999)", number));
+    }
+    Ok(number)
+}
+
+/// Maps `id, party_id, party_name, number (text), invoice_date, due_date,
+/// total, open` into an aging row as of the given date.
+pub fn aging_row_from_row(row: &Row, as_of_date: NaiveDate) -> rusqlite::Result<AgingRow> {
+    let due_date: NaiveDate = row.get(5)?;
+    let days_overdue = (as_of_date - due_date).num_days().max(0);
+    Ok(AgingRow {
+        invoice_id: row.get(0)?,
+        party_id: row.get(1)?,
+        party_name: row.get(2)?,
+        invoice_number: row.get(3)?,
+        invoice_date: row.get(4)?,
+        due_date,
+        total: row.get(6)?,
+        open: row.get(7)?,
+        days_overdue,
+        bucket: aging_bucket(days_overdue).to_string(),
+    })
+}
+
+/// Drops settled invoices and sums the open amounts per aging bucket.
+pub fn summarize_aging(
+    as_of_date: NaiveDate,
+    rows: Vec<AgingRow>,
+) -> Result<AgingReport, String> {
+    let mut report = AgingReport {
+        as_of_date,
+        ..AgingReport::default()
+    };
+    for row in rows.into_iter().filter(|row| !row.open.is_zero()) {
+        let bucket_total = match row.bucket.as_str() {
+            "not_due" => &mut report.not_due,
+            "1-30" => &mut report.overdue_1_30,
+            "31-60" => &mut report.overdue_31_60,
+            "61-90" => &mut report.overdue_61_90,
+            _ => &mut report.overdue_over_90,
+        };
+        *bucket_total = Money::checked_sum([*bucket_total, row.open])?;
+        report.total_open = Money::checked_sum([report.total_open, row.open])?;
+        report.rows.push(row);
+    }
+    Ok(report)
+}
+
+fn aging_bucket(days_overdue: i64) -> &'static str {
+    match days_overdue {
+        0 => "not_due",
+        1..=30 => "1-30",
+        31..=60 => "31-60",
+        61..=90 => "61-90",
+        _ => "over_90",
+    }
+}
+
+/// Swedish OCR reference: invoice number, a length digit and a Luhn check digit.
+pub fn ocr_number(invoice_number: i64) -> String {
+    let base = invoice_number.to_string();
+    let with_length = format!("{}{}", base, (base.len() + 2) % 10);
+    format!("{}{}", with_length, luhn_check_digit(&with_length))
+}
+
+/// Luhn (modulus 10) check digit for a string of digits.
+pub fn luhn_check_digit(digits: &str) -> u32 {
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|ch| ch.to_digit(10))
+        .enumerate()
+        .map(|(index, digit)| {
+            if index % 2 == 0 {
+                let doubled = digit * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                digit
+            }
+        })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+pub fn output_vat_account(rate: i64) -> Result<i64, String> {
+    match rate {
+        25 => Ok(2611),
+        12 => Ok(2621),
+        6 => Ok(2631),
+        _ => Err(format!("No output VAT account for {}%", rate)),
+    }
+}
+
+/// A credit row, or a debit row when the amount is negative (e.g. discounts).
+pub fn credit_row(account_id: AccountId, amount: Money) -> Result<CreateVoucherRowInput, String> {
+    let negated = amount.checked_neg().ok_or("Amount is too large")?;
+    Ok(CreateVoucherRowInput {
+        account_id,
+        description: None,
+        debit: negated.max(Money::ZERO),
+        credit: amount.max(Money::ZERO),
+        vat_code: None,
+    })
+}
+
+/// Revenue per account and output VAT per rate account, as signed credit
+/// amounts that add up to the invoice total.
+pub fn revenue_amounts(
+    conn: &Connection,
+    user: &str,
+    company_id: &str,
+    lines: &[CustomerInvoiceLine],
+) -> Result<Vec<(AccountId, Money)>, String> {
+    let mut revenue: BTreeMap<&AccountId, Money> = BTreeMap::new();
+    let mut vat: BTreeMap<i64, Money> = BTreeMap::new();
+    for line in lines {
+        let amount = revenue.entry(&line.revenue_account_id).or_default();
+        *amount = Money::checked_sum([*amount, line.net])?;
+        let amount = vat.entry(line.vat_rate_percent).or_default();
+        *amount = Money::checked_sum([*amount, line.vat])?;
+    }
+    let mut amounts: Vec<(AccountId, Money)> = revenue
+        .into_iter()
+        .map(|(account_id, amount)| (account_id.clone(), amount))
+        .collect();
+    for (rate, amount) in vat {
+        if amount.is_zero() {
+            continue;
+        }
+        let account_id = bas_account_id(conn, user, company_id, output_vat_account(rate)?)
+            .map_err(map_error)?;
+        amounts.push((account_id, amount));
+    }
+    Ok(amounts)
+}
+
+/// The part of `amounts` (summing to `total`) that belongs to the payments
+/// between `from` and `to` paid. Shares are computed cumulatively, so all
+/// payments of an invoice add up to `amounts` exactly.
+pub fn cash_share(
+    amounts: &[(AccountId, Money)],
+    total: Money,
+    from: Money,
+    to: Money,
+) -> Result<Vec<(AccountId, Money)>, String> {
+    let before = prorate(amounts, total, from)?;
+    let after = prorate(amounts, total, to)?;
+    amounts
+        .iter()
+        .zip(before.iter().zip(after.iter()))
+        .map(|((account_id, _), (before, after))| {
+            let share = after.checked_sub(*before).ok_or("Amount is too large")?;
+            Ok((account_id.clone(), share))
+        })
+        .collect()
+}
+
+/// Splits `paid` over `amounts` in proportion, with the rounding remainder on
+/// the largest amount.
+fn prorate(
+    amounts: &[(AccountId, Money)],
+    total: Money,
+    paid: Money,
+) -> Result<Vec<Money>, String> {
+    if total.is_zero() || paid == total {
+        return Ok(amounts.iter().map(|(_, amount)| *amount).collect());
+    }
+    let total = total.cents() as i128;
+    let mut shares = amounts
+        .iter()
+        .map(|(_, amount)| {
+            let share = amount.cents() as i128 * paid.cents() as i128 * 2 + total;
+            i64::try_from(share.div_euclid(2 * total))
+                .map(Money::from_cents)
+                .map_err(|_| "Amount is too large".to_string())
+        })
+        .collect::<Result<Vec<Money>, String>>()?;
+    let remainder = paid
+        .checked_sub(Money::checked_sum(shares.iter().copied())?)
+        .ok_or("Amount is too large")?;
+    let largest = (0..amounts.len()).max_by_key(|index| amounts[*index].1.cents().unsigned_abs());
+    if let Some(largest) = largest {
+        shares[largest] = shares[largest].checked_post(remainder, Money::ZERO)?;
+    }
+    Ok(shares)
+}
+
+pub fn fetch_invoice(conn: &Connection, invoice_id: &str) -> Result<CustomerInvoice, String> {
+    let mut invoice = conn
+        .query_row(&format!("{} WHERE i.id = ?1", INVOICE_QUERY), [invoice_id], invoice_from_row)
+        .map_err(map_sql_error)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, invoice_id, description, quantity, unit_price_cents, vat_rate_percent, revenue_account_id, net_cents, vat_cents
+             FROM customer_invoice_lines WHERE invoice_id = ?1 ORDER BY sort_order",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([invoice_id], |row| {
+            Ok(CustomerInvoiceLine {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                description: row.get(2)?,
+                quantity: row.get(3)?,
+                unit_price: row.get(4)?,
+                vat_rate_percent: row.get(5)?,
+                revenue_account_id: row.get(6)?,
+                net: row.get(7)?,
+                vat: row.get(8)?,
+            })
+        })
+        .map_err(map_sql_error)?;
+    let mut lines = Vec::new();
+    for row in rows {
+        lines.push(row.map_err(map_sql_error)?);
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, invoice_id, payment_date, amount_cents, voucher_id, created_at, created_by
+             FROM customer_payments WHERE invoice_id = ?1 ORDER BY payment_date",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([invoice_id], payment_from_row)
+        .map_err(map_sql_error)?;
+    let mut payments = Vec::new();
+    for row in rows {
+        payments.push(row.map_err(map_sql_error)?);
+    }
+
+    invoice.lines = Some(lines);
+    invoice.payments = Some(payments);
+    Ok(invoice)
+}
+
+/// `total - paid` of an invoice row, with `paid` read from column 11.
+pub fn open_amount(total: Money, paid: Money) -> rusqlite::Result<Money> {
+    total.checked_sub(paid).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(11, Type::Integer, "Amount is too large".into())
+    })
+}
+
+fn invoice_from_row(row: &Row) -> rusqlite::Result<CustomerInvoice> {
+    let total: Money = row.get(10)?;
+    let paid: Money = row.get(11)?;
+    Ok(CustomerInvoice {
+        id: row.get(0)?,
+        company_id: row.get(1)?,
+        customer_id: row.get(2)?,
+        customer_name: row.get(3)?,
+        invoice_number: row.get(4)?,
+        invoice_date: row.get(5)?,
+        due_date: row.get(6)?,
+        ocr_number: row.get(7)?,
+        net: row.get(8)?,
+        vat: row.get(9)?,
+        total,
+        paid,
+        open: open_amount(total, paid)?,
+        voucher_id: row.get(12)?,
+        created_at: row.get(13)?,
+        created_by: row.get(14)?,
+        lines: None,
+        payments: None,
+    })
+}
+
+pub fn payment_from_row(row: &Row) -> rusqlite::Result<InvoicePayment> {
+    Ok(InvoicePayment {
+        id: row.get(0)?,
+        invoice_id: row.get(1)?,
+        payment_date: row.get(2)?,
+        amount: row.get(3)?,
+        voucher_id: row.get(4)?,
+        created_at: row.get(5)?,
+        created_by: row.get(6)?,
+    })
+}
+
+fn customer_from_row(row: &Row) -> rusqlite::Result<Customer> {
+    Ok(Customer {
+        id: row.get(0)?,
+        company_id: row.get(1)?,
+        customer_number: row.get(2)?,
+        name: row.get(3)?,
+        org_number: row.get(4)?,
+        email: row.get(5)?,
+        address: row.get(6)?,
+        payment_terms_days: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
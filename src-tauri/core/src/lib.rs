@@ -0,0 +1,1467 @@
+//! Bookkeeping on a SQLite database: companies, the chart of accounts,
+//! vouchers, period locks, invoicing, bank matching, reports and exports.
+//!
+//! Everything goes through [`Books`], so the desktop app, the command line
+//! and tests apply the same validation.
+
+use std::path::Path;
+
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+
+pub mod api_tokens;
+mod approvals;
+mod archive;
+pub mod backup;
+mod bank;
+mod cash_method;
+mod counterparties;
+pub mod db;
+pub mod encryption;
+pub mod ids;
+mod inbox;
+mod invoicing;
+pub mod models;
+pub mod money;
+mod reconciliation;
+mod sie;
+mod suppliers;
+mod templates;
+pub mod users;
+
+use db::{accounting_method, allows_zero_rows, is_period_locked, DbError, ACCRUAL_METHOD, CASH_METHOD};
+use models::{
+    Account, AddAttachmentInput, Attachment, Company, CompanyIdInput, CreateCompanyInput, CreateCorrectionInput,
+    CreateVoucherInput, CsvExport, ExportInput, FieldError, LedgerRow, ListVouchersInput, LockPeriodInput, PeriodLock, PeriodLockIdInput,
+    ReportLedgerInput, ReportTrialBalanceInput, ReportVoucherListInput, SetAccountingMethodInput, SetApprovalRequirementInput, SetZeroRowAllowanceInput, TrialBalanceRow, UpsertAccountInput, Voucher, VoucherIdInput,
+    VoucherListItem, VoucherRow, VoucherSeries,
+};
+use ids::{AccountId, AttachmentId, CompanyId, CounterpartyId, PeriodLockId, SeriesId, VoucherId, VoucherRowId};
+use money::Money;
+use sie::FIRST_RESULT_ACCOUNT;
+
+/// The books of all companies in one database, worked on by `user`, who is
+/// recorded as `created_by`, `locked_by` etc. on everything written. Callers
+/// check the user's role before calling in.
+pub struct Books<'a> {
+    conn: &'a mut Connection,
+    user: &'a str,
+    documents_dir: &'a Path,
+}
+
+impl<'a> Books<'a> {
+    /// `documents_dir` is where imported documents are stored, normally
+    /// [`db::documents_dir`] next to the database.
+    pub fn new(conn: &'a mut Connection, user: &'a str, documents_dir: &'a Path) -> Books<'a> {
+        Books {
+            conn,
+            user,
+            documents_dir,
+        }
+    }
+}
+
+fn map_error(error: DbError) -> String {
+    error.to_string()
+}
+
+fn map_sql_error(error: rusqlite::Error) -> String {
+    error.to_string()
+}
+
+const COMPANY_COLUMNS: &str = "id, name, org_number, fiscal_year_start, fiscal_year_end, accounting_method, requires_approval, allow_zero_rows, created_at";
+
+/// Longest voucher or row description, in characters.
+pub const MAX_DESCRIPTION_CHARS: usize = 200;
+
+impl Books<'_> {
+    pub fn list_companies(&mut self) -> Result<Vec<Company>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM companies", COMPANY_COLUMNS))
+            .map_err(map_sql_error)?;
+        let rows = stmt.query_map([], company_from_row).map_err(map_sql_error)?;
+        let mut companies = Vec::new();
+        for row in rows {
+            companies.push(row.map_err(map_sql_error)?);
+        }
+        Ok(companies)
+    }
+
+    pub fn create_company(&mut self, payload: CreateCompanyInput) -> Result<Company, String> {
+        let conn = &mut *self.conn;
+        let now = Utc::now().to_rfc3339();
+        let id = CompanyId::generate();
+        let fiscal_year_start = NaiveDate::from_ymd_opt(2024, 1, 1).expect("valid date");
+        let fiscal_year_end = NaiveDate::from_ymd_opt(2024, 12, 31).expect("valid date");
+        let name = payload.name.clone();
+        let org_number = payload.org_number.clone();
+        let accounting_method = payload
+            .accounting_method
+            .clone()
+            .unwrap_or_else(|| ACCRUAL_METHOD.to_string());
+        validate_accounting_method(&accounting_method)?;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        tx.execute(
+            "INSERT INTO companies (id, name, org_number, fiscal_year_start, fiscal_year_end, accounting_method, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                id,
+                &name,
+                &org_number,
+                fiscal_year_start,
+                fiscal_year_end,
+                &accounting_method,
+                now
+            ],
+        )
+        .map_err(map_sql_error)?;
+
+        tx.execute(
+            "INSERT INTO voucher_series (id, company_id, code, description, next_number)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![SeriesId::generate(), id, "A", "Main series", 1],
+        )
+        .map_err(map_sql_error)?;
+        tx.commit().map_err(map_sql_error)?;
+
+        Ok(Company {
+            id,
+            name,
+            org_number,
+            fiscal_year_start,
+            fiscal_year_end,
+            accounting_method,
+            requires_approval: false,
+            allow_zero_rows: false,
+            created_at: now,
+        })
+    }
+
+    /// Switches between faktureringsmetoden and kontantmetoden. Only allowed while
+    /// no invoices are open, since open invoices were booked under the old method.
+    pub fn set_accounting_method(
+        &mut self,
+        payload: SetAccountingMethodInput,
+    ) -> Result<Company, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        validate_accounting_method(&payload.accounting_method)?;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let current = accounting_method(&tx, &payload.company_id).map_err(map_error)?;
+        if current != payload.accounting_method {
+            let open_invoices: i64 = tx
+                .query_row(
+                    "SELECT
+                       (SELECT COUNT(*) FROM customer_invoices i WHERE i.company_id = ?1
+                          AND i.total_cents > COALESCE((SELECT SUM(p.amount_cents) FROM customer_payments p WHERE p.invoice_id = i.id), 0))
+                     + (SELECT COUNT(*) FROM supplier_invoices i WHERE i.company_id = ?1
+                          AND i.total_cents > COALESCE((SELECT SUM(p.amount_cents) FROM supplier_payments p WHERE p.invoice_id = i.id), 0))",
+                    [&payload.company_id],
+                    |row| row.get(0),
+                )
+                .map_err(map_sql_error)?;
+            if open_invoices > 0 {
+                return Err(format!(
+                    "Cannot change accounting method while {} invoices are open",
+                    open_invoices
+                ));
+            }
+            tx.execute(
+                "UPDATE companies SET accounting_method = ?1 WHERE id = ?2",
+                params![&payload.accounting_method, &payload.company_id],
+            )
+            .map_err(map_sql_error)?;
+            insert_audit_entry(
+                &tx,
+                user,
+                &payload.company_id,
+                "company",
+                &payload.company_id,
+                "accounting_method",
+                json!({ "from": current, "to": &payload.accounting_method }),
+            )?;
+        }
+        let company = tx
+            .query_row(
+                &format!("SELECT {} FROM companies WHERE id = ?1", COMPANY_COLUMNS),
+                [&payload.company_id],
+                company_from_row,
+            )
+            .map_err(map_sql_error)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(company)
+    }
+
+    /// Turns the four-eyes rule on or off: when on, vouchers must be approved by
+    /// someone other than their creator before they can be posted.
+    pub fn set_approval_requirement(
+        &mut self,
+        payload: SetApprovalRequirementInput,
+    ) -> Result<Company, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let changed = tx
+            .execute(
+                "UPDATE companies SET requires_approval = ?1 WHERE id = ?2 AND requires_approval != ?1",
+                params![payload.requires_approval as i64, &payload.company_id],
+            )
+            .map_err(map_sql_error)?;
+        if changed > 0 {
+            insert_audit_entry(
+                &tx,
+                user,
+                &payload.company_id,
+                "company",
+                &payload.company_id,
+                "requires_approval",
+                json!({ "requires_approval": payload.requires_approval }),
+            )?;
+        }
+        let company = tx
+            .query_row(
+                &format!("SELECT {} FROM companies WHERE id = ?1", COMPANY_COLUMNS),
+                [&payload.company_id],
+                company_from_row,
+            )
+            .map_err(map_sql_error)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(company)
+    }
+
+    /// Allows or forbids voucher rows without an amount. Vouchers already
+    /// booked are not affected.
+    pub fn set_zero_row_allowance(
+        &mut self,
+        payload: SetZeroRowAllowanceInput,
+    ) -> Result<Company, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let changed = tx
+            .execute(
+                "UPDATE companies SET allow_zero_rows = ?1 WHERE id = ?2 AND allow_zero_rows != ?1",
+                params![payload.allow_zero_rows as i64, &payload.company_id],
+            )
+            .map_err(map_sql_error)?;
+        if changed > 0 {
+            insert_audit_entry(
+                &tx,
+                user,
+                &payload.company_id,
+                "company",
+                &payload.company_id,
+                "allow_zero_rows",
+                json!({ "allow_zero_rows": payload.allow_zero_rows }),
+            )?;
+        }
+        let company = tx
+            .query_row(
+                &format!("SELECT {} FROM companies WHERE id = ?1", COMPANY_COLUMNS),
+                [&payload.company_id],
+                company_from_row,
+            )
+            .map_err(map_sql_error)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(company)
+    }
+
+    pub fn list_accounts(&mut self, payload: CompanyIdInput) -> Result<Vec<Account>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, company_id, number, name, type, vat_code, is_active, created_at
+                 FROM accounts WHERE company_id = ?1 ORDER BY number",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], |row| {
+                Ok(Account {
+                    id: row.get(0)?,
+                    company_id: row.get(1)?,
+                    number: row.get(2)?,
+                    name: row.get(3)?,
+                    account_type: row.get(4)?,
+                    vat_code: row.get(5)?,
+                    is_active: row.get::<_, i64>(6)? == 1,
+                    created_at: row.get(7)?,
+                })
+            })
+            .map_err(map_sql_error)?;
+        let mut accounts = Vec::new();
+        for row in rows {
+            accounts.push(row.map_err(map_sql_error)?);
+        }
+        Ok(accounts)
+    }
+
+    pub fn upsert_account(&mut self, payload: UpsertAccountInput) -> Result<Account, String> {
+        let conn = &mut *self.conn;
+        let now = Utc::now().to_rfc3339();
+        let account_id = payload
+            .id
+            .clone()
+            .unwrap_or_else(AccountId::generate);
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        tx.execute(
+            "INSERT INTO accounts (id, company_id, number, name, type, vat_code, is_active, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(id) DO UPDATE SET
+               number = excluded.number,
+               name = excluded.name,
+               type = excluded.type,
+               vat_code = excluded.vat_code,
+               is_active = excluded.is_active",
+            params![
+                account_id,
+                &payload.company_id,
+                payload.number,
+                &payload.name,
+                &payload.account_type,
+                &payload.vat_code,
+                if payload.is_active { 1 } else { 0 },
+                now
+            ],
+        )
+        .map_err(map_sql_error)?;
+        tx.commit().map_err(map_sql_error)?;
+
+        Ok(Account {
+            id: account_id,
+            company_id: payload.company_id,
+            number: payload.number,
+            name: payload.name,
+            account_type: payload.account_type,
+            vat_code: payload.vat_code,
+            is_active: payload.is_active,
+            created_at: now,
+        })
+    }
+
+    pub fn list_voucher_series(
+        &mut self,
+        payload: CompanyIdInput,
+    ) -> Result<Vec<VoucherSeries>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, company_id, code, description, next_number FROM voucher_series WHERE company_id = ?1",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], |row| {
+                Ok(VoucherSeries {
+                    id: row.get(0)?,
+                    company_id: row.get(1)?,
+                    code: row.get(2)?,
+                    description: row.get(3)?,
+                    next_number: row.get(4)?,
+                })
+            })
+            .map_err(map_sql_error)?;
+        let mut series = Vec::new();
+        for row in rows {
+            series.push(row.map_err(map_sql_error)?);
+        }
+        Ok(series)
+    }
+
+    pub fn list_vouchers(&mut self, payload: ListVouchersInput) -> Result<Vec<Voucher>, String> {
+        let conn = &*self.conn;
+        fetch_vouchers_with_rows(
+            conn,
+            &payload.company_id,
+            payload.from_date,
+            payload.to_date,
+            payload.limit,
+            payload.after.as_ref(),
+        )
+    }
+
+    pub fn get_voucher(&mut self, payload: VoucherIdInput) -> Result<Voucher, String> {
+        let conn = &*self.conn;
+        let voucher = conn
+            .query_row(
+                "SELECT id, company_id, series_id, voucher_number, date, description, counterparty, created_at, created_by, posted_at, counterparty_id
+                 FROM vouchers WHERE id = ?1",
+                [payload.voucher_id],
+                |row| {
+                    Ok(Voucher {
+                        id: row.get(0)?,
+                        company_id: row.get(1)?,
+                        series_id: row.get(2)?,
+                        voucher_number: row.get(3)?,
+                        date: row.get(4)?,
+                        description: row.get(5)?,
+                        counterparty: row.get(6)?,
+                        counterparty_id: row.get(10)?,
+                        created_at: row.get(7)?,
+                        created_by: row.get(8)?,
+                        posted_at: row.get(9)?,
+                        rows: None,
+                        attachments: None,
+                    })
+                },
+            )
+            .map_err(map_sql_error)?;
+
+        let mut voucher = voucher;
+        voucher.rows = Some(fetch_voucher_rows(conn, &voucher.id)?);
+        voucher.attachments = Some(fetch_attachments(conn, &voucher.id)?);
+        Ok(voucher)
+    }
+
+    pub fn create_voucher(&mut self, payload: CreateVoucherInput) -> Result<Voucher, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let voucher = insert_voucher(&tx, user, &payload)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(voucher)
+    }
+
+    pub fn list_attachments(&mut self, payload: VoucherIdInput) -> Result<Vec<Attachment>, String> {
+        let conn = &*self.conn;
+        fetch_attachments(conn, &payload.voucher_id)
+    }
+
+    /// Attaches supporting documentation to an existing voucher. This is allowed
+    /// for posted vouchers and in locked periods since it does not alter the entry.
+    pub fn add_attachment_to_voucher(
+        &mut self,
+        payload: AddAttachmentInput,
+    ) -> Result<Attachment, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        if payload.ref_value.trim().is_empty() {
+            return Err("Attachment reference is required".to_string());
+        }
+
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let company_id: String = tx
+            .query_row(
+                "SELECT company_id FROM vouchers WHERE id = ?1",
+                [payload.voucher_id.clone()],
+                |row| row.get(0),
+            )
+            .map_err(map_sql_error)?;
+
+        let now = Utc::now().to_rfc3339();
+        let attachment = insert_attachment(
+            &tx,
+            &payload.voucher_id,
+            &payload.ref_type,
+            &payload.ref_value,
+            payload.note.as_deref(),
+            &now,
+        )?;
+
+        insert_audit_entry(
+            &tx,
+            user,
+            &company_id,
+            "attachment",
+            &attachment.id,
+            "create",
+            json!({
+                "voucher_id": &attachment.voucher_id,
+                "ref_type": &attachment.ref_type,
+                "ref_value": &attachment.ref_value
+            }),
+        )?;
+
+        tx.commit().map_err(map_sql_error)?;
+        Ok(attachment)
+    }
+
+    pub fn post_voucher(&mut self, payload: VoucherIdInput) -> Result<Voucher, String> {
+        let conn = &mut *self.conn;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let (company_id, date, posted_at, requires_approval): (CompanyId, NaiveDate, Option<String>, bool) = tx
+            .query_row(
+                "SELECT v.company_id, v.date, v.posted_at, c.requires_approval
+                 FROM vouchers v JOIN companies c ON c.id = v.company_id WHERE v.id = ?1",
+                [payload.voucher_id.clone()],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get::<_, i64>(3)? == 1)),
+            )
+            .map_err(map_sql_error)?;
+
+        if posted_at.is_some() {
+            return Err("Voucher is already posted".to_string());
+        }
+
+        match approvals::approval_decision(&tx, &payload.voucher_id)?.as_deref() {
+            Some(approvals::REJECTED) => return Err("Voucher has been rejected".to_string()),
+            Some(approvals::APPROVED) => {}
+            _ if requires_approval => return Err("Voucher must be approved before posting".to_string()),
+            _ => {}
+        }
+
+        if is_period_locked(&tx, &company_id, date).map_err(map_error)? {
+            return Err("Period is locked".to_string());
+        }
+
+        let now = Utc::now().to_rfc3339();
+        tx.execute(
+            "UPDATE vouchers SET posted_at = ?1 WHERE id = ?2",
+            params![now, payload.voucher_id],
+        )
+        .map_err(map_sql_error)?;
+
+        tx.commit().map_err(map_sql_error)?;
+
+        self.get_voucher(payload)
+    }
+
+    pub fn create_correction_voucher(
+        &mut self,
+        payload: CreateCorrectionInput,
+    ) -> Result<Voucher, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        // The correction keeps the counterparty so per-counterparty reports net out.
+        let original: (VoucherId, CompanyId, SeriesId, i64, Option<String>, Option<CounterpartyId>) = conn
+            .query_row(
+                "SELECT id, company_id, series_id, voucher_number, counterparty, counterparty_id FROM vouchers WHERE id = ?1",
+                [payload.original_voucher_id.clone()],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+            )
+            .map_err(map_sql_error)?;
+
+        if is_period_locked(conn, &original.1, payload.date).map_err(map_error)? {
+            return Err("Period is locked".to_string());
+        }
+
+        let rows = fetch_voucher_rows(conn, &original.0)?;
+        // Rows without an amount have nothing to reverse.
+        let reversed_rows: Vec<_> = rows
+            .into_iter()
+            .filter(|row| !row.debit.is_zero() || !row.credit.is_zero())
+            .map(|row| models::CreateVoucherRowInput {
+                account_id: row.account_id,
+                description: row.description,
+                debit: row.credit,
+                credit: row.debit,
+                vat_code: row.vat_code,
+            })
+            .collect();
+
+        let payload = CreateVoucherInput {
+            company_id: original.1,
+            series_id: original.2,
+            date: payload.date,
+            description: format!("{} (Correction of {})", payload.description, original.3),
+            counterparty: original.4,
+            counterparty_id: original.5,
+            rows: reversed_rows,
+            attachments: None,
+        };
+
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let voucher = book_voucher(&tx, user, &payload, false)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(voucher)
+    }
+
+    pub fn list_period_locks(
+        &mut self,
+        payload: CompanyIdInput,
+    ) -> Result<Vec<PeriodLock>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, company_id, period_start, period_end, locked_at, locked_by
+                 FROM period_locks WHERE company_id = ?1 ORDER BY period_start DESC",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], |row| {
+                Ok(PeriodLock {
+                    id: row.get(0)?,
+                    company_id: row.get(1)?,
+                    period_start: row.get(2)?,
+                    period_end: row.get(3)?,
+                    locked_at: row.get(4)?,
+                    locked_by: row.get(5)?,
+                })
+            })
+            .map_err(map_sql_error)?;
+        let mut locks = Vec::new();
+        for row in rows {
+            locks.push(row.map_err(map_sql_error)?);
+        }
+        Ok(locks)
+    }
+
+    pub fn lock_period(&mut self, payload: LockPeriodInput) -> Result<PeriodLock, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let lock_id = PeriodLockId::generate();
+        let now = Utc::now().to_rfc3339();
+        let company_id = payload.company_id.clone();
+        let period_start = payload.period_start;
+        let period_end = payload.period_end;
+        tx.execute(
+            "INSERT INTO period_locks (id, company_id, period_start, period_end, locked_at, locked_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                lock_id,
+                &company_id,
+                &period_start,
+                &period_end,
+                now,
+                user
+            ],
+        )
+        .map_err(map_sql_error)?;
+
+        tx.commit().map_err(map_sql_error)?;
+
+        Ok(PeriodLock {
+            id: lock_id,
+            company_id,
+            period_start,
+            period_end,
+            locked_at: now,
+            locked_by: user.to_string(),
+        })
+    }
+
+    /// Removes a period lock so corrections can be booked in the period again.
+    pub fn unlock_period(&mut self, payload: PeriodLockIdInput) -> Result<(), String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let (company_id, period_start, period_end, locked_by): (String, String, String, String) = tx
+            .query_row(
+                "SELECT company_id, period_start, period_end, locked_by FROM period_locks WHERE id = ?1",
+                [&payload.lock_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .map_err(map_sql_error)?;
+        tx.execute("DELETE FROM period_locks WHERE id = ?1", [&payload.lock_id])
+            .map_err(map_sql_error)?;
+        insert_audit_entry(
+            &tx,
+            user,
+            &company_id,
+            "period_lock",
+            &payload.lock_id,
+            "unlock",
+            json!({
+                "period_start": period_start,
+                "period_end": period_end,
+                "locked_by": locked_by
+            }),
+        )?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(())
+    }
+
+    pub fn report_voucher_list(
+        &mut self,
+        payload: ReportVoucherListInput,
+    ) -> Result<Vec<VoucherListItem>, String> {
+        let conn = &*self.conn;
+        let mut query = String::from(
+            "SELECT v.id, v.voucher_number, v.date, v.description, SUM(r.debit_cents)
+             FROM vouchers v
+             JOIN voucher_rows r ON r.voucher_id = v.id
+             WHERE v.company_id = ?1",
+        );
+        if payload.from_date.is_some() {
+            query.push_str(" AND v.date >= ?2");
+        }
+        if payload.to_date.is_some() {
+            query.push_str(if payload.from_date.is_some() { " AND v.date <= ?3" } else { " AND v.date <= ?2" });
+        }
+        query.push_str(" GROUP BY v.id ORDER BY v.date ASC, v.voucher_number ASC");
+
+        let mut stmt = conn.prepare(&query).map_err(map_sql_error)?;
+        let mut params_vec: Vec<&dyn rusqlite::ToSql> = vec![&payload.company_id];
+        if let Some(ref value) = payload.from_date {
+            params_vec.push(value);
+        }
+        if let Some(ref value) = payload.to_date {
+            params_vec.push(value);
+        }
+
+        let rows = stmt
+            .query_map(params_vec.as_slice(), |row| {
+                Ok(VoucherListItem {
+                    id: row.get(0)?,
+                    voucher_number: row.get(1)?,
+                    date: row.get(2)?,
+                    description: row.get(3)?,
+                    total: row.get(4)?,
+                })
+            })
+            .map_err(map_sql_error)?;
+        let mut list = Vec::new();
+        for row in rows {
+            list.push(row.map_err(map_sql_error)?);
+        }
+        Ok(list)
+    }
+
+    pub fn report_ledger_for_account(
+        &mut self,
+        payload: ReportLedgerInput,
+    ) -> Result<Vec<LedgerRow>, String> {
+        let conn = &*self.conn;
+        fetch_ledger(conn, &payload)
+    }
+
+    /// Råbalans: per account, the balance before `from_date`, the debits and
+    /// credits in the period and the balance after it. Result accounts start
+    /// at zero, so they show the period's movements only.
+    pub fn report_trial_balance(
+        &mut self,
+        payload: ReportTrialBalanceInput,
+    ) -> Result<Vec<TrialBalanceRow>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT a.id, a.number, a.name,
+                        COALESCE(SUM(CASE WHEN v.date < ?2 THEN r.debit_cents - r.credit_cents END), 0),
+                        COALESCE(SUM(CASE WHEN ?2 IS NULL OR v.date >= ?2 THEN r.debit_cents END), 0),
+                        COALESCE(SUM(CASE WHEN ?2 IS NULL OR v.date >= ?2 THEN r.credit_cents END), 0)
+                 FROM accounts a
+                 JOIN voucher_rows r ON r.account_id = a.id
+                 JOIN vouchers v ON v.id = r.voucher_id
+                 WHERE a.company_id = ?1 AND (?3 IS NULL OR v.date <= ?3)
+                 GROUP BY a.id
+                 ORDER BY a.number",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map(
+                params![payload.company_id, payload.from_date, payload.to_date],
+                |row| {
+                    Ok((
+                        row.get::<_, AccountId>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, Money>(3)?,
+                        row.get::<_, Money>(4)?,
+                        row.get::<_, Money>(5)?,
+                    ))
+                },
+            )
+            .map_err(map_sql_error)?;
+
+        let mut balance = Vec::new();
+        for row in rows {
+            let (account_id, account_number, account_name, before, debit, credit) =
+                row.map_err(map_sql_error)?;
+            let opening = if account_number < FIRST_RESULT_ACCOUNT {
+                before
+            } else {
+                Money::ZERO
+            };
+            if opening.is_zero() && debit.is_zero() && credit.is_zero() {
+                continue;
+            }
+            balance.push(TrialBalanceRow {
+                account_id,
+                account_number,
+                account_name,
+                opening,
+                debit,
+                credit,
+                closing: opening.checked_post(debit, credit)?,
+            });
+        }
+        Ok(balance)
+    }
+
+    pub fn export_csv(&mut self, payload: ExportInput) -> Result<String, String> {
+        let export = self.render_csv(CompanyIdInput {
+            company_id: payload.company_id,
+        })?;
+        let base_path = std::path::PathBuf::from(payload.target_path);
+        let (voucher_path, row_path) = if base_path.extension().and_then(|ext| ext.to_str()) == Some("csv") {
+            let rows_path = base_path
+                .with_file_name("voucher_rows.csv");
+            (base_path, rows_path)
+        } else {
+            (base_path.join("vouchers.csv"), base_path.join("voucher_rows.csv"))
+        };
+
+        if let Some(parent) = voucher_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+
+        std::fs::write(&voucher_path, export.vouchers).map_err(|err| err.to_string())?;
+        std::fs::write(&row_path, export.voucher_rows).map_err(|err| err.to_string())?;
+
+        Ok(format!(
+            "CSV exported to {} and {}",
+            voucher_path.display(),
+            row_path.display()
+        ))
+    }
+
+    /// The two files of [`export_csv`](Self::export_csv), returned instead
+    /// of written.
+    pub fn render_csv(&mut self, payload: CompanyIdInput) -> Result<CsvExport, String> {
+        let conn = &*self.conn;
+        let vouchers = fetch_vouchers_with_rows(conn, &payload.company_id, None, None, None, None)?;
+
+        let mut voucher_lines = vec!["id,voucher_number,date,description,counterparty,posted_at".to_string()];
+        let mut row_lines = vec!["voucher_id,account_id,description,debit_cents,credit_cents,vat_code".to_string()];
+        for voucher in &vouchers {
+            voucher_lines.push(format!(
+                "{},{},{},{},{},{}",
+                voucher.id,
+                voucher.voucher_number,
+                voucher.date,
+                voucher.description.replace(',', " "),
+                voucher.counterparty.clone().unwrap_or_default().replace(',', " "),
+                voucher.posted_at.clone().unwrap_or_default()
+            ));
+            if let Some(rows) = &voucher.rows {
+                for row in rows {
+                    row_lines.push(format!(
+                        "{},{},{},{},{},{}",
+                        voucher.id,
+                        row.account_id,
+                        row.description.clone().unwrap_or_default().replace(',', " "),
+                        row.debit.cents(),
+                        row.credit.cents(),
+                        row.vat_code.clone().unwrap_or_default()
+                    ));
+                }
+            }
+        }
+
+        Ok(CsvExport {
+            vouchers: voucher_lines.join("\n"),
+            voucher_rows: row_lines.join("\n"),
+        })
+    }
+
+    pub fn export_sie_stub(&mut self, payload: ExportInput) -> Result<String, String> {
+        let sie = self.render_sie_stub(CompanyIdInput {
+            company_id: payload.company_id,
+        })?;
+        let base_path = std::path::PathBuf::from(payload.target_path);
+        let sie_path = if base_path.extension().and_then(|ext| ext.to_str())
+            .map(|ext| ext.eq_ignore_ascii_case("se") || ext.eq_ignore_ascii_case("sie"))
+            .unwrap_or(false)
+        {
+            base_path
+        } else {
+            base_path.join("export.sie")
+        };
+
+        if let Some(parent) = sie_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+
+        std::fs::write(&sie_path, sie).map_err(|err| err.to_string())?;
+
+        Ok(format!("SIE stub exported to {}", sie_path.display()))
+    }
+
+    /// The file of [`export_sie_stub`](Self::export_sie_stub), returned
+    /// instead of written.
+    pub fn render_sie_stub(&mut self, payload: CompanyIdInput) -> Result<String, String> {
+        let conn = &*self.conn;
+        let accounts = fetch_accounts(conn, &payload.company_id)?;
+        let vouchers = fetch_vouchers_with_rows(conn, &payload.company_id, None, None, None, None)?;
+
+        let account_lookup: std::collections::HashMap<AccountId, i64> = accounts
+            .iter()
+            .map(|account| (account.id.clone(), account.number))
+            .collect();
+
+        let mut lines = vec![
+            "#FLAGGA 0".to_string(),
+            "#PROGRAM Bokforingsprogram 0.1".to_string(),
+            "#FORMAT PC8".to_string(),
+            "#SIETYP 4".to_string(),
+            "#TODO Implement full SIE4 export".to_string(),
+        ];
+
+        for account in &accounts {
+            lines.push(format!(
+                "#KONTO {} \"{}\"",
+                account.number, account.name
+            ));
+        }
+
+        for voucher in vouchers {
+            lines.push(format!(
+                "#VER A {} {} \"{}\" {}",
+                voucher.voucher_number,
+                voucher.date,
+                voucher.description,
+                voucher.created_at
+            ));
+            if let Some(rows) = voucher.rows {
+                for row in rows {
+                    let account_number = account_lookup
+                        .get(&row.account_id)
+                        .copied()
+                        .unwrap_or_default();
+                    lines.push(format!(
+                        "#TRANS {} {} {}",
+                        account_number,
+                        row.debit.cents(),
+                        row.credit.cents()
+                    ));
+                }
+            }
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
+
+fn validate_accounting_method(method: &str) -> Result<(), String> {
+    if method == ACCRUAL_METHOD || method == CASH_METHOD {
+        Ok(())
+    } else {
+        Err(format!("Unknown accounting method: {}", method))
+    }
+}
+
+fn company_from_row(row: &rusqlite::Row) -> rusqlite::Result<Company> {
+    Ok(Company {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        org_number: row.get(2)?,
+        fiscal_year_start: row.get(3)?,
+        fiscal_year_end: row.get(4)?,
+        accounting_method: row.get(5)?,
+        requires_approval: row.get::<_, i64>(6)? == 1,
+        allow_zero_rows: row.get::<_, i64>(7)? == 1,
+        created_at: row.get(8)?,
+    })
+}
+
+/// Validates and inserts a voucher with its rows, attachments and audit entry.
+/// The caller owns the transaction so other records can be written atomically
+/// together with the voucher.
+fn insert_voucher(
+    conn: &Connection,
+    user: &str,
+    payload: &CreateVoucherInput,
+) -> Result<Voucher, String> {
+    book_voucher(conn, user, payload, true)
+}
+
+/// [`insert_voucher`], except that an inactive counterparty is accepted when
+/// `require_active_counterparty` is false. A correction must be possible even
+/// after the counterparty of the original voucher has been deactivated.
+fn book_voucher(
+    conn: &Connection,
+    user: &str,
+    payload: &CreateVoucherInput,
+    require_active_counterparty: bool,
+) -> Result<Voucher, String> {
+    if payload.rows.is_empty() {
+        return Err("Voucher must have rows".to_string());
+    }
+    let allow_zero_rows = allows_zero_rows(conn, &payload.company_id).map_err(map_error)?;
+    validate_voucher_fields(payload, allow_zero_rows)?;
+    let date = payload.date;
+    let debit_total = Money::checked_sum(payload.rows.iter().map(|row| row.debit))?;
+    let credit_total = Money::checked_sum(payload.rows.iter().map(|row| row.credit))?;
+    if debit_total != credit_total {
+        return Err("Voucher does not balance".to_string());
+    }
+    if is_period_locked(conn, &payload.company_id, date).map_err(map_error)? {
+        return Err("Period is locked".to_string());
+    }
+
+    let (series_company_id, next_number): (CompanyId, i64) = conn
+        .query_row(
+            "SELECT company_id, next_number FROM voucher_series WHERE id = ?1",
+            [payload.series_id.clone()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(map_sql_error)?;
+
+    if series_company_id != payload.company_id {
+        return Err("Series does not belong to company".to_string());
+    }
+
+    conn.execute(
+        "UPDATE voucher_series SET next_number = next_number + 1 WHERE id = ?1",
+        [payload.series_id.clone()],
+    )
+    .map_err(map_sql_error)?;
+
+    let voucher_id = VoucherId::generate();
+    let now = Utc::now().to_rfc3339();
+    let company_id = payload.company_id.clone();
+    let series_id = payload.series_id.clone();
+    let description = payload.description.trim().to_string();
+    let counterparty_id = payload.counterparty_id.clone();
+    let mut counterparty = payload.counterparty.clone();
+    if let Some(counterparty_id) = &counterparty_id {
+        let name = if require_active_counterparty {
+            counterparties::active_counterparty_name(conn, &company_id, counterparty_id)?
+        } else {
+            counterparties::company_counterparty(conn, &company_id, counterparty_id)?.name
+        };
+        if counterparty.as_deref().is_none_or(|value| value.trim().is_empty()) {
+            counterparty = Some(name);
+        }
+    }
+    conn.execute(
+        "INSERT INTO vouchers (id, company_id, series_id, voucher_number, date, description, counterparty, counterparty_id, created_at, created_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            voucher_id,
+            &company_id,
+            &series_id,
+            next_number,
+            date,
+            &description,
+            &counterparty,
+            &counterparty_id,
+            now,
+            user
+        ],
+    )
+    .map_err(map_sql_error)?;
+
+    for row in &payload.rows {
+        conn.execute(
+            "INSERT INTO voucher_rows (id, voucher_id, account_id, description, debit_cents, credit_cents, vat_code)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                VoucherRowId::generate(),
+                voucher_id,
+                row.account_id,
+                row.description
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|description| !description.is_empty()),
+                row.debit,
+                row.credit,
+                row.vat_code
+            ],
+        )
+        .map_err(map_sql_error)?;
+    }
+
+    let mut attachments = Vec::new();
+    if let Some(inputs) = &payload.attachments {
+        for attachment in inputs {
+            if attachment.ref_value.trim().is_empty() {
+                continue;
+            }
+            attachments.push(insert_attachment(
+                conn,
+                &voucher_id,
+                &attachment.ref_type,
+                &attachment.ref_value,
+                attachment.note.as_deref(),
+                &now,
+            )?);
+        }
+    }
+
+    insert_audit_entry(
+        conn,
+        user,
+        &company_id,
+        "voucher",
+        &voucher_id,
+        "create",
+        json!({ "voucher_number": next_number }),
+    )?;
+
+    let rows = fetch_voucher_rows(conn, &voucher_id)?;
+    Ok(Voucher {
+        id: voucher_id,
+        company_id,
+        series_id,
+        voucher_number: next_number,
+        date,
+        description,
+        counterparty,
+        counterparty_id,
+        created_at: now,
+        created_by: user.to_string(),
+        posted_at: None,
+        rows: Some(rows),
+        attachments: Some(attachments),
+    })
+}
+
+fn fetch_voucher_rows(conn: &Connection, voucher_id: &str) -> Result<Vec<VoucherRow>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, voucher_id, account_id, description, debit_cents, credit_cents, vat_code
+             FROM voucher_rows WHERE voucher_id = ?1",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([voucher_id], |row| {
+            Ok(VoucherRow {
+                id: row.get(0)?,
+                voucher_id: row.get(1)?,
+                account_id: row.get(2)?,
+                description: row.get(3)?,
+                debit: row.get(4)?,
+                credit: row.get(5)?,
+                vat_code: row.get(6)?,
+            })
+        })
+        .map_err(map_sql_error)?;
+    let mut list = Vec::new();
+    for row in rows {
+        list.push(row.map_err(map_sql_error)?);
+    }
+    Ok(list)
+}
+
+fn fetch_attachments(conn: &Connection, voucher_id: &str) -> Result<Vec<Attachment>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, voucher_id, ref_type, ref_value, note, created_at
+             FROM attachments WHERE voucher_id = ?1 ORDER BY created_at",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([voucher_id], |row| {
+            Ok(Attachment {
+                id: row.get(0)?,
+                voucher_id: row.get(1)?,
+                ref_type: row.get(2)?,
+                ref_value: row.get(3)?,
+                note: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })
+        .map_err(map_sql_error)?;
+    let mut list = Vec::new();
+    for row in rows {
+        list.push(row.map_err(map_sql_error)?);
+    }
+    Ok(list)
+}
+
+/// Fails when `id` is an existing row of `table` in another company, so that
+/// an upsert cannot move a record between companies. `what` names the record
+/// in the error.
+fn ensure_company(
+    conn: &Connection,
+    table: &str,
+    id: &str,
+    company_id: &str,
+    what: &str,
+) -> Result<(), String> {
+    let stored: Option<CompanyId> = conn
+        .query_row(
+            &format!("SELECT company_id FROM {} WHERE id = ?1", table),
+            [id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(map_sql_error)?;
+    match stored {
+        Some(stored) if *stored != *company_id => {
+            Err(format!("{} does not belong to company", what))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn insert_audit_entry(
+    conn: &Connection,
+    user: &str,
+    company_id: &str,
+    entity_type: &str,
+    entity_id: &str,
+    action: &str,
+    payload: serde_json::Value,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO audit_log (id, company_id, entity_type, entity_id, action, payload_json, created_at, created_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            company_id,
+            entity_type,
+            entity_id,
+            action,
+            payload.to_string(),
+            Utc::now().to_rfc3339(),
+            user
+        ],
+    )
+    .map_err(map_sql_error)?;
+    Ok(())
+}
+
+/// Checks each field of a voucher. All problems are reported together as a
+/// JSON array of [`FieldError`]s.
+fn validate_voucher_fields(
+    payload: &CreateVoucherInput,
+    allow_zero_rows: bool,
+) -> Result<(), String> {
+    let mut errors = Vec::new();
+    let mut error = |field: String, message: String| errors.push(FieldError { field, message });
+    let description = payload.description.trim();
+    if description.is_empty() {
+        error("description".to_string(), "Description is required".to_string());
+    } else if description.chars().count() > MAX_DESCRIPTION_CHARS {
+        error("description".to_string(), description_too_long());
+    }
+
+    for (index, row) in payload.rows.iter().enumerate() {
+        let field = |name: &str| format!("rows[{}]{}", index, name);
+        if row
+            .description
+            .as_deref()
+            .is_some_and(|description| description.trim().chars().count() > MAX_DESCRIPTION_CHARS)
+        {
+            error(field(".description"), description_too_long());
+        }
+        if row.debit.is_negative() {
+            error(field(".debit"), "Amount cannot be negative".to_string());
+        }
+        if row.credit.is_negative() {
+            error(field(".credit"), "Amount cannot be negative".to_string());
+        }
+        if row.debit.is_positive() && row.credit.is_positive() {
+            error(field(""), "Row cannot have both a debit and a credit amount".to_string());
+        } else if row.debit.is_zero() && row.credit.is_zero() && !allow_zero_rows {
+            error(field(""), "Row must have a debit or a credit amount".to_string());
+        }
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(serde_json::to_string(&errors).map_err(|err| err.to_string())?)
+}
+
+fn description_too_long() -> String {
+    format!("Description is longer than {} characters", MAX_DESCRIPTION_CHARS)
+}
+
+fn insert_attachment(
+    conn: &Connection,
+    voucher_id: &VoucherId,
+    ref_type: &str,
+    ref_value: &str,
+    note: Option<&str>,
+    created_at: &str,
+) -> Result<Attachment, String> {
+    let id = AttachmentId::generate();
+    conn.execute(
+        "INSERT INTO attachments (id, voucher_id, ref_type, ref_value, note, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![id, voucher_id, ref_type, ref_value, note, created_at],
+    )
+    .map_err(map_sql_error)?;
+    Ok(Attachment {
+        id,
+        voucher_id: voucher_id.clone(),
+        ref_type: ref_type.to_string(),
+        ref_value: ref_value.to_string(),
+        note: note.map(str::to_string),
+        created_at: created_at.to_string(),
+    })
+}
+
+fn fetch_accounts(conn: &Connection, company_id: &str) -> Result<Vec<Account>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, company_id, number, name, type, vat_code, is_active, created_at
+             FROM accounts WHERE company_id = ?1 ORDER BY number",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([company_id], |row| {
+            Ok(Account {
+                id: row.get(0)?,
+                company_id: row.get(1)?,
+                number: row.get(2)?,
+                name: row.get(3)?,
+                account_type: row.get(4)?,
+                vat_code: row.get(5)?,
+                is_active: row.get::<_, i64>(6)? == 1,
+                created_at: row.get(7)?,
+            })
+        })
+        .map_err(map_sql_error)?;
+    let mut accounts = Vec::new();
+    for row in rows {
+        accounts.push(row.map_err(map_sql_error)?);
+    }
+    Ok(accounts)
+}
+
+fn fetch_ledger(conn: &Connection, payload: &ReportLedgerInput) -> Result<Vec<LedgerRow>, String> {
+    let mut query = String::from(
+        "SELECT v.date, v.voucher_number, v.description, r.debit_cents, r.credit_cents
+         FROM voucher_rows r
+         JOIN vouchers v ON v.id = r.voucher_id
+         WHERE v.company_id = ?1 AND r.account_id = ?2",
+    );
+    if payload.from_date.is_some() {
+        query.push_str(" AND v.date >= ?3");
+    }
+    if payload.to_date.is_some() {
+        query.push_str(if payload.from_date.is_some() { " AND v.date <= ?4" } else { " AND v.date <= ?3" });
+    }
+    query.push_str(" ORDER BY v.date ASC, v.voucher_number ASC");
+
+    let mut stmt = conn.prepare(&query).map_err(map_sql_error)?;
+    let mut params_vec: Vec<&dyn rusqlite::ToSql> = vec![&payload.company_id, &payload.account_id];
+    if let Some(ref value) = payload.from_date {
+        params_vec.push(value);
+    }
+    if let Some(ref value) = payload.to_date {
+        params_vec.push(value);
+    }
+
+    let rows = stmt
+        .query_map(params_vec.as_slice(), |row| {
+            Ok((
+                row.get::<_, NaiveDate>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Money>(3)?,
+                row.get::<_, Money>(4)?,
+            ))
+        })
+        .map_err(map_sql_error)?;
+
+    let mut balance = Money::ZERO;
+    let mut ledger = Vec::new();
+    for row in rows {
+        let (date, voucher_number, description, debit, credit) = row.map_err(map_sql_error)?;
+        balance = balance.checked_post(debit, credit)?;
+        ledger.push(LedgerRow {
+            date,
+            voucher_number,
+            description,
+            debit,
+            credit,
+            balance,
+        });
+    }
+    Ok(ledger)
+}
+
+/// Vouchers of a company, newest first, with their rows, read in one query.
+/// `limit` and `after` page through them: `after` is the last voucher of the
+/// previous page.
+fn fetch_vouchers_with_rows(
+    conn: &Connection,
+    company_id: &str,
+    from_date: Option<NaiveDate>,
+    to_date: Option<NaiveDate>,
+    limit: Option<i64>,
+    after: Option<&VoucherId>,
+) -> Result<Vec<Voucher>, String> {
+    if limit.is_some_and(|limit| limit < 1) {
+        return Err("limit: must be at least 1".to_string());
+    }
+    let after = match after {
+        Some(voucher_id) => Some(
+            conn.query_row(
+                "SELECT date, voucher_number, id FROM vouchers WHERE id = ?1 AND company_id = ?2",
+                params![voucher_id, company_id],
+                |row| {
+                    Ok((
+                        row.get::<_, NaiveDate>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, VoucherId>(2)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(map_sql_error)?
+            .ok_or_else(|| "after: voucher not found".to_string())?,
+        ),
+        None => None,
+    };
+    // A negative LIMIT means no limit in SQLite.
+    let limit = limit.unwrap_or(-1);
+
+    let mut params_vec: Vec<&dyn rusqlite::ToSql> = vec![&company_id, &limit];
+    let mut query = String::from(
+        "SELECT id, company_id, series_id, voucher_number, date, description, counterparty, created_at, created_by, posted_at, counterparty_id
+         FROM vouchers WHERE company_id = ?1",
+    );
+    if let Some(value) = &from_date {
+        params_vec.push(value);
+        query.push_str(&format!(" AND date >= ?{}", params_vec.len()));
+    }
+    if let Some(value) = &to_date {
+        params_vec.push(value);
+        query.push_str(&format!(" AND date <= ?{}", params_vec.len()));
+    }
+    if let Some((date, voucher_number, id)) = &after {
+        params_vec.extend([date as &dyn rusqlite::ToSql, voucher_number, id]);
+        let n = params_vec.len();
+        query.push_str(&format!(
+            " AND (date, voucher_number, id) < (?{}, ?{}, ?{})",
+            n - 2,
+            n - 1,
+            n
+        ));
+    }
+    // The id breaks ties between series so that pages neither skip nor repeat
+    // vouchers. Rows keep the order they were entered in.
+    let query = format!(
+        "SELECT v.*, r.id, r.account_id, r.description, r.debit_cents, r.credit_cents, r.vat_code
+         FROM ({} ORDER BY date DESC, voucher_number DESC, id DESC LIMIT ?2) v
+         LEFT JOIN voucher_rows r ON r.voucher_id = v.id
+         ORDER BY v.date DESC, v.voucher_number DESC, v.id DESC, r.rowid",
+        query
+    );
+
+    let mut stmt = conn.prepare(&query).map_err(map_sql_error)?;
+    let mut rows = stmt.query(params_vec.as_slice()).map_err(map_sql_error)?;
+    let mut vouchers: Vec<Voucher> = Vec::new();
+    while let Some(row) = rows.next().map_err(map_sql_error)? {
+        let id: VoucherId = row.get(0).map_err(map_sql_error)?;
+        if vouchers.last().map(|voucher| &voucher.id) != Some(&id) {
+            vouchers.push(Voucher {
+                id: id.clone(),
+                company_id: row.get(1).map_err(map_sql_error)?,
+                series_id: row.get(2).map_err(map_sql_error)?,
+                voucher_number: row.get(3).map_err(map_sql_error)?,
+                date: row.get(4).map_err(map_sql_error)?,
+                description: row.get(5).map_err(map_sql_error)?,
+                counterparty: row.get(6).map_err(map_sql_error)?,
+                counterparty_id: row.get(10).map_err(map_sql_error)?,
+                created_at: row.get(7).map_err(map_sql_error)?,
+                created_by: row.get(8).map_err(map_sql_error)?,
+                posted_at: row.get(9).map_err(map_sql_error)?,
+                rows: Some(Vec::new()),
+                attachments: None,
+            });
+        }
+        // A voucher without rows comes back once, with NULL row columns.
+        let row_id: Option<VoucherRowId> = row.get(11).map_err(map_sql_error)?;
+        if let (Some(row_id), Some(Voucher { rows: Some(voucher_rows), .. })) =
+            (row_id, vouchers.last_mut())
+        {
+            voucher_rows.push(VoucherRow {
+                id: row_id,
+                voucher_id: id,
+                account_id: row.get(12).map_err(map_sql_error)?,
+                description: row.get(13).map_err(map_sql_error)?,
+                debit: row.get(14).map_err(map_sql_error)?,
+                credit: row.get(15).map_err(map_sql_error)?,
+                vat_code: row.get(16).map_err(map_sql_error)?,
+            });
+        }
+    }
+    Ok(vouchers)
+}
@@ -0,0 +1,1202 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::ids::{
+    AccountId, ApiTokenId, ApprovalId, AttachmentId, BankRuleId, BankTransactionId,
+    CashYearEndBookingId, CompanyId, CounterpartyId, CustomerId, InboxDocumentId, InvoiceId,
+    InvoiceLineId, PartyId, PaymentId, PeriodLockId, PermissionDenialId, ReconciliationId,
+    ScheduleId, SeriesId, SupplierId, TemplateId, TemplateRowId, UserId, VoucherId, VoucherRowId,
+};
+use crate::money::Money;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Company {
+    pub id: CompanyId,
+    pub name: String,
+    pub org_number: Option<String>,
+    pub fiscal_year_start: NaiveDate,
+    pub fiscal_year_end: NaiveDate,
+    pub accounting_method: String,
+    pub requires_approval: bool,
+    pub allow_zero_rows: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStatus {
+    pub encrypted: bool,
+    pub unlocked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupResult {
+    pub path: String,
+    pub created_at: String,
+    pub size_bytes: i64,
+    pub encrypted: bool,
+    pub attachment_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupFile {
+    pub path: String,
+    pub file_name: String,
+    pub size_bytes: i64,
+    pub automatic: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSettings {
+    pub enabled: bool,
+    pub interval_hours: i64,
+    pub retention_count: i64,
+    pub directory: Option<String>,
+    pub include_attachments: bool,
+    pub last_backup_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveFile {
+    pub path: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveExportResult {
+    pub path: String,
+    pub fiscal_year_start: NaiveDate,
+    pub fiscal_year_end: NaiveDate,
+    pub voucher_count: i64,
+    pub attachment_count: i64,
+    pub sha256: String,
+    pub files: Vec<ArchiveFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreResult {
+    pub restored_from: String,
+    pub backup_created_at: String,
+    pub schema_version: i64,
+    pub attachment_count: i64,
+    pub previous_database_path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub id: UserId,
+    pub username: String,
+    pub display_name: String,
+    pub role: String,
+    pub is_active: bool,
+    pub created_at: String,
+    pub created_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionDenial {
+    pub id: PermissionDenialId,
+    pub username: String,
+    pub role: String,
+    pub command: String,
+    pub required_role: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthStatus {
+    pub has_users: bool,
+    pub user: Option<User>,
+    pub logged_in_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Account {
+    pub id: AccountId,
+    pub company_id: CompanyId,
+    pub number: i64,
+    pub name: String,
+    pub account_type: String,
+    pub vat_code: Option<String>,
+    pub is_active: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoucherSeries {
+    pub id: SeriesId,
+    pub company_id: CompanyId,
+    pub code: String,
+    pub description: String,
+    pub next_number: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Voucher {
+    pub id: VoucherId,
+    pub company_id: CompanyId,
+    pub series_id: SeriesId,
+    pub voucher_number: i64,
+    pub date: NaiveDate,
+    pub description: String,
+    pub counterparty: Option<String>,
+    pub counterparty_id: Option<CounterpartyId>,
+    pub created_at: String,
+    pub created_by: String,
+    pub posted_at: Option<String>,
+    pub rows: Option<Vec<VoucherRow>>,
+    pub attachments: Option<Vec<Attachment>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoucherRow {
+    pub id: VoucherRowId,
+    pub voucher_id: VoucherId,
+    pub account_id: AccountId,
+    pub description: Option<String>,
+    pub debit: Money,
+    pub credit: Money,
+    pub vat_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: AttachmentId,
+    pub voucher_id: VoucherId,
+    pub ref_type: String,
+    pub ref_value: String,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxDocument {
+    pub id: InboxDocumentId,
+    pub company_id: CompanyId,
+    pub file_name: String,
+    pub stored_path: String,
+    pub size_bytes: i64,
+    pub note: Option<String>,
+    pub received_at: String,
+    pub received_by: String,
+    pub voucher_id: Option<VoucherId>,
+    pub linked_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlinkedDocumentItem {
+    pub id: InboxDocumentId,
+    pub file_name: String,
+    pub stored_path: String,
+    pub note: Option<String>,
+    pub received_at: String,
+    pub days_pending: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankTransaction {
+    pub id: BankTransactionId,
+    pub company_id: CompanyId,
+    pub account_id: AccountId,
+    pub booking_date: NaiveDate,
+    pub value_date: Option<NaiveDate>,
+    pub amount: Money,
+    pub description: String,
+    pub reference: Option<String>,
+    pub counterparty: Option<String>,
+    pub source_format: String,
+    pub imported_at: String,
+    pub imported_by: String,
+    pub voucher_id: Option<VoucherId>,
+    pub matched_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankImportResult {
+    pub imported: Vec<BankTransaction>,
+    pub skipped_duplicates: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankRule {
+    pub id: BankRuleId,
+    pub company_id: CompanyId,
+    pub name: String,
+    pub match_text: String,
+    pub counter_account_id: AccountId,
+    pub vat_account_id: Option<AccountId>,
+    pub vat_rate_percent: Option<i64>,
+    pub is_active: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankVoucherMatch {
+    pub voucher_id: VoucherId,
+    pub voucher_number: i64,
+    pub date: NaiveDate,
+    pub description: String,
+    pub amount: Money,
+    pub day_difference: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankMatchSuggestions {
+    pub bank_transaction_id: BankTransactionId,
+    pub vouchers: Vec<BankVoucherMatch>,
+    pub rules: Vec<BankRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnmatchedLedgerRow {
+    pub voucher_id: VoucherId,
+    pub voucher_number: i64,
+    pub date: NaiveDate,
+    pub description: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankReconciliationReport {
+    pub company_id: CompanyId,
+    pub account_id: AccountId,
+    pub statement_date: NaiveDate,
+    pub statement_balance: Money,
+    pub ledger_balance: Money,
+    pub difference: Money,
+    pub unmatched_ledger_rows: Vec<UnmatchedLedgerRow>,
+    pub unmatched_statement_lines: Vec<BankTransaction>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankReconciliation {
+    pub id: ReconciliationId,
+    pub company_id: CompanyId,
+    pub account_id: AccountId,
+    pub period: String,
+    pub statement_date: NaiveDate,
+    pub statement_balance: Money,
+    pub ledger_balance: Money,
+    pub difference: Money,
+    pub unmatched_ledger_count: i64,
+    pub unmatched_statement_count: i64,
+    pub note: Option<String>,
+    pub signed_off_at: String,
+    pub signed_off_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoucherTemplate {
+    pub id: TemplateId,
+    pub company_id: CompanyId,
+    pub name: String,
+    pub description: String,
+    pub is_active: bool,
+    pub created_at: String,
+    pub rows: Vec<VoucherTemplateRow>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoucherTemplateRow {
+    pub id: TemplateRowId,
+    pub template_id: TemplateId,
+    pub account_id: AccountId,
+    pub description: Option<String>,
+    pub side: String,
+    pub percent_basis_points: Option<i64>,
+    pub fixed_amount: Option<Money>,
+    pub vat_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecurringSchedule {
+    pub id: ScheduleId,
+    pub company_id: CompanyId,
+    pub template_id: TemplateId,
+    pub series_id: SeriesId,
+    pub total: Money,
+    pub day_of_month: i64,
+    pub next_run_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub is_active: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecurringRunFailure {
+    pub schedule_id: ScheduleId,
+    pub date: NaiveDate,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecurringRunResult {
+    pub created: Vec<Voucher>,
+    pub failed: Vec<RecurringRunFailure>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Customer {
+    pub id: CustomerId,
+    pub company_id: CompanyId,
+    pub customer_number: i64,
+    pub name: String,
+    pub org_number: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub payment_terms_days: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomerInvoice {
+    pub id: InvoiceId,
+    pub company_id: CompanyId,
+    pub customer_id: CustomerId,
+    pub customer_name: String,
+    pub invoice_number: i64,
+    pub invoice_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub ocr_number: String,
+    pub net: Money,
+    pub vat: Money,
+    pub total: Money,
+    pub paid: Money,
+    pub open: Money,
+    pub voucher_id: Option<VoucherId>,
+    pub created_at: String,
+    pub created_by: String,
+    pub lines: Option<Vec<CustomerInvoiceLine>>,
+    pub payments: Option<Vec<InvoicePayment>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomerInvoiceLine {
+    pub id: InvoiceLineId,
+    pub invoice_id: InvoiceId,
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: Money,
+    pub vat_rate_percent: i64,
+    pub revenue_account_id: AccountId,
+    pub net: Money,
+    pub vat: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoicePayment {
+    pub id: PaymentId,
+    pub invoice_id: InvoiceId,
+    pub payment_date: NaiveDate,
+    pub amount: Money,
+    pub voucher_id: Option<VoucherId>,
+    pub created_at: String,
+    pub created_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Supplier {
+    pub id: SupplierId,
+    pub company_id: CompanyId,
+    pub supplier_number: i64,
+    pub name: String,
+    pub org_number: Option<String>,
+    pub bankgiro: Option<String>,
+    pub default_expense_account_id: Option<AccountId>,
+    pub payment_terms_days: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupplierInvoice {
+    pub id: InvoiceId,
+    pub company_id: CompanyId,
+    pub supplier_id: SupplierId,
+    pub supplier_name: String,
+    pub invoice_number: String,
+    pub invoice_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub payment_reference: Option<String>,
+    pub net: Money,
+    pub vat: Money,
+    pub total: Money,
+    pub paid: Money,
+    pub open: Money,
+    pub voucher_id: Option<VoucherId>,
+    pub created_at: String,
+    pub created_by: String,
+    pub lines: Option<Vec<SupplierInvoiceLine>>,
+    pub payments: Option<Vec<InvoicePayment>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupplierInvoiceLine {
+    pub id: InvoiceLineId,
+    pub invoice_id: InvoiceId,
+    pub description: Option<String>,
+    pub expense_account_id: AccountId,
+    pub net: Money,
+    pub vat: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CashYearEndBooking {
+    pub id: CashYearEndBookingId,
+    pub company_id: CompanyId,
+    pub year_end_date: NaiveDate,
+    pub voucher_id: VoucherId,
+    pub reversal_voucher_id: VoucherId,
+    pub receivables: Money,
+    pub payables: Money,
+    pub created_at: String,
+    pub created_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CashYearEndResult {
+    pub booking: CashYearEndBooking,
+    pub voucher: Voucher,
+    pub reversal: Voucher,
+    pub customer_invoice_ids: Vec<InvoiceId>,
+    pub supplier_invoice_ids: Vec<InvoiceId>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgingRow {
+    pub invoice_id: InvoiceId,
+    pub party_id: PartyId,
+    pub party_name: String,
+    pub invoice_number: String,
+    pub invoice_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub total: Money,
+    pub open: Money,
+    pub days_overdue: i64,
+    pub bucket: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgingReport {
+    pub as_of_date: NaiveDate,
+    pub rows: Vec<AgingRow>,
+    pub not_due: Money,
+    pub overdue_1_30: Money,
+    pub overdue_31_60: Money,
+    pub overdue_61_90: Money,
+    pub overdue_over_90: Money,
+    pub total_open: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodLock {
+    pub id: PeriodLockId,
+    pub company_id: CompanyId,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub locked_at: String,
+    pub locked_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoucherListItem {
+    pub id: VoucherId,
+    pub voucher_number: i64,
+    pub date: NaiveDate,
+    pub description: String,
+    pub total: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Counterparty {
+    pub id: CounterpartyId,
+    pub company_id: CompanyId,
+    pub name: String,
+    pub org_number: Option<String>,
+    pub vat_number: Option<String>,
+    pub address: Option<String>,
+    pub default_revenue_account_id: Option<AccountId>,
+    pub default_expense_account_id: Option<AccountId>,
+    pub is_active: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CounterpartyReport {
+    pub counterparty: Counterparty,
+    pub vouchers: Vec<VoucherListItem>,
+    pub voucher_count: i64,
+    pub total: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerRow {
+    pub date: NaiveDate,
+    pub voucher_number: i64,
+    pub description: String,
+    pub debit: Money,
+    pub credit: Money,
+    pub balance: Money,
+}
+
+/// A problem with one field of a payload, named by its path in the JSON,
+/// e.g. `rows[1].debit`. A rejected voucher's error is a JSON array of these.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateVoucherInput {
+    pub company_id: CompanyId,
+    pub series_id: SeriesId,
+    pub date: NaiveDate,
+    pub description: String,
+    pub counterparty: Option<String>,
+    pub counterparty_id: Option<CounterpartyId>,
+    pub rows: Vec<CreateVoucherRowInput>,
+    pub attachments: Option<Vec<CreateAttachmentInput>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateVoucherRowInput {
+    pub account_id: AccountId,
+    pub description: Option<String>,
+    pub debit: Money,
+    pub credit: Money,
+    pub vat_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAttachmentInput {
+    pub ref_type: String,
+    pub ref_value: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddAttachmentInput {
+    pub voucher_id: VoucherId,
+    pub ref_type: String,
+    pub ref_value: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportInboxDocumentsInput {
+    pub company_id: CompanyId,
+    pub file_paths: Vec<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListInboxDocumentsInput {
+    pub company_id: CompanyId,
+    pub include_linked: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateVoucherFromInboxInput {
+    pub inbox_document_id: InboxDocumentId,
+    pub voucher: CreateVoucherInput,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportBankStatementInput {
+    pub company_id: CompanyId,
+    pub file_path: String,
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListBankTransactionsInput {
+    pub company_id: CompanyId,
+    pub unmatched_only: Option<bool>,
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankTransactionIdInput {
+    pub bank_transaction_id: BankTransactionId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchBankTransactionInput {
+    pub bank_transaction_id: BankTransactionId,
+    pub voucher_id: VoucherId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpsertBankRuleInput {
+    pub id: Option<BankRuleId>,
+    pub company_id: CompanyId,
+    pub name: String,
+    pub match_text: String,
+    pub counter_account_id: AccountId,
+    pub vat_account_id: Option<AccountId>,
+    pub vat_rate_percent: Option<i64>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateVoucherFromBankRuleInput {
+    pub bank_transaction_id: BankTransactionId,
+    pub rule_id: BankRuleId,
+    pub series_id: SeriesId,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileBankInput {
+    pub company_id: CompanyId,
+    pub statement_date: NaiveDate,
+    pub statement_balance: Money,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignOffReconciliationInput {
+    pub company_id: CompanyId,
+    pub period: String,
+    pub statement_balance: Money,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpsertVoucherTemplateInput {
+    pub id: Option<TemplateId>,
+    pub company_id: CompanyId,
+    pub name: String,
+    pub description: String,
+    pub is_active: bool,
+    pub rows: Vec<VoucherTemplateRowInput>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoucherTemplateRowInput {
+    pub account_id: AccountId,
+    pub description: Option<String>,
+    pub side: String,
+    pub percent_basis_points: Option<i64>,
+    pub fixed_amount: Option<Money>,
+    pub vat_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstantiateTemplateInput {
+    pub template_id: TemplateId,
+    pub series_id: SeriesId,
+    pub date: NaiveDate,
+    pub total: Money,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpsertRecurringScheduleInput {
+    pub id: Option<ScheduleId>,
+    pub company_id: CompanyId,
+    pub template_id: TemplateId,
+    pub series_id: SeriesId,
+    pub total: Money,
+    pub day_of_month: i64,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecurringSchedulesInput {
+    pub company_id: CompanyId,
+    pub as_of_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpsertCustomerInput {
+    pub id: Option<CustomerId>,
+    pub company_id: CompanyId,
+    pub name: String,
+    pub org_number: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub payment_terms_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCustomerInvoiceInput {
+    pub company_id: CompanyId,
+    pub customer_id: CustomerId,
+    pub series_id: Option<SeriesId>,
+    pub invoice_date: NaiveDate,
+    pub due_date: Option<NaiveDate>,
+    pub lines: Vec<CustomerInvoiceLineInput>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomerInvoiceLineInput {
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: Money,
+    pub vat_rate_percent: i64,
+    pub revenue_account_id: AccountId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpsertSupplierInput {
+    pub id: Option<SupplierId>,
+    pub company_id: CompanyId,
+    pub name: String,
+    pub org_number: Option<String>,
+    pub bankgiro: Option<String>,
+    pub default_expense_account_id: Option<AccountId>,
+    pub payment_terms_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSupplierInvoiceInput {
+    pub company_id: CompanyId,
+    pub supplier_id: SupplierId,
+    pub series_id: Option<SeriesId>,
+    pub invoice_number: String,
+    pub invoice_date: NaiveDate,
+    pub due_date: Option<NaiveDate>,
+    pub payment_reference: Option<String>,
+    pub lines: Vec<SupplierInvoiceLineInput>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupplierInvoiceLineInput {
+    pub description: Option<String>,
+    pub expense_account_id: Option<AccountId>,
+    pub net: Money,
+    pub vat: Money,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListInvoicesInput {
+    pub company_id: CompanyId,
+    pub open_only: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceIdInput {
+    pub invoice_id: InvoiceId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterPaymentInput {
+    pub invoice_id: InvoiceId,
+    pub series_id: Option<SeriesId>,
+    pub payment_date: NaiveDate,
+    pub amount: Money,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgingReportInput {
+    pub company_id: CompanyId,
+    pub as_of_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCorrectionInput {
+    pub original_voucher_id: VoucherId,
+    pub date: NaiveDate,
+    pub description: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpsertAccountInput {
+    pub id: Option<AccountId>,
+    pub company_id: CompanyId,
+    pub number: i64,
+    pub name: String,
+    pub account_type: String,
+    pub vat_code: Option<String>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUserInput {
+    pub username: String,
+    pub display_name: String,
+    pub password: String,
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetUserRoleInput {
+    pub user_id: UserId,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockDatabaseInput {
+    pub passphrase: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeDatabasePassphraseInput {
+    pub current_passphrase: Option<String>,
+    pub new_passphrase: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateBackupInput {
+    pub directory: Option<String>,
+    pub include_attachments: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateBackupSettingsInput {
+    pub enabled: bool,
+    pub interval_hours: i64,
+    pub retention_count: i64,
+    pub directory: Option<String>,
+    pub include_attachments: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreBackupInput {
+    pub path: String,
+    pub passphrase: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginInput {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordInput {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCompanyInput {
+    pub name: String,
+    pub org_number: Option<String>,
+    pub accounting_method: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpsertCounterpartyInput {
+    pub id: Option<CounterpartyId>,
+    pub company_id: CompanyId,
+    pub name: String,
+    pub org_number: Option<String>,
+    pub vat_number: Option<String>,
+    pub address: Option<String>,
+    pub default_revenue_account_id: Option<AccountId>,
+    pub default_expense_account_id: Option<AccountId>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchCounterpartiesInput {
+    pub company_id: CompanyId,
+    pub query: String,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CounterpartyReportInput {
+    pub company_id: CompanyId,
+    pub counterparty_id: CounterpartyId,
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetAccountingMethodInput {
+    pub company_id: CompanyId,
+    pub accounting_method: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetApprovalRequirementInput {
+    pub company_id: CompanyId,
+    pub requires_approval: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetZeroRowAllowanceInput {
+    pub company_id: CompanyId,
+    pub allow_zero_rows: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoucherApproval {
+    pub id: ApprovalId,
+    pub voucher_id: VoucherId,
+    pub decision: String,
+    pub comment: Option<String>,
+    pub decided_at: String,
+    pub decided_by: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingApproval {
+    pub voucher_id: VoucherId,
+    pub series_id: SeriesId,
+    pub voucher_number: i64,
+    pub date: NaiveDate,
+    pub description: String,
+    pub counterparty: Option<String>,
+    pub total: Money,
+    pub created_at: String,
+    pub created_by: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApproveVoucherInput {
+    pub voucher_id: VoucherId,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectVoucherInput {
+    pub voucher_id: VoucherId,
+    pub comment: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CashYearEndInput {
+    pub company_id: CompanyId,
+    pub year_end_date: NaiveDate,
+    pub series_id: Option<SeriesId>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyIdInput {
+    pub company_id: CompanyId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListVouchersInput {
+    pub company_id: CompanyId,
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+    /// Page size; all vouchers when missing.
+    pub limit: Option<i64>,
+    /// The last voucher of the previous page, to continue after it.
+    pub after: Option<VoucherId>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoucherIdInput {
+    pub voucher_id: VoucherId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockPeriodInput {
+    pub company_id: CompanyId,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodLockIdInput {
+    pub lock_id: PeriodLockId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportVoucherListInput {
+    pub company_id: CompanyId,
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportLedgerInput {
+    pub company_id: CompanyId,
+    pub account_id: AccountId,
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportInput {
+    pub company_id: CompanyId,
+    pub target_path: String,
+}
+
+/// The contents of `vouchers.csv` and `voucher_rows.csv`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvExport {
+    pub vouchers: String,
+    pub voucher_rows: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportArchiveInput {
+    pub company_id: CompanyId,
+    pub fiscal_year_start: NaiveDate,
+    pub target_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportTrialBalanceInput {
+    pub company_id: CompanyId,
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrialBalanceRow {
+    pub account_id: AccountId,
+    pub account_number: i64,
+    pub account_name: String,
+    pub opening: Money,
+    pub debit: Money,
+    pub credit: Money,
+    pub closing: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: ApiTokenId,
+    pub username: String,
+    pub name: String,
+    pub created_at: String,
+    pub created_by: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+/// A new token with its secret, which is only stored hashed and cannot be
+/// shown again.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiToken {
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiTokenInput {
+    pub username: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenIdInput {
+    pub token_id: ApiTokenId,
+}
@@ -0,0 +1,223 @@
+//! Amounts in SEK, kept as whole öre (cents) so that sums are exact.
+//!
+//! In JSON an amount is a decimal string such as `"1234.50"`; in the
+//! database it is an INTEGER of öre.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// An amount in öre.
+///
+/// There are no `+`, `-` or `sum()`: amounts come from users and the
+/// database, so all arithmetic is checked and overflow is reported as an
+/// error rather than a panic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_cents(cents: i64) -> Money {
+        Money(cents)
+    }
+
+    pub const fn cents(self) -> i64 {
+        self.0
+    }
+
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub const fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn checked_abs(self) -> Option<Money> {
+        self.0.checked_abs().map(Money)
+    }
+
+    pub fn checked_neg(self) -> Option<Money> {
+        self.0.checked_neg().map(Money)
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        self.0.checked_add(other.0).map(Money)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        self.0.checked_sub(other.0).map(Money)
+    }
+
+    /// The sum of `amounts`, or an error if it does not fit.
+    pub fn checked_sum<I: IntoIterator<Item = Money>>(amounts: I) -> Result<Money, String> {
+        amounts
+            .into_iter()
+            .try_fold(Money::ZERO, Money::checked_add)
+            .ok_or_else(|| "Amount is too large".to_string())
+    }
+
+    /// The balance after `debit` and `credit` are posted to `self`, or an
+    /// error if it does not fit.
+    pub fn checked_post(self, debit: Money, credit: Money) -> Result<Money, String> {
+        self.checked_add(debit)
+            .and_then(|balance| balance.checked_sub(credit))
+            .ok_or_else(|| "Amount is too large".to_string())
+    }
+
+    /// `self * quantity`, rounded half away from zero to whole öre. `None` if
+    /// `quantity` is not finite or the result does not fit.
+    pub fn checked_mul_quantity(self, quantity: f64) -> Option<Money> {
+        let product = (self.0 as f64 * quantity).round();
+        // `i64::MAX as f64` is 2^63, one past the largest amount.
+        if !product.is_finite() || product < i64::MIN as f64 || product >= i64::MAX as f64 {
+            return None;
+        }
+        Some(Money(product as i64))
+    }
+
+    /// `self * numerator / denominator`, rounded half away from zero to whole
+    /// öre. `None` if the result does not fit or `denominator` is zero.
+    pub fn checked_mul_ratio(self, numerator: i64, denominator: i64) -> Option<Money> {
+        if denominator == 0 {
+            return None;
+        }
+        let product = self.0 as i128 * numerator as i128;
+        let denominator = denominator as i128;
+        let half = denominator.abs() / 2;
+        let rounded = if (product < 0) == (denominator < 0) {
+            (product.abs() + half) / denominator.abs()
+        } else {
+            -((product.abs() + half) / denominator.abs())
+        };
+        i64::try_from(rounded).ok().map(Money)
+    }
+
+    /// `rate_percent` percent of the amount, e.g. VAT on a net amount,
+    /// rounded half away from zero. `None` if the result does not fit.
+    pub fn percent(self, rate_percent: i64) -> Option<Money> {
+        self.checked_mul_ratio(rate_percent, 100)
+    }
+
+    /// Rounded half away from zero to whole kronor (öresavrundning). `None` if
+    /// the result does not fit.
+    pub fn round_to_krona(self) -> Option<Money> {
+        self.checked_mul_ratio(1, 100)
+            .and_then(|kronor| kronor.0.checked_mul(100))
+            .map(Money)
+    }
+
+    /// Swedish notation: space as thousands separator and decimal comma, as
+    /// in "-1 234,50".
+    pub fn to_swedish(self) -> String {
+        let kronor = (self.0.unsigned_abs() / 100).to_string();
+        let mut grouped = String::new();
+        for (index, digit) in kronor.chars().enumerate() {
+            if index > 0 && (kronor.len() - index).is_multiple_of(3) {
+                grouped.push(' ');
+            }
+            grouped.push(digit);
+        }
+        let sign = if self.is_negative() { "-" } else { "" };
+        format!("{}{},{:02}", sign, grouped, self.0.unsigned_abs() % 100)
+    }
+}
+
+/// The decimal form used in JSON and machine-readable exports, "-1234.50".
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+/// Parses "1234.50", "1234,5", "1 234,50" or "-12": a decimal point or comma
+/// with at most two decimals, and spaces between the digits.
+impl FromStr for Money {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Money, String> {
+        let invalid = || format!("Invalid amount: {}", value);
+        let trimmed = value.trim();
+        let (negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let digits: String = unsigned
+            .chars()
+            .filter(|ch| !matches!(ch, ' ' | '\u{a0}' | '\u{202f}'))
+            .collect();
+        let (integer, fraction) = match digits.split_once(['.', ',']) {
+            Some((integer, fraction)) => (integer, fraction),
+            None => (digits.as_str(), ""),
+        };
+        if integer.is_empty() && fraction.is_empty()
+            || fraction.len() > 2
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|ch| ch.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let kronor: i64 = if integer.is_empty() {
+            0
+        } else {
+            integer.parse().map_err(|_| invalid())?
+        };
+        let ore: i64 = format!("{:0<2}", fraction).parse().map_err(|_| invalid())?;
+        let cents = kronor
+            .checked_mul(100)
+            .and_then(|cents| cents.checked_add(ore))
+            .ok_or_else(invalid)?;
+        Ok(Money(if negative { -cents } else { cents }))
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        struct MoneyVisitor;
+
+        impl Visitor<'_> for MoneyVisitor {
+            type Value = Money;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an amount as a decimal string such as \"1234.50\"")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Money, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(MoneyVisitor)
+    }
+}
+
+impl ToSql for Money {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0))
+    }
+}
+
+impl FromSql for Money {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Money> {
+        i64::column_result(value).map(Money)
+    }
+}
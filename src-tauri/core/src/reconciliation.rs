@@ -0,0 +1,245 @@
+use chrono::{Months, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+
+use crate::bank::{bank_account_id, bank_transaction_from_row, BANK_TRANSACTION_COLUMNS};
+use crate::ids::{CompanyId, ReconciliationId};
+use crate::models::{
+    BankReconciliation, BankReconciliationReport, CompanyIdInput, ReconcileBankInput,
+    ReportLedgerInput, SignOffReconciliationInput, UnmatchedLedgerRow,
+};
+use crate::money::Money;
+use crate::{fetch_ledger, insert_audit_entry, map_sql_error, Books};
+
+impl Books<'_> {
+    /// Compares a bank statement closing balance with the 1930 ledger balance on
+    /// the same date and lists the items that explain any difference.
+    pub fn report_bank_reconciliation(
+        &mut self,
+        payload: ReconcileBankInput,
+    ) -> Result<BankReconciliationReport, String> {
+        let conn = &*self.conn;
+        build_report(
+            conn,
+            &payload.company_id,
+            payload.statement_date,
+            payload.statement_balance,
+        )
+    }
+
+    /// Stores a signed-off reconciliation for a month (`YYYY-MM`). A remaining
+    /// difference is only accepted together with a note explaining it.
+    pub fn sign_off_bank_reconciliation(
+        &mut self,
+        payload: SignOffReconciliationInput,
+    ) -> Result<BankReconciliation, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let (period, statement_date) = month_end(&payload.period)?;
+        let note = payload.note.filter(|note| !note.trim().is_empty());
+
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let report = build_report(
+            &tx,
+            &payload.company_id,
+            statement_date,
+            payload.statement_balance,
+        )?;
+        if !report.difference.is_zero() && note.is_none() {
+            return Err(format!(
+                "Statement and ledger differ by {}; add a note explaining the difference",
+                report.difference
+            ));
+        }
+        let existing: Option<String> = tx
+            .query_row(
+                "SELECT id FROM bank_reconciliations WHERE company_id = ?1 AND account_id = ?2 AND period = ?3",
+                params![&payload.company_id, &report.account_id, &period],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(map_sql_error)?;
+        if existing.is_some() {
+            return Err(format!("Reconciliation for {} is already signed off", period));
+        }
+
+        let reconciliation = BankReconciliation {
+            id: ReconciliationId::generate(),
+            company_id: payload.company_id,
+            account_id: report.account_id,
+            period,
+            statement_date,
+            statement_balance: report.statement_balance,
+            ledger_balance: report.ledger_balance,
+            difference: report.difference,
+            unmatched_ledger_count: report.unmatched_ledger_rows.len() as i64,
+            unmatched_statement_count: report.unmatched_statement_lines.len() as i64,
+            note,
+            signed_off_at: Utc::now().to_rfc3339(),
+            signed_off_by: user.to_string(),
+        };
+        tx.execute(
+            "INSERT INTO bank_reconciliations (id, company_id, account_id, period, statement_date, statement_balance_cents, ledger_balance_cents, difference_cents, unmatched_ledger_count, unmatched_statement_count, note, signed_off_at, signed_off_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                &reconciliation.id,
+                &reconciliation.company_id,
+                &reconciliation.account_id,
+                &reconciliation.period,
+                &reconciliation.statement_date,
+                reconciliation.statement_balance,
+                reconciliation.ledger_balance,
+                reconciliation.difference,
+                reconciliation.unmatched_ledger_count,
+                reconciliation.unmatched_statement_count,
+                &reconciliation.note,
+                &reconciliation.signed_off_at,
+                &reconciliation.signed_off_by
+            ],
+        )
+        .map_err(map_sql_error)?;
+        insert_audit_entry(
+            &tx,
+            user,
+            &reconciliation.company_id,
+            "bank_reconciliation",
+            &reconciliation.id,
+            "sign_off",
+            json!({
+                "period": &reconciliation.period,
+                "statement_balance_cents": reconciliation.statement_balance.cents(),
+                "ledger_balance_cents": reconciliation.ledger_balance.cents(),
+                "difference_cents": reconciliation.difference.cents()
+            }),
+        )?;
+        tx.commit().map_err(map_sql_error)?;
+
+        Ok(reconciliation)
+    }
+
+    pub fn list_bank_reconciliations(
+        &mut self,
+        payload: CompanyIdInput,
+    ) -> Result<Vec<BankReconciliation>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, company_id, account_id, period, statement_date, statement_balance_cents, ledger_balance_cents, difference_cents, unmatched_ledger_count, unmatched_statement_count, note, signed_off_at, signed_off_by
+                 FROM bank_reconciliations WHERE company_id = ?1 ORDER BY period DESC",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], |row| {
+                Ok(BankReconciliation {
+                    id: row.get(0)?,
+                    company_id: row.get(1)?,
+                    account_id: row.get(2)?,
+                    period: row.get(3)?,
+                    statement_date: row.get(4)?,
+                    statement_balance: row.get(5)?,
+                    ledger_balance: row.get(6)?,
+                    difference: row.get(7)?,
+                    unmatched_ledger_count: row.get(8)?,
+                    unmatched_statement_count: row.get(9)?,
+                    note: row.get(10)?,
+                    signed_off_at: row.get(11)?,
+                    signed_off_by: row.get(12)?,
+                })
+            })
+            .map_err(map_sql_error)?;
+        let mut reconciliations = Vec::new();
+        for row in rows {
+            reconciliations.push(row.map_err(map_sql_error)?);
+        }
+        Ok(reconciliations)
+    }
+}
+
+fn build_report(
+    conn: &Connection,
+    company_id: &CompanyId,
+    statement_date: NaiveDate,
+    statement_balance: Money,
+) -> Result<BankReconciliationReport, String> {
+    let account_id = bank_account_id(conn, company_id)?;
+    let ledger = fetch_ledger(
+        conn,
+        &ReportLedgerInput {
+            company_id: company_id.clone(),
+            account_id: account_id.clone(),
+            from_date: None,
+            to_date: Some(statement_date),
+        },
+    )?;
+    let ledger_balance = ledger.last().map(|row| row.balance).unwrap_or_default();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT v.id, v.voucher_number, v.date, v.description, SUM(r.debit_cents - r.credit_cents)
+             FROM vouchers v
+             JOIN voucher_rows r ON r.voucher_id = v.id
+             WHERE v.company_id = ?1 AND r.account_id = ?2 AND v.date <= ?3
+               AND NOT EXISTS (SELECT 1 FROM bank_transactions b WHERE b.voucher_id = v.id)
+             GROUP BY v.id
+             ORDER BY v.date ASC, v.voucher_number ASC",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map(params![company_id, &account_id, statement_date], |row| {
+            Ok(UnmatchedLedgerRow {
+                voucher_id: row.get(0)?,
+                voucher_number: row.get(1)?,
+                date: row.get(2)?,
+                description: row.get(3)?,
+                amount: row.get(4)?,
+            })
+        })
+        .map_err(map_sql_error)?;
+    let mut unmatched_ledger_rows = Vec::new();
+    for row in rows {
+        unmatched_ledger_rows.push(row.map_err(map_sql_error)?);
+    }
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM bank_transactions
+             WHERE company_id = ?1 AND account_id = ?2 AND booking_date <= ?3 AND voucher_id IS NULL
+             ORDER BY booking_date ASC",
+            BANK_TRANSACTION_COLUMNS
+        ))
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map(
+            params![company_id, &account_id, statement_date],
+            bank_transaction_from_row,
+        )
+        .map_err(map_sql_error)?;
+    let mut unmatched_statement_lines = Vec::new();
+    for row in rows {
+        unmatched_statement_lines.push(row.map_err(map_sql_error)?);
+    }
+
+    Ok(BankReconciliationReport {
+        company_id: company_id.clone(),
+        account_id,
+        statement_date,
+        statement_balance,
+        ledger_balance,
+        difference: statement_balance
+            .checked_sub(ledger_balance)
+            .ok_or("Amount is too large")?,
+        unmatched_ledger_rows,
+        unmatched_statement_lines,
+    })
+}
+
+/// Returns the normalized period and its last date.
+fn month_end(period: &str) -> Result<(String, NaiveDate), String> {
+    let first = NaiveDate::parse_from_str(&format!("{}-01", period), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid period: {} (expected YYYY-MM)", period))?;
+    let last = first
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .ok_or_else(|| format!("Invalid period: {}", period))?;
+    Ok((first.format("%Y-%m").to_string(), last))
+}
@@ -0,0 +1,113 @@
+use chrono::Utc;
+
+use crate::archive::YearBooks;
+use crate::money::Money;
+
+/// Accounts below this number are balance sheet accounts and get opening and
+/// closing balances; the rest are result accounts.
+pub const FIRST_RESULT_ACCOUNT: i64 = 3000;
+
+/// SIE 4 file (type 4: balances and vouchers) for one fiscal year, encoded in
+/// PC8 (code page 437) as the format requires.
+pub fn sie4(books: &YearBooks, generated_by: &str) -> Result<Vec<u8>, String> {
+    let mut lines = vec![
+        "#FLAGGA 0".to_string(),
+        format!("#PROGRAM {} {}", quoted("Bokforingsprogram"), env!("CARGO_PKG_VERSION")),
+        "#FORMAT PC8".to_string(),
+        format!(
+            "#GEN {} {}",
+            Utc::now().format("%Y%m%d"),
+            quoted(generated_by)
+        ),
+        "#SIETYP 4".to_string(),
+        format!("#FNAMN {}", quoted(&books.company_name)),
+    ];
+    if let Some(org_number) = &books.org_number {
+        lines.push(format!("#ORGNR {}", org_number));
+    }
+    lines.push(format!(
+        "#RAR 0 {} {}",
+        books.year_start.format("%Y%m%d"),
+        books.year_end.format("%Y%m%d")
+    ));
+
+    for account in &books.accounts {
+        lines.push(format!("#KONTO {} {}", account.number, quoted(&account.name)));
+        if let Some(account_type) = account_type(&account.account_type) {
+            lines.push(format!("#KTYP {} {}", account.number, account_type));
+        }
+    }
+    for account in &books.accounts {
+        if account.number < FIRST_RESULT_ACCOUNT {
+            lines.push(format!("#IB 0 {} {}", account.number, account.opening));
+            lines.push(format!("#UB 0 {} {}", account.number, account.closing));
+        } else if !account.closing.is_zero() {
+            lines.push(format!("#RES 0 {} {}", account.number, account.closing));
+        }
+    }
+
+    for voucher in &books.vouchers {
+        lines.push(format!(
+            "#VER {} {} {} {} {}",
+            quoted(&voucher.series_code),
+            voucher.voucher_number,
+            voucher.date.replace('-', ""),
+            quoted(&voucher.description),
+            voucher.created_at.get(..10).unwrap_or_default().replace('-', "")
+        ));
+        lines.push("{".to_string());
+        for row in &voucher.rows {
+            lines.push(format!(
+                "   #TRANS {} {{}} {}",
+                row.account_number,
+                Money::ZERO.checked_post(row.debit, row.credit)?
+            ));
+        }
+        lines.push("}".to_string());
+    }
+
+    let mut text = lines.join("\r\n");
+    text.push_str("\r\n");
+    Ok(pc8(&text))
+}
+
+/// `#KTYP`: T tillgång, S skuld, I intäkt, K kostnad.
+fn account_type(account_type: &str) -> Option<&'static str> {
+    match account_type {
+        "Asset" => Some("T"),
+        "Liability" | "Equity" => Some("S"),
+        "Income" | "Revenue" => Some("I"),
+        "Expense" => Some("K"),
+        _ => None,
+    }
+}
+
+fn quoted(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .map(|ch| if ch.is_control() { ' ' } else { ch })
+        .collect();
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Encodes text in code page 437. Characters outside it become `?`.
+fn pc8(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|ch| match ch {
+            ch if ch.is_ascii() => ch as u8,
+            'ü' => 0x81,
+            'é' => 0x82,
+            'ä' => 0x84,
+            'å' => 0x86,
+            'Ä' => 0x8E,
+            'Å' => 0x8F,
+            'É' => 0x90,
+            'æ' => 0x91,
+            'Æ' => 0x92,
+            'ö' => 0x94,
+            'Ö' => 0x99,
+            'Ü' => 0x9A,
+            _ => b'?',
+        })
+        .collect()
+}
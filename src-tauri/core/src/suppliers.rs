@@ -0,0 +1,599 @@
+use std::collections::BTreeMap;
+
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde_json::json;
+
+use crate::db::{
+    accounting_method, bas_account_id, default_series_id, CASH_METHOD,
+};
+use crate::invoicing::{
+    aging_row_from_row, cash_share, credit_row, open_amount, payment_from_row, summarize_aging,
+};
+use crate::ids::{AccountId, InvoiceId, InvoiceLineId, PaymentId, SupplierId};
+use crate::models::{
+    AgingReport, AgingReportInput, CompanyIdInput, CreateSupplierInvoiceInput, CreateVoucherInput,
+    CreateVoucherRowInput, InvoiceIdInput, ListInvoicesInput, RegisterPaymentInput, Supplier,
+    SupplierInvoice, SupplierInvoiceLine, UpsertSupplierInput,
+};
+use crate::money::Money;
+use crate::{ensure_company, insert_audit_entry, insert_voucher, map_error, map_sql_error, Books};
+
+pub const PAYABLES_ACCOUNT_NUMBER: i64 = 2440;
+pub const INPUT_VAT_ACCOUNT_NUMBER: i64 = 2641;
+const DEFAULT_PAYMENT_TERMS_DAYS: i64 = 30;
+const MAX_PAYMENT_TERMS_DAYS: i64 = 365;
+
+const SUPPLIER_COLUMNS: &str = "id, company_id, supplier_number, name, org_number, bankgiro, default_expense_account_id, payment_terms_days, created_at";
+
+const INVOICE_QUERY: &str = "SELECT i.id, i.company_id, i.supplier_id, s.name, i.invoice_number, i.invoice_date, i.due_date, i.payment_reference, i.net_cents, i.vat_cents, i.total_cents,
+        COALESCE((SELECT SUM(p.amount_cents) FROM supplier_payments p WHERE p.invoice_id = i.id), 0),
+        i.voucher_id, i.created_at, i.created_by
+ FROM supplier_invoices i
+ JOIN suppliers s ON s.id = i.supplier_id";
+
+impl Books<'_> {
+    pub fn list_suppliers(&mut self, payload: CompanyIdInput) -> Result<Vec<Supplier>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM suppliers WHERE company_id = ?1 ORDER BY supplier_number",
+                SUPPLIER_COLUMNS
+            ))
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], supplier_from_row)
+            .map_err(map_sql_error)?;
+        let mut suppliers = Vec::new();
+        for row in rows {
+            suppliers.push(row.map_err(map_sql_error)?);
+        }
+        Ok(suppliers)
+    }
+
+    pub fn upsert_supplier(&mut self, payload: UpsertSupplierInput) -> Result<Supplier, String> {
+        let conn = &mut *self.conn;
+        if payload.name.trim().is_empty() {
+            return Err("Supplier name is required".to_string());
+        }
+        let payment_terms_days = payload.payment_terms_days.unwrap_or(DEFAULT_PAYMENT_TERMS_DAYS);
+        if payment_terms_days < 0 {
+            return Err("Payment terms cannot be negative".to_string());
+        }
+        if payment_terms_days > MAX_PAYMENT_TERMS_DAYS {
+            return Err(format!("Payment terms cannot exceed {} days", MAX_PAYMENT_TERMS_DAYS));
+        }
+
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        if let Some(account_id) = &payload.default_expense_account_id {
+            expense_account_number(&tx, &payload.company_id, account_id)?;
+        }
+        let now = Utc::now().to_rfc3339();
+        let supplier_id = payload
+            .id
+            .clone()
+            .unwrap_or_else(SupplierId::generate);
+        ensure_company(&tx, "suppliers", &supplier_id, &payload.company_id, "Supplier")?;
+        let next_number: i64 = tx
+            .query_row(
+                "SELECT COALESCE(MAX(supplier_number), 0) + 1 FROM suppliers WHERE company_id = ?1",
+                [&payload.company_id],
+                |row| row.get(0),
+            )
+            .map_err(map_sql_error)?;
+        tx.execute(
+            "INSERT INTO suppliers (id, company_id, supplier_number, name, org_number, bankgiro, default_expense_account_id, payment_terms_days, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(id) DO UPDATE SET
+               name = excluded.name,
+               org_number = excluded.org_number,
+               bankgiro = excluded.bankgiro,
+               default_expense_account_id = excluded.default_expense_account_id,
+               payment_terms_days = excluded.payment_terms_days",
+            params![
+                supplier_id,
+                &payload.company_id,
+                next_number,
+                &payload.name,
+                &payload.org_number,
+                &payload.bankgiro,
+                &payload.default_expense_account_id,
+                payment_terms_days,
+                now
+            ],
+        )
+        .map_err(map_sql_error)?;
+        let supplier = fetch_supplier(&tx, &supplier_id)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(supplier)
+    }
+
+    /// Registers a supplier invoice (leverantörsfaktura) and books the purchase
+    /// voucher: expense accounts and 2641 against 2440.
+    pub fn create_supplier_invoice(
+        &mut self,
+        payload: CreateSupplierInvoiceInput,
+    ) -> Result<SupplierInvoice, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        if payload.lines.is_empty() {
+            return Err("Invoice must have lines".to_string());
+        }
+        if payload.invoice_number.trim().is_empty() {
+            return Err("Supplier invoice number is required".to_string());
+        }
+        let invoice_date = payload.invoice_date;
+
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let supplier = fetch_supplier(&tx, &payload.supplier_id)?;
+        if supplier.company_id != payload.company_id {
+            return Err("Supplier does not belong to company".to_string());
+        }
+        let duplicate: Option<String> = tx
+            .query_row(
+                "SELECT id FROM supplier_invoices WHERE supplier_id = ?1 AND invoice_number = ?2",
+                params![&supplier.id, payload.invoice_number.trim()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(map_sql_error)?;
+        if duplicate.is_some() {
+            return Err(format!(
+                "Invoice {} from {} is already registered",
+                payload.invoice_number.trim(),
+                supplier.name
+            ));
+        }
+        let due_date = match payload.due_date {
+            Some(due_date) => due_date,
+            None => Duration::try_days(supplier.payment_terms_days)
+                .and_then(|terms| invoice_date.checked_add_signed(terms))
+                .ok_or("Payment terms are too long")?,
+        };
+        if due_date < invoice_date {
+            return Err("Due date is before invoice date".to_string());
+        }
+
+        let mut lines = Vec::new();
+        let invoice_id = InvoiceId::generate();
+        for (index, line) in payload.lines.iter().enumerate() {
+            let expense_account_id = line
+                .expense_account_id
+                .clone()
+                .or_else(|| supplier.default_expense_account_id.clone())
+                .ok_or_else(|| format!("Row {}: expense account is required", index + 1))?;
+            expense_account_number(&tx, &payload.company_id, &expense_account_id)
+                .map_err(|err| format!("Row {}: {}", index + 1, err))?;
+            if line.vat.is_negative() || line.net.is_negative() {
+                return Err(format!("Row {}: amounts cannot be negative", index + 1));
+            }
+            lines.push(SupplierInvoiceLine {
+                id: InvoiceLineId::generate(),
+                invoice_id: invoice_id.clone(),
+                description: line.description.clone(),
+                expense_account_id,
+                net: line.net,
+                vat: line.vat,
+            });
+        }
+        let net = Money::checked_sum(lines.iter().map(|line| line.net))?;
+        let vat = Money::checked_sum(lines.iter().map(|line| line.vat))?;
+        let total = Money::checked_sum([net, vat])?;
+        if !total.is_positive() {
+            return Err("Invoice total must be positive".to_string());
+        }
+
+        let series_id = match &payload.series_id {
+            Some(series_id) => series_id.clone(),
+            None => default_series_id(&tx, &payload.company_id).map_err(map_error)?,
+        };
+        // Under kontantmetoden nothing is booked until the invoice is paid.
+        let now = Utc::now().to_rfc3339();
+        let voucher_id = if accounting_method(&tx, &payload.company_id).map_err(map_error)? == CASH_METHOD {
+            None
+        } else {
+            let mut rows: Vec<CreateVoucherRowInput> =
+                expense_amounts(&tx, user, &payload.company_id, &lines)?
+                    .into_iter()
+                    .map(|(account_id, amount)| debit_row(account_id, amount))
+                    .collect::<Result<_, _>>()?;
+            rows.push(credit_row(
+                bas_account_id(&tx, user, &payload.company_id, PAYABLES_ACCOUNT_NUMBER)
+                    .map_err(map_error)?,
+                total,
+            )?);
+            rows.retain(|row| !row.debit.is_zero() || !row.credit.is_zero());
+
+            let voucher = insert_voucher(
+                &tx,
+                user,
+                &CreateVoucherInput {
+                    company_id: payload.company_id.clone(),
+                    series_id,
+                    date: invoice_date,
+                    description: format!(
+                        "Leverantörsfaktura {} {}",
+                        payload.invoice_number.trim(),
+                        supplier.name
+                    ),
+                    counterparty: Some(supplier.name.clone()),
+                    counterparty_id: None,
+                    rows,
+                    attachments: None,
+                },
+            )?;
+            Some(voucher.id)
+        };
+
+        tx.execute(
+            "INSERT INTO supplier_invoices (id, company_id, supplier_id, invoice_number, invoice_date, due_date, payment_reference, net_cents, vat_cents, total_cents, voucher_id, created_at, created_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                invoice_id,
+                &payload.company_id,
+                &supplier.id,
+                payload.invoice_number.trim(),
+                invoice_date,
+                due_date,
+                &payload.payment_reference,
+                net,
+                vat,
+                total,
+                &voucher_id,
+                now,
+                user
+            ],
+        )
+        .map_err(map_sql_error)?;
+        for (index, line) in lines.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO supplier_invoice_lines (id, invoice_id, sort_order, description, expense_account_id, net_cents, vat_cents)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    line.id,
+                    invoice_id,
+                    index as i64,
+                    line.description,
+                    line.expense_account_id,
+                    line.net,
+                    line.vat
+                ],
+            )
+            .map_err(map_sql_error)?;
+        }
+        insert_audit_entry(
+            &tx,
+            user,
+            &payload.company_id,
+            "supplier_invoice",
+            &invoice_id,
+            "create",
+            json!({
+                "supplier_id": &supplier.id,
+                "invoice_number": payload.invoice_number.trim(),
+                "total_cents": total.cents(),
+                "voucher_id": &voucher_id
+            }),
+        )?;
+
+        let invoice = fetch_invoice(&tx, &invoice_id)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(invoice)
+    }
+
+    pub fn list_supplier_invoices(
+        &mut self,
+        payload: ListInvoicesInput,
+    ) -> Result<Vec<SupplierInvoice>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(&format!(
+                "{} WHERE i.company_id = ?1 ORDER BY i.due_date DESC, i.created_at DESC",
+                INVOICE_QUERY
+            ))
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], invoice_from_row)
+            .map_err(map_sql_error)?;
+        let open_only = payload.open_only.unwrap_or(false);
+        let mut invoices = Vec::new();
+        for row in rows {
+            let invoice = row.map_err(map_sql_error)?;
+            if !open_only || !invoice.open.is_zero() {
+                invoices.push(invoice);
+            }
+        }
+        Ok(invoices)
+    }
+
+    pub fn get_supplier_invoice(
+        &mut self,
+        payload: InvoiceIdInput,
+    ) -> Result<SupplierInvoice, String> {
+        let conn = &*self.conn;
+        fetch_invoice(conn, &payload.invoice_id)
+    }
+
+    /// Registers a (partial) payment and books 2440 against 1930.
+    pub fn register_supplier_payment(
+        &mut self,
+        payload: RegisterPaymentInput,
+    ) -> Result<SupplierInvoice, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let payment_date = payload.payment_date;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let invoice = fetch_invoice(&tx, &payload.invoice_id)?;
+        if !payload.amount.is_positive() {
+            return Err("Payment amount must be positive".to_string());
+        }
+        if payload.amount > invoice.open {
+            return Err(format!(
+                "Payment exceeds the open balance of {}",
+                invoice.open
+            ));
+        }
+
+        let series_id = match &payload.series_id {
+            Some(series_id) => series_id.clone(),
+            None => default_series_id(&tx, &invoice.company_id).map_err(map_error)?,
+        };
+        let mut rows = Vec::new();
+        if invoice.voucher_id.is_some() {
+            rows.push(debit_row(
+                bas_account_id(&tx, user, &invoice.company_id, PAYABLES_ACCOUNT_NUMBER)
+                    .map_err(map_error)?,
+                payload.amount,
+            )?);
+        } else {
+            // Invoice booked under kontantmetoden: the payment books its share of
+            // the expenses and input VAT.
+            let lines = invoice.lines.as_deref().unwrap_or_default();
+            let amounts = expense_amounts(&tx, user, &invoice.company_id, lines)?;
+            let paid = invoice
+                .paid
+                .checked_add(payload.amount)
+                .ok_or("Amount is too large")?;
+            let shares = cash_share(&amounts, invoice.total, invoice.paid, paid)?;
+            for (account_id, amount) in shares {
+                rows.push(debit_row(account_id, amount)?);
+            }
+            rows.retain(|row| !row.debit.is_zero() || !row.credit.is_zero());
+        }
+        rows.push(credit_row(
+            bas_account_id(&tx, user, &invoice.company_id, crate::bank::BANK_ACCOUNT_NUMBER)
+                .map_err(map_error)?,
+            payload.amount,
+        )?);
+        let voucher = insert_voucher(
+            &tx,
+            user,
+            &CreateVoucherInput {
+                company_id: invoice.company_id.clone(),
+                series_id,
+                date: payment_date,
+                description: format!("Betalning leverantörsfaktura {}", invoice.invoice_number),
+                counterparty: Some(invoice.supplier_name.clone()),
+                counterparty_id: None,
+                rows,
+                attachments: None,
+            },
+        )?;
+
+        let payment_id = PaymentId::generate();
+        tx.execute(
+            "INSERT INTO supplier_payments (id, invoice_id, payment_date, amount_cents, voucher_id, created_at, created_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                payment_id,
+                &invoice.id,
+                payment_date,
+                payload.amount,
+                &voucher.id,
+                &voucher.created_at,
+                user
+            ],
+        )
+        .map_err(map_sql_error)?;
+        insert_audit_entry(
+            &tx,
+            user,
+            &invoice.company_id,
+            "supplier_invoice",
+            &invoice.id,
+            "payment",
+            json!({
+                "payment_id": payment_id,
+                "amount_cents": payload.amount.cents(),
+                "voucher_id": &voucher.id
+            }),
+        )?;
+
+        let invoice = fetch_invoice(&tx, &invoice.id)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(invoice)
+    }
+
+    /// Accounts payable (leverantörsreskontra) aging per `as_of_date`, default today.
+    pub fn report_accounts_payable(
+        &mut self,
+        payload: AgingReportInput,
+    ) -> Result<AgingReport, String> {
+        let conn = &*self.conn;
+        let as_of_date = payload.as_of_date.unwrap_or_else(|| Utc::now().date_naive());
+        let mut stmt = conn
+            .prepare(
+                "SELECT i.id, i.supplier_id, s.name, i.invoice_number, i.invoice_date, i.due_date, i.total_cents,
+                        i.total_cents - COALESCE((SELECT SUM(p.amount_cents) FROM supplier_payments p
+                                                  WHERE p.invoice_id = i.id AND p.payment_date <= ?2), 0)
+                 FROM supplier_invoices i
+                 JOIN suppliers s ON s.id = i.supplier_id
+                 WHERE i.company_id = ?1 AND i.invoice_date <= ?2
+                 ORDER BY i.due_date ASC, s.name ASC",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map(
+                params![payload.company_id, as_of_date],
+                |row| aging_row_from_row(row, as_of_date),
+            )
+            .map_err(map_sql_error)?;
+        let mut aging_rows = Vec::new();
+        for row in rows {
+            aging_rows.push(row.map_err(map_sql_error)?);
+        }
+        summarize_aging(as_of_date, aging_rows)
+    }
+}
+
+/// Checks that the account belongs to the company and is a cost account
+/// (4000–6999) and returns its number.
+fn expense_account_number(conn: &Connection, company_id: &str, account_id: &str) -> Result<i64, String> {
+    let number: i64 = conn
+        .query_row(
+            "SELECT number FROM accounts WHERE id = ?1 AND company_id = ?2",
+            params![account_id, company_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(map_sql_error)?
+        .ok_or_else(|| "Account not found".to_string())?;
+    if !(4000..=6999).contains(&number) {
+        return Err(format!("Account {} is not a cost account (4000-6999)", number));
+    }
+    Ok(number)
+}
+
+/// Expenses per account and input VAT, as signed debit amounts that add up to
+/// the invoice total.
+pub fn expense_amounts(
+    conn: &Connection,
+    user: &str,
+    company_id: &str,
+    lines: &[SupplierInvoiceLine],
+) -> Result<Vec<(AccountId, Money)>, String> {
+    let mut expenses: BTreeMap<&AccountId, Money> = BTreeMap::new();
+    for line in lines {
+        let amount = expenses.entry(&line.expense_account_id).or_default();
+        *amount = Money::checked_sum([*amount, line.net])?;
+    }
+    let mut amounts: Vec<(AccountId, Money)> = expenses
+        .into_iter()
+        .map(|(account_id, amount)| (account_id.clone(), amount))
+        .collect();
+    let vat = Money::checked_sum(lines.iter().map(|line| line.vat))?;
+    if !vat.is_zero() {
+        amounts.push((
+            bas_account_id(conn, user, company_id, INPUT_VAT_ACCOUNT_NUMBER).map_err(map_error)?,
+            vat,
+        ));
+    }
+    Ok(amounts)
+}
+
+/// A debit row, or a credit row when the amount is negative.
+pub fn debit_row(account_id: AccountId, amount: Money) -> Result<CreateVoucherRowInput, String> {
+    let negated = amount.checked_neg().ok_or("Amount is too large")?;
+    Ok(CreateVoucherRowInput {
+        account_id,
+        description: None,
+        debit: amount.max(Money::ZERO),
+        credit: negated.max(Money::ZERO),
+        vat_code: None,
+    })
+}
+
+fn fetch_supplier(conn: &Connection, supplier_id: &str) -> Result<Supplier, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM suppliers WHERE id = ?1", SUPPLIER_COLUMNS),
+        [supplier_id],
+        supplier_from_row,
+    )
+    .map_err(map_sql_error)
+}
+
+pub fn fetch_invoice(conn: &Connection, invoice_id: &str) -> Result<SupplierInvoice, String> {
+    let mut invoice = conn
+        .query_row(&format!("{} WHERE i.id = ?1", INVOICE_QUERY), [invoice_id], invoice_from_row)
+        .map_err(map_sql_error)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, invoice_id, description, expense_account_id, net_cents, vat_cents
+             FROM supplier_invoice_lines WHERE invoice_id = ?1 ORDER BY sort_order",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([invoice_id], |row| {
+            Ok(SupplierInvoiceLine {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                description: row.get(2)?,
+                expense_account_id: row.get(3)?,
+                net: row.get(4)?,
+                vat: row.get(5)?,
+            })
+        })
+        .map_err(map_sql_error)?;
+    let mut lines = Vec::new();
+    for row in rows {
+        lines.push(row.map_err(map_sql_error)?);
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, invoice_id, payment_date, amount_cents, voucher_id, created_at, created_by
+             FROM supplier_payments WHERE invoice_id = ?1 ORDER BY payment_date",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([invoice_id], payment_from_row)
+        .map_err(map_sql_error)?;
+    let mut payments = Vec::new();
+    for row in rows {
+        payments.push(row.map_err(map_sql_error)?);
+    }
+
+    invoice.lines = Some(lines);
+    invoice.payments = Some(payments);
+    Ok(invoice)
+}
+
+fn invoice_from_row(row: &Row) -> rusqlite::Result<SupplierInvoice> {
+    let total: Money = row.get(10)?;
+    let paid: Money = row.get(11)?;
+    Ok(SupplierInvoice {
+        id: row.get(0)?,
+        company_id: row.get(1)?,
+        supplier_id: row.get(2)?,
+        supplier_name: row.get(3)?,
+        invoice_number: row.get(4)?,
+        invoice_date: row.get(5)?,
+        due_date: row.get(6)?,
+        payment_reference: row.get(7)?,
+        net: row.get(8)?,
+        vat: row.get(9)?,
+        total,
+        paid,
+        open: open_amount(total, paid)?,
+        voucher_id: row.get(12)?,
+        created_at: row.get(13)?,
+        created_by: row.get(14)?,
+        lines: None,
+        payments: None,
+    })
+}
+
+fn supplier_from_row(row: &Row) -> rusqlite::Result<Supplier> {
+    Ok(Supplier {
+        id: row.get(0)?,
+        company_id: row.get(1)?,
+        supplier_number: row.get(2)?,
+        name: row.get(3)?,
+        org_number: row.get(4)?,
+        bankgiro: row.get(5)?,
+        default_expense_account_id: row.get(6)?,
+        payment_terms_days: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
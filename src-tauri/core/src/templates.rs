@@ -0,0 +1,594 @@
+use chrono::{Datelike, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+
+use crate::ids::{ScheduleId, SeriesId, TemplateId, TemplateRowId};
+use crate::models::{
+    CompanyIdInput, CreateVoucherInput, CreateVoucherRowInput, InstantiateTemplateInput,
+    RecurringRunFailure, RecurringRunResult, RecurringSchedule, RunRecurringSchedulesInput,
+    UpsertRecurringScheduleInput, UpsertVoucherTemplateInput, Voucher, VoucherTemplate,
+    VoucherTemplateRow,
+};
+use crate::money::Money;
+use crate::{ensure_company, insert_audit_entry, insert_voucher, map_sql_error, Books};
+
+const FULL_PERCENT_BASIS_POINTS: i64 = 10_000;
+
+impl Books<'_> {
+    pub fn list_voucher_templates(
+        &mut self,
+        payload: CompanyIdInput,
+    ) -> Result<Vec<VoucherTemplate>, String> {
+        let conn = &*self.conn;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, company_id, name, description, is_active, created_at
+                 FROM voucher_templates WHERE company_id = ?1 ORDER BY name",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([payload.company_id], |row| {
+                Ok(VoucherTemplate {
+                    id: row.get(0)?,
+                    company_id: row.get(1)?,
+                    name: row.get(2)?,
+                    description: row.get(3)?,
+                    is_active: row.get::<_, i64>(4)? == 1,
+                    created_at: row.get(5)?,
+                    rows: Vec::new(),
+                })
+            })
+            .map_err(map_sql_error)?;
+        let mut templates = Vec::new();
+        for row in rows {
+            let mut template = row.map_err(map_sql_error)?;
+            template.rows = fetch_template_rows(conn, &template.id)?;
+            templates.push(template);
+        }
+        Ok(templates)
+    }
+
+    /// Creates or replaces a template. Percentages and fixed amounts must balance
+    /// separately, so every instantiated voucher balances whatever the total is.
+    pub fn upsert_voucher_template(
+        &mut self,
+        payload: UpsertVoucherTemplateInput,
+    ) -> Result<VoucherTemplate, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        if payload.name.trim().is_empty() {
+            return Err("Template name is required".to_string());
+        }
+        if payload.rows.len() < 2 {
+            return Err("Template must have at least two rows".to_string());
+        }
+        let mut percent_balance = 0;
+        let mut fixed_balance = Money::ZERO;
+        for (index, row) in payload.rows.iter().enumerate() {
+            let sign = match row.side.as_str() {
+                "debit" => 1,
+                "credit" => -1,
+                _ => return Err(format!("Row {}: side must be debit or credit", index + 1)),
+            };
+            match (row.percent_basis_points, row.fixed_amount) {
+                (Some(percent), None) if percent > 0 && percent <= FULL_PERCENT_BASIS_POINTS => {
+                    percent_balance += sign * percent;
+                }
+                (None, Some(fixed)) if fixed.is_positive() => {
+                    fixed_balance = if sign > 0 {
+                        fixed_balance.checked_post(fixed, Money::ZERO)?
+                    } else {
+                        fixed_balance.checked_post(Money::ZERO, fixed)?
+                    };
+                }
+                _ => {
+                    return Err(format!(
+                        "Row {}: set either a percentage (0-100%) or a positive fixed amount",
+                        index + 1
+                    ))
+                }
+            }
+        }
+        if percent_balance != 0 || !fixed_balance.is_zero() {
+            return Err("Template does not balance".to_string());
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let template_id = payload
+            .id
+            .clone()
+            .unwrap_or_else(TemplateId::generate);
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        ensure_company(&tx, "voucher_templates", &template_id, &payload.company_id, "Template")?;
+        for (index, row) in payload.rows.iter().enumerate() {
+            let exists: Option<i64> = tx
+                .query_row(
+                    "SELECT 1 FROM accounts WHERE id = ?1 AND company_id = ?2",
+                    params![&row.account_id, &payload.company_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(map_sql_error)?;
+            if exists.is_none() {
+                return Err(format!("Row {}: Account does not belong to company", index + 1));
+            }
+        }
+        tx.execute(
+            "INSERT INTO voucher_templates (id, company_id, name, description, is_active, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(id) DO UPDATE SET
+               name = excluded.name,
+               description = excluded.description,
+               is_active = excluded.is_active",
+            params![
+                template_id,
+                &payload.company_id,
+                &payload.name,
+                &payload.description,
+                if payload.is_active { 1 } else { 0 },
+                now
+            ],
+        )
+        .map_err(map_sql_error)?;
+        tx.execute(
+            "DELETE FROM voucher_template_rows WHERE template_id = ?1",
+            [&template_id],
+        )
+        .map_err(map_sql_error)?;
+        for (index, row) in payload.rows.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO voucher_template_rows (id, template_id, sort_order, account_id, description, side, percent_basis_points, fixed_cents, vat_code)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    TemplateRowId::generate(),
+                    template_id,
+                    index as i64,
+                    row.account_id,
+                    row.description,
+                    row.side,
+                    row.percent_basis_points,
+                    row.fixed_amount,
+                    row.vat_code
+                ],
+            )
+            .map_err(map_sql_error)?;
+        }
+        insert_audit_entry(
+            &tx,
+            user,
+            &payload.company_id,
+            "voucher_template",
+            &template_id,
+            if payload.id.is_some() { "update" } else { "create" },
+            json!({ "name": &payload.name, "rows": payload.rows.len() }),
+        )?;
+        let template = fetch_template(&tx, &template_id)?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(template)
+    }
+
+    /// Creates an unposted voucher from a template for the given total and date.
+    pub fn instantiate_voucher_template(
+        &mut self,
+        payload: InstantiateTemplateInput,
+    ) -> Result<Voucher, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let template = fetch_template(&tx, &payload.template_id)?;
+        let voucher = insert_voucher(
+            &tx,
+            user,
+            &template_voucher_input(
+                &template,
+                &payload.series_id,
+                payload.date,
+                payload.total,
+                payload.description,
+            )?,
+        )?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(voucher)
+    }
+
+    pub fn list_recurring_schedules(
+        &mut self,
+        payload: CompanyIdInput,
+    ) -> Result<Vec<RecurringSchedule>, String> {
+        let conn = &*self.conn;
+        fetch_schedules(conn, &payload.company_id, false)
+    }
+
+    /// Creates or updates a monthly schedule. An edited schedule keeps its next
+    /// run unless `start_date` or `day_of_month` changed; the recalculated run
+    /// is never in a month that has already run.
+    pub fn upsert_recurring_schedule(
+        &mut self,
+        payload: UpsertRecurringScheduleInput,
+    ) -> Result<RecurringSchedule, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        if !(1..=31).contains(&payload.day_of_month) {
+            return Err("Day of month must be between 1 and 31".to_string());
+        }
+        if !payload.total.is_positive() {
+            return Err("Amount must be positive".to_string());
+        }
+        let start_date = payload.start_date;
+        if payload.end_date.is_some_and(|end_date| end_date < start_date) {
+            return Err("End date is before start date".to_string());
+        }
+
+        let tx = conn.transaction().map_err(map_sql_error)?;
+        let template = fetch_template(&tx, &payload.template_id)?;
+        if template.company_id != payload.company_id {
+            return Err("Template does not belong to company".to_string());
+        }
+
+        let stored = match &payload.id {
+            Some(id) => {
+                ensure_company(&tx, "recurring_schedules", id, &payload.company_id, "Schedule")?;
+                fetch_schedule_dates(&tx, id)?
+            }
+            None => None,
+        };
+        let next_run_date = match stored {
+            Some(stored)
+                if stored.start_date == Some(start_date)
+                    && stored.day_of_month == payload.day_of_month =>
+            {
+                stored.next_run_date
+            }
+            stored => {
+                let mut next_run_date = monthly_date(start_date.year(), start_date.month(), payload.day_of_month)?;
+                if next_run_date < start_date {
+                    next_run_date = next_monthly_date(next_run_date, payload.day_of_month)?;
+                }
+                if let Some(last_run) = stored.and_then(|stored| stored.last_run_date) {
+                    while (next_run_date.year(), next_run_date.month()) <= (last_run.year(), last_run.month()) {
+                        next_run_date = next_monthly_date(next_run_date, payload.day_of_month)?;
+                    }
+                }
+                next_run_date
+            }
+        };
+        let now = Utc::now().to_rfc3339();
+        let schedule = RecurringSchedule {
+            id: payload
+                .id
+                .clone()
+                .unwrap_or_else(ScheduleId::generate),
+            company_id: payload.company_id,
+            template_id: payload.template_id,
+            series_id: payload.series_id,
+            total: payload.total,
+            day_of_month: payload.day_of_month,
+            next_run_date,
+            end_date: payload.end_date,
+            is_active: payload.is_active,
+            created_at: now,
+        };
+        tx.execute(
+            "INSERT INTO recurring_schedules (id, company_id, template_id, series_id, total_cents, day_of_month, next_run_date, end_date, is_active, created_at, start_date)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT(id) DO UPDATE SET
+               template_id = excluded.template_id,
+               series_id = excluded.series_id,
+               total_cents = excluded.total_cents,
+               day_of_month = excluded.day_of_month,
+               start_date = excluded.start_date,
+               next_run_date = excluded.next_run_date,
+               end_date = excluded.end_date,
+               is_active = excluded.is_active",
+            params![
+                &schedule.id,
+                &schedule.company_id,
+                &schedule.template_id,
+                &schedule.series_id,
+                schedule.total,
+                schedule.day_of_month,
+                &schedule.next_run_date,
+                &schedule.end_date,
+                if schedule.is_active { 1 } else { 0 },
+                &schedule.created_at,
+                start_date
+            ],
+        )
+        .map_err(map_sql_error)?;
+        insert_audit_entry(
+            &tx,
+            user,
+            &schedule.company_id,
+            "recurring_schedule",
+            &schedule.id,
+            if payload.id.is_some() { "update" } else { "create" },
+            json!({
+                "template_id": &schedule.template_id,
+                "total_cents": schedule.total.cents(),
+                "next_run_date": &schedule.next_run_date
+            }),
+        )?;
+        tx.commit().map_err(map_sql_error)?;
+        Ok(schedule)
+    }
+
+    /// Creates draft vouchers for every schedule occurrence up to `as_of_date`
+    /// (default today). Each occurrence is committed on its own; a failing one,
+    /// e.g. in a locked period, is reported and stops that schedule only.
+    pub fn run_recurring_schedules(
+        &mut self,
+        payload: RunRecurringSchedulesInput,
+    ) -> Result<RecurringRunResult, String> {
+        let user = self.user;
+        let conn = &mut *self.conn;
+        let as_of_date = payload.as_of_date.unwrap_or_else(|| Utc::now().date_naive());
+
+        let mut created = Vec::new();
+        let mut failed = Vec::new();
+        for schedule in fetch_schedules(conn, &payload.company_id, true)? {
+            let mut next_run_date = schedule.next_run_date;
+            while next_run_date <= as_of_date && schedule.end_date.is_none_or(|end| next_run_date <= end) {
+                let date = next_run_date;
+                match run_occurrence(conn, user, &schedule, date) {
+                    Ok((voucher, following)) => {
+                        created.push(voucher);
+                        next_run_date = following;
+                    }
+                    Err(message) => {
+                        failed.push(RecurringRunFailure {
+                            schedule_id: schedule.id.clone(),
+                            date,
+                            message,
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(RecurringRunResult { created, failed })
+    }
+}
+
+fn run_occurrence(
+    conn: &mut Connection,
+    user: &str,
+    schedule: &RecurringSchedule,
+    date: NaiveDate,
+) -> Result<(Voucher, NaiveDate), String> {
+    let tx = conn.transaction().map_err(map_sql_error)?;
+    let template = fetch_template(&tx, &schedule.template_id)?;
+    let voucher = insert_voucher(
+        &tx,
+        user,
+        &template_voucher_input(&template, &schedule.series_id, date, schedule.total, None)?,
+    )?;
+    let following = next_monthly_date(date, schedule.day_of_month)?;
+    let still_active = schedule.end_date.is_none_or(|end| following <= end);
+    tx.execute(
+        "UPDATE recurring_schedules SET next_run_date = ?1, is_active = ?2, last_run_date = ?3 WHERE id = ?4",
+        params![following, if still_active { 1 } else { 0 }, date, &schedule.id],
+    )
+    .map_err(map_sql_error)?;
+    insert_audit_entry(
+        &tx,
+        user,
+        &schedule.company_id,
+        "recurring_schedule",
+        &schedule.id,
+        "run",
+        json!({ "date": date, "voucher_id": &voucher.id }),
+    )?;
+    tx.commit().map_err(map_sql_error)?;
+    Ok((voucher, following))
+}
+
+/// Expands a template into voucher rows for `total`. Rounding
+/// differences on percentage rows go to the largest percentage row per side.
+fn template_voucher_input(
+    template: &VoucherTemplate,
+    series_id: &SeriesId,
+    date: NaiveDate,
+    total: Money,
+    description: Option<String>,
+) -> Result<CreateVoucherInput, String> {
+    if !template.is_active {
+        return Err("Template is inactive".to_string());
+    }
+    if !total.is_positive() {
+        return Err("Amount must be positive".to_string());
+    }
+
+    let mut amounts: Vec<Money> = Vec::with_capacity(template.rows.len());
+    for row in &template.rows {
+        let amount = match (row.percent_basis_points, row.fixed_amount) {
+            (Some(percent), _) => rounded_share(total, percent)?,
+            (None, Some(fixed)) => fixed,
+            (None, None) => return Err("Template row has no amount".to_string()),
+        };
+        amounts.push(amount);
+    }
+    for side in ["debit", "credit"] {
+        let percent_rows: Vec<usize> = template
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.side == side && row.percent_basis_points.is_some())
+            .map(|(index, _)| index)
+            .collect();
+        let Some(&largest) = percent_rows
+            .iter()
+            .max_by_key(|&&index| template.rows[index].percent_basis_points)
+        else {
+            continue;
+        };
+        let side_percent: i64 = percent_rows
+            .iter()
+            .filter_map(|&index| template.rows[index].percent_basis_points)
+            .sum();
+        let side_amount = Money::checked_sum(percent_rows.iter().map(|&index| amounts[index]))?;
+        amounts[largest] =
+            amounts[largest].checked_post(rounded_share(total, side_percent)?, side_amount)?;
+    }
+
+    // A small total can round a percentage row down to nothing.
+    let rows = template
+        .rows
+        .iter()
+        .zip(amounts)
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(row, amount)| CreateVoucherRowInput {
+            account_id: row.account_id.clone(),
+            description: row.description.clone(),
+            debit: if row.side == "debit" { amount } else { Money::ZERO },
+            credit: if row.side == "credit" { amount } else { Money::ZERO },
+            vat_code: row.vat_code.clone(),
+        })
+        .collect();
+    Ok(CreateVoucherInput {
+        company_id: template.company_id.clone(),
+        series_id: series_id.clone(),
+        date,
+        description: description.unwrap_or_else(|| template.description.clone()),
+        counterparty: None,
+        counterparty_id: None,
+        rows,
+        attachments: None,
+    })
+}
+
+fn rounded_share(total: Money, basis_points: i64) -> Result<Money, String> {
+    total
+        .checked_mul_ratio(basis_points, FULL_PERCENT_BASIS_POINTS)
+        .ok_or_else(|| "Amount is too large".to_string())
+}
+
+fn fetch_template(conn: &Connection, template_id: &str) -> Result<VoucherTemplate, String> {
+    let mut template = conn
+        .query_row(
+            "SELECT id, company_id, name, description, is_active, created_at
+             FROM voucher_templates WHERE id = ?1",
+            [template_id],
+            |row| {
+                Ok(VoucherTemplate {
+                    id: row.get(0)?,
+                    company_id: row.get(1)?,
+                    name: row.get(2)?,
+                    description: row.get(3)?,
+                    is_active: row.get::<_, i64>(4)? == 1,
+                    created_at: row.get(5)?,
+                    rows: Vec::new(),
+                })
+            },
+        )
+        .map_err(map_sql_error)?;
+    template.rows = fetch_template_rows(conn, template_id)?;
+    Ok(template)
+}
+
+fn fetch_template_rows(conn: &Connection, template_id: &str) -> Result<Vec<VoucherTemplateRow>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, template_id, account_id, description, side, percent_basis_points, fixed_cents, vat_code
+             FROM voucher_template_rows WHERE template_id = ?1 ORDER BY sort_order",
+        )
+        .map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([template_id], |row| {
+            Ok(VoucherTemplateRow {
+                id: row.get(0)?,
+                template_id: row.get(1)?,
+                account_id: row.get(2)?,
+                description: row.get(3)?,
+                side: row.get(4)?,
+                percent_basis_points: row.get(5)?,
+                fixed_amount: row.get(6)?,
+                vat_code: row.get(7)?,
+            })
+        })
+        .map_err(map_sql_error)?;
+    let mut list = Vec::new();
+    for row in rows {
+        list.push(row.map_err(map_sql_error)?);
+    }
+    Ok(list)
+}
+
+/// What an edit of a schedule needs to know about its stored run dates.
+struct ScheduleDates {
+    start_date: Option<NaiveDate>,
+    day_of_month: i64,
+    next_run_date: NaiveDate,
+    last_run_date: Option<NaiveDate>,
+}
+
+fn fetch_schedule_dates(conn: &Connection, schedule_id: &str) -> Result<Option<ScheduleDates>, String> {
+    conn.query_row(
+        "SELECT start_date, day_of_month, next_run_date, last_run_date FROM recurring_schedules WHERE id = ?1",
+        [schedule_id],
+        |row| {
+            Ok(ScheduleDates {
+                start_date: row.get(0)?,
+                day_of_month: row.get(1)?,
+                next_run_date: row.get(2)?,
+                last_run_date: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(map_sql_error)
+}
+
+fn fetch_schedules(
+    conn: &Connection,
+    company_id: &str,
+    active_only: bool,
+) -> Result<Vec<RecurringSchedule>, String> {
+    let mut query = String::from(
+        "SELECT id, company_id, template_id, series_id, total_cents, day_of_month, next_run_date, end_date, is_active, created_at
+         FROM recurring_schedules WHERE company_id = ?1",
+    );
+    if active_only {
+        query.push_str(" AND is_active = 1");
+    }
+    query.push_str(" ORDER BY next_run_date ASC");
+
+    let mut stmt = conn.prepare(&query).map_err(map_sql_error)?;
+    let rows = stmt
+        .query_map([company_id], |row| {
+            Ok(RecurringSchedule {
+                id: row.get(0)?,
+                company_id: row.get(1)?,
+                template_id: row.get(2)?,
+                series_id: row.get(3)?,
+                total: row.get(4)?,
+                day_of_month: row.get(5)?,
+                next_run_date: row.get(6)?,
+                end_date: row.get(7)?,
+                is_active: row.get::<_, i64>(8)? == 1,
+                created_at: row.get(9)?,
+            })
+        })
+        .map_err(map_sql_error)?;
+    let mut schedules = Vec::new();
+    for row in rows {
+        schedules.push(row.map_err(map_sql_error)?);
+    }
+    Ok(schedules)
+}
+
+/// The given day in a month, clamped to the month's last day (31 -> 28 Feb).
+fn monthly_date(year: i32, month: u32, day_of_month: i64) -> Result<NaiveDate, String> {
+    (1..=day_of_month as u32)
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .ok_or_else(|| format!("Invalid date: {}-{}-{}", year, month, day_of_month))
+}
+
+fn next_monthly_date(date: NaiveDate, day_of_month: i64) -> Result<NaiveDate, String> {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    monthly_date(year, month, day_of_month)
+}
@@ -0,0 +1,331 @@
+use argon2::Argon2;
+use chrono::Utc;
+use password_hash::rand_core::OsRng;
+use password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::db::Role;
+use crate::ids::{PermissionDenialId, UserId};
+use crate::models::{
+    ChangePasswordInput, CreateUserInput, PermissionDenial, SetUserRoleInput, User,
+};
+use crate::{map_sql_error, Books};
+
+const USER_COLUMNS: &str = "id, username, display_name, role, is_active, created_at, created_by";
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// A logged-in user. Each login is recorded in `user_sessions`.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub id: String,
+    pub user_id: UserId,
+    pub username: String,
+    pub role: Role,
+    pub logged_in_at: String,
+}
+
+impl Books<'_> {
+    pub fn list_users(&mut self) -> Result<Vec<User>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM users ORDER BY username COLLATE NOCASE",
+                USER_COLUMNS
+            ))
+            .map_err(map_sql_error)?;
+        let rows = stmt.query_map([], user_from_row).map_err(map_sql_error)?;
+        let mut users = Vec::new();
+        for row in rows {
+            users.push(row.map_err(map_sql_error)?);
+        }
+        Ok(users)
+    }
+
+    /// Creates a user with the requested role, viewer by default.
+    pub fn create_user(&mut self, payload: CreateUserInput) -> Result<User, String> {
+        let role = match &payload.role {
+            Some(role) => Role::parse(role)?,
+            None => Role::Viewer,
+        };
+        insert_user(self.conn, self.user, role, &payload)
+    }
+
+    /// Changes a user's role. Takes effect at the user's next login; the last
+    /// active admin cannot be demoted.
+    pub fn set_user_role(&mut self, payload: SetUserRoleInput) -> Result<User, String> {
+        let role = Role::parse(&payload.role)?;
+        let conn = &*self.conn;
+        let user = fetch_user(conn, &payload.user_id)?;
+        if user.role == Role::Admin.as_str() && role != Role::Admin {
+            let admins: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM users WHERE role = 'admin' AND is_active = 1",
+                    [],
+                    |row| row.get(0),
+                )
+                .map_err(map_sql_error)?;
+            if admins <= 1 {
+                return Err("Cannot remove the last admin".to_string());
+            }
+        }
+        conn.execute(
+            "UPDATE users SET role = ?1 WHERE id = ?2",
+            params![role.as_str(), &user.id],
+        )
+        .map_err(map_sql_error)?;
+        fetch_user(conn, &user.id)
+    }
+
+    pub fn list_permission_denials(&mut self) -> Result<Vec<PermissionDenial>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, username, role, command, required_role, created_at
+                 FROM permission_denials ORDER BY created_at DESC",
+            )
+            .map_err(map_sql_error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(PermissionDenial {
+                    id: row.get(0)?,
+                    username: row.get(1)?,
+                    role: row.get(2)?,
+                    command: row.get(3)?,
+                    required_role: row.get(4)?,
+                    created_at: row.get(5)?,
+                })
+            })
+            .map_err(map_sql_error)?;
+        let mut denials = Vec::new();
+        for row in rows {
+            denials.push(row.map_err(map_sql_error)?);
+        }
+        Ok(denials)
+    }
+}
+
+pub fn user_count(conn: &Connection) -> Result<i64, String> {
+    conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+        .map_err(map_sql_error)
+}
+
+/// Creates the very first user, who becomes admin. Fails once any user
+/// exists; after that only admins add users through [`Books::create_user`].
+pub fn create_first_admin(conn: &Connection, payload: &CreateUserInput) -> Result<User, String> {
+    if user_count(conn)? > 0 {
+        return Err("Users already exist; log in as an admin to add users".to_string());
+    }
+    insert_user(conn, payload.username.trim(), Role::Admin, payload)
+}
+
+/// Checks the username and password of an active user.
+pub fn authenticate(conn: &Connection, username: &str, password: &str) -> Result<User, String> {
+    let credentials: Option<(String, String, bool)> = conn
+        .query_row(
+            "SELECT id, password_hash, is_active FROM users WHERE username = ?1",
+            [username.trim()],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? == 1)),
+        )
+        .optional()
+        .map_err(map_sql_error)?;
+    match credentials {
+        Some((user_id, password_hash, true)) if verify_password(password, &password_hash) => {
+            fetch_user(conn, &user_id)
+        }
+        _ => Err("Invalid username or password".to_string()),
+    }
+}
+
+pub fn start_session(conn: &Connection, user: &User) -> Result<Session, String> {
+    let session = Session {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: user.id.clone(),
+        username: user.username.clone(),
+        role: Role::parse(&user.role)?,
+        logged_in_at: Utc::now().to_rfc3339(),
+    };
+    conn.execute(
+        "INSERT INTO user_sessions (id, user_id, logged_in_at) VALUES (?1, ?2, ?3)",
+        params![&session.id, &session.user_id, &session.logged_in_at],
+    )
+    .map_err(map_sql_error)?;
+    Ok(session)
+}
+
+pub fn change_password(
+    conn: &Connection,
+    user_id: &str,
+    payload: &ChangePasswordInput,
+) -> Result<(), String> {
+    let password_hash: String = conn
+        .query_row(
+            "SELECT password_hash FROM users WHERE id = ?1",
+            [user_id],
+            |row| row.get(0),
+        )
+        .map_err(map_sql_error)?;
+    if !verify_password(&payload.current_password, &password_hash) {
+        return Err("Current password is incorrect".to_string());
+    }
+    validate_password(&payload.new_password)?;
+    conn.execute(
+        "UPDATE users SET password_hash = ?1 WHERE id = ?2",
+        params![hash_password(&payload.new_password)?, user_id],
+    )
+    .map_err(map_sql_error)?;
+    Ok(())
+}
+
+/// Checks that `user` has at least the `required` role, for callers without
+/// a login session such as the command line. `user` is the result of
+/// [`authenticate`] or [`crate::api_tokens::token_user`], so the role is
+/// never taken from an unverified username. Denials are logged like those of
+/// logged-in users.
+pub fn authorize_user(
+    conn: &Connection,
+    user: &User,
+    required: Role,
+    command: &str,
+) -> Result<(), String> {
+    if !user.is_active {
+        return Err(format!("Unknown or inactive user: {}", user.username));
+    }
+    let role = Role::parse(&user.role)?;
+    if role >= required {
+        return Ok(());
+    }
+    record_permission_denial(conn, &user.username, role, command, required)?;
+    Err(format!(
+        "Permission denied: {} requires the {} role",
+        command,
+        required.as_str()
+    ))
+}
+
+/// Logs a command refused because `username` lacked the `required` role.
+pub fn record_permission_denial(
+    conn: &Connection,
+    username: &str,
+    role: Role,
+    command: &str,
+    required: Role,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO permission_denials (id, username, role, command, required_role, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            PermissionDenialId::generate(),
+            username,
+            role.as_str(),
+            command,
+            required.as_str(),
+            Utc::now().to_rfc3339()
+        ],
+    )
+    .map_err(map_sql_error)?;
+    Ok(())
+}
+
+fn insert_user(
+    conn: &Connection,
+    created_by: &str,
+    role: Role,
+    payload: &CreateUserInput,
+) -> Result<User, String> {
+    let username = payload.username.trim().to_string();
+    if username.is_empty() || username.chars().any(char::is_whitespace) {
+        return Err("Username must be non-empty and without spaces".to_string());
+    }
+    if payload.display_name.trim().is_empty() {
+        return Err("Display name is required".to_string());
+    }
+    validate_password(&payload.password)?;
+    let taken: Option<i64> = conn
+        .query_row(
+            "SELECT 1 FROM users WHERE username = ?1",
+            [&username],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(map_sql_error)?;
+    if taken.is_some() {
+        return Err(format!("Username {} is already taken", username));
+    }
+
+    let id = UserId::generate();
+    conn.execute(
+        "INSERT INTO users (id, username, display_name, password_hash, role, is_active, created_at, created_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            id,
+            &username,
+            payload.display_name.trim(),
+            hash_password(&payload.password)?,
+            role.as_str(),
+            1,
+            Utc::now().to_rfc3339(),
+            created_by
+        ],
+    )
+    .map_err(map_sql_error)?;
+    fetch_user(conn, &id)
+}
+
+pub fn end_session(conn: &Connection, session: &Session) -> Result<(), String> {
+    conn.execute(
+        "UPDATE user_sessions SET logged_out_at = ?1 WHERE id = ?2",
+        params![Utc::now().to_rfc3339(), &session.id],
+    )
+    .map_err(map_sql_error)?;
+    Ok(())
+}
+
+fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+/// Argon2id hash in PHC string format, with a random salt.
+fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| err.to_string())
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+pub fn fetch_user(conn: &Connection, user_id: &str) -> Result<User, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM users WHERE id = ?1", USER_COLUMNS),
+        [user_id],
+        user_from_row,
+    )
+    .map_err(map_sql_error)
+}
+
+fn user_from_row(row: &Row) -> rusqlite::Result<User> {
+    Ok(User {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        display_name: row.get(2)?,
+        role: row.get(3)?,
+        is_active: row.get::<_, i64>(4)? == 1,
+        created_at: row.get(5)?,
+        created_by: row.get(6)?,
+    })
+}
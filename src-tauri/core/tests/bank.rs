@@ -0,0 +1,217 @@
+//! Bank statement import, bank rules and reconciliation.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use bokforing_core::models::{
+    CompanyIdInput, ImportBankStatementInput, MatchBankTransactionInput,
+    SignOffReconciliationInput, UpsertBankRuleInput,
+};
+use bokforing_core::money::Money;
+use bokforing_core::Books;
+use common::{date, open_books, other_company, Demo, USER};
+
+#[test]
+fn csv_dates_with_two_and_four_digit_years() {
+    let mut conn = open_books();
+    let dir = tempfile::tempdir().unwrap();
+    let mut books = Books::new(&mut conn, USER, dir.path());
+    let demo = Demo::load(&mut books);
+    let file = dir.path().join("statement.csv");
+    fs::write(
+        &file,
+        "Bokföringsdag;Belopp;Beskrivning\n\
+         24-01-05;-100,00;Two digits\n\
+         2024-01-06;-200,00;Dashes\n\
+         20240107;-300,00;Compact\n\
+         2024/01/08;-400,00;Slashes\n",
+    )
+    .unwrap();
+
+    let result = books
+        .import_bank_statement(ImportBankStatementInput {
+            company_id: demo.company_id.clone(),
+            file_path: file.to_string_lossy().into_owned(),
+            format: None,
+        })
+        .unwrap();
+
+    let dates: Vec<_> = result
+        .imported
+        .iter()
+        .map(|transaction| transaction.booking_date)
+        .collect();
+    assert_eq!(
+        dates,
+        [
+            date("2024-01-05"),
+            date("2024-01-06"),
+            date("2024-01-07"),
+            date("2024-01-08"),
+        ]
+    );
+}
+
+#[test]
+fn csv_row_with_an_invalid_date_fails_the_import() {
+    let mut conn = open_books();
+    let dir = tempfile::tempdir().unwrap();
+    let mut books = Books::new(&mut conn, USER, dir.path());
+    let demo = Demo::load(&mut books);
+    let file = dir.path().join("statement.csv");
+    fs::write(
+        &file,
+        "Bokföringsdag;Belopp;Beskrivning\n\
+         2024-01-05;-100,00;Fee\n\
+         2024-13-45;-200,00;Bad date\n\
+         Summa;-300,00;\n",
+    )
+    .unwrap();
+
+    let result = books.import_bank_statement(ImportBankStatementInput {
+        company_id: demo.company_id.clone(),
+        file_path: file.to_string_lossy().into_owned(),
+        format: None,
+    });
+    assert_eq!(result.unwrap_err(), "Row 3: invalid date '2024-13-45'");
+}
+
+#[test]
+fn bank_rule_cannot_move_to_another_company() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let other = other_company(&mut books);
+    let rule = |id, company_id| UpsertBankRuleInput {
+        id,
+        company_id,
+        name: "Bank fees".to_string(),
+        match_text: "AVGIFT".to_string(),
+        counter_account_id: demo.account(1930),
+        vat_account_id: None,
+        vat_rate_percent: None,
+        is_active: true,
+    };
+    let created = books
+        .upsert_bank_rule(rule(None, demo.company_id.clone()))
+        .unwrap();
+
+    let result = books.upsert_bank_rule(rule(Some(created.id), other));
+    assert_eq!(result.unwrap_err(), "Rule does not belong to company");
+}
+
+#[test]
+fn voucher_can_be_matched_to_one_bank_transaction_only() {
+    let mut conn = open_books();
+    let dir = tempfile::tempdir().unwrap();
+    let mut books = Books::new(&mut conn, USER, dir.path());
+    let demo = Demo::load(&mut books);
+    let file = dir.path().join("statement.csv");
+    fs::write(
+        &file,
+        "Bokföringsdag;Belopp;Beskrivning\n\
+         2024-01-05;100,00;Payment 1\n\
+         2024-01-05;100,00;Payment 2\n",
+    )
+    .unwrap();
+    let imported = books
+        .import_bank_statement(ImportBankStatementInput {
+            company_id: demo.company_id.clone(),
+            file_path: file.to_string_lossy().into_owned(),
+            format: None,
+        })
+        .unwrap()
+        .imported;
+    let voucher = books
+        .create_voucher(demo.voucher("2024-01-05", "Sale", &[(1930, 10000, 0), (3010, 0, 10000)]))
+        .unwrap();
+
+    books
+        .match_bank_transaction(MatchBankTransactionInput {
+            bank_transaction_id: imported[0].id.clone(),
+            voucher_id: voucher.id.clone(),
+        })
+        .unwrap();
+    let result = books.match_bank_transaction(MatchBankTransactionInput {
+        bank_transaction_id: imported[1].id.clone(),
+        voucher_id: voucher.id,
+    });
+    assert_eq!(
+        result.unwrap_err(),
+        "Voucher is already matched to another bank transaction"
+    );
+}
+
+#[test]
+fn reconciliation_with_a_difference_needs_a_note() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    books
+        .create_voucher(demo.voucher(
+            "2024-03-10",
+            "Sale",
+            &[(1930, 100000, 0), (3010, 0, 100000)],
+        ))
+        .unwrap();
+    let sign_off = |period: &str, balance: i64, note: Option<&str>| SignOffReconciliationInput {
+        company_id: demo.company_id.clone(),
+        period: period.to_string(),
+        statement_balance: Money::from_cents(balance),
+        note: note.map(str::to_string),
+    };
+
+    for note in [None, Some("  ")] {
+        let result = books.sign_off_bank_reconciliation(sign_off("2024-03", 90000, note));
+        let error = result.unwrap_err();
+        assert!(error.contains("add a note"), "{}", error);
+    }
+    let signed = books
+        .sign_off_bank_reconciliation(sign_off("2024-03", 90000, Some("Card fee not booked")))
+        .unwrap();
+    assert_eq!(signed.statement_date, date("2024-03-31"));
+    assert_eq!(signed.difference, Money::from_cents(-10000));
+    assert_eq!(signed.note.as_deref(), Some("Card fee not booked"));
+    // A balance that matches needs no note.
+    books
+        .sign_off_bank_reconciliation(sign_off("2024-04", 100000, None))
+        .unwrap();
+}
+
+#[test]
+fn month_is_signed_off_once() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let sign_off = |period: &str| SignOffReconciliationInput {
+        company_id: demo.company_id.clone(),
+        period: period.to_string(),
+        statement_balance: Money::from_cents(0),
+        note: None,
+    };
+
+    books
+        .sign_off_bank_reconciliation(sign_off("2024-03"))
+        .unwrap();
+    // The period is normalized, so "2024-3" is the same month.
+    for period in ["2024-03", "2024-3"] {
+        let result = books.sign_off_bank_reconciliation(sign_off(period));
+        assert_eq!(
+            result.unwrap_err(),
+            "Reconciliation for 2024-03 is already signed off"
+        );
+    }
+    let result = books.sign_off_bank_reconciliation(sign_off("2024-13"));
+    assert_eq!(
+        result.unwrap_err(),
+        "Invalid period: 2024-13 (expected YYYY-MM)"
+    );
+    let reconciliations = books
+        .list_bank_reconciliations(CompanyIdInput {
+            company_id: demo.company_id.clone(),
+        })
+        .unwrap();
+    assert_eq!(reconciliations.len(), 1);
+}
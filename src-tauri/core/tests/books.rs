@@ -0,0 +1,1101 @@
+//! The legal guarantees of the books: balanced vouchers, gapless numbering,
+//! period locks, corrections and the exported files.
+
+mod common;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use bokforing_core::db;
+use bokforing_core::ids::{AccountId, CompanyId};
+use bokforing_core::models::{
+    ApproveVoucherInput, CompanyIdInput, CreateAttachmentInput, CreateCorrectionInput,
+    CreateVoucherInput, ExportArchiveInput, ExportInput, FieldError, ListVouchersInput,
+    LockPeriodInput, RejectVoucherInput, ReportLedgerInput, RunRecurringSchedulesInput,
+    SetApprovalRequirementInput, SetZeroRowAllowanceInput, UpsertAccountInput,
+    UpsertCounterpartyInput, UpsertRecurringScheduleInput, UpsertVoucherTemplateInput, Voucher,
+    VoucherIdInput, VoucherTemplateRowInput,
+};
+use bokforing_core::money::Money;
+use bokforing_core::{Books, MAX_DESCRIPTION_CHARS};
+use chrono::NaiveDate;
+use common::{assert_golden, date, open_books, other_company, Demo, USER};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+#[test]
+fn create_voucher_rejects_unbalanced_rows() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+
+    let result = books.create_voucher(demo.voucher(
+        "2024-03-01",
+        "Sale",
+        &[(1930, 12500, 0), (3010, 0, 12000)],
+    ));
+    assert_eq!(result.unwrap_err(), "Voucher does not balance");
+    let result = books.create_voucher(demo.voucher("2024-03-01", "Empty", &[]));
+    assert_eq!(result.unwrap_err(), "Voucher must have rows");
+
+    let vouchers = books
+        .list_vouchers(ListVouchersInput {
+            company_id: demo.company_id.clone(),
+            from_date: None,
+            to_date: None,
+            limit: None,
+            after: None,
+        })
+        .unwrap();
+    assert!(vouchers.is_empty());
+}
+
+#[test]
+fn create_voucher_reports_every_invalid_field() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+
+    let input = demo.voucher(
+        "2024-02-29",
+        &"x".repeat(MAX_DESCRIPTION_CHARS + 1),
+        &[(1930, -100, 0), (3010, 100, 100), (2611, 0, 0)],
+    );
+    let result = books.create_voucher(input);
+    let field_errors = |error: String| -> Vec<(String, String)> {
+        serde_json::from_str::<Vec<FieldError>>(&error)
+            .unwrap()
+            .into_iter()
+            .map(|error| (error.field, error.message))
+            .collect()
+    };
+    assert_eq!(
+        field_errors(result.unwrap_err()),
+        [
+            ("description", "Description is longer than 200 characters"),
+            ("rows[0].debit", "Amount cannot be negative"),
+            (
+                "rows[1]",
+                "Row cannot have both a debit and a credit amount"
+            ),
+            ("rows[2]", "Row must have a debit or a credit amount"),
+        ]
+        .map(|(field, message)| (field.to_string(), message.to_string()))
+    );
+    let result =
+        books.create_voucher(demo.voucher("2024-03-01", " ", &[(1930, 100, 0), (3010, 0, 100)]));
+    assert_eq!(
+        field_errors(result.unwrap_err()),
+        [(
+            "description".to_string(),
+            "Description is required".to_string()
+        )]
+    );
+}
+
+#[test]
+fn voucher_descriptions_are_stored_trimmed() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let mut input = demo.voucher("2024-03-01", "  Sale ", &[(1930, 100, 0), (3010, 0, 100)]);
+    input.rows[0].description = Some(" Cash ".to_string());
+    input.rows[1].description = Some("  ".to_string());
+
+    let voucher = books.create_voucher(input).unwrap();
+    assert_eq!(voucher.description, "Sale");
+    let rows = voucher.rows.unwrap();
+    assert_eq!(rows[0].description.as_deref(), Some("Cash"));
+    assert_eq!(rows[1].description, None);
+}
+
+#[test]
+fn zero_rows_are_allowed_only_when_enabled() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let rows = [(1930, 100, 0), (3010, 0, 100), (2611, 0, 0)];
+
+    books
+        .create_voucher(demo.voucher("2024-03-01", "Memo row", &rows))
+        .unwrap_err();
+    let company = books
+        .set_zero_row_allowance(SetZeroRowAllowanceInput {
+            company_id: demo.company_id.clone(),
+            allow_zero_rows: true,
+        })
+        .unwrap();
+    assert!(company.allow_zero_rows);
+    let voucher = books
+        .create_voucher(demo.voucher("2024-03-01", "Memo row", &rows))
+        .unwrap();
+    assert_eq!(voucher.rows.unwrap().len(), 3);
+}
+
+#[test]
+fn voucher_input_rejects_invalid_dates_and_ids() {
+    let input = |date: &str, account_id: &str| {
+        serde_json::from_value::<CreateVoucherInput>(json!({
+            "companyId": "6f1c2a9e-0d4b-4c5e-9a7f-3b2d1e0c9f8a",
+            "seriesId": "0b8e7d6c-5a4f-4e3d-8c2b-1a0f9e8d7c6b",
+            "date": date,
+            "description": "Sale",
+            "rows": [{ "accountId": account_id, "debit": "1.00", "credit": "0" }],
+        }))
+    };
+    let account_id = "A1B2C3D4-E5F6-4A7B-8C9D-0E1F2A3B4C5D";
+
+    let parsed = input("2024-3-1", account_id).unwrap();
+    assert_eq!(parsed.date, date("2024-03-01"));
+    assert_eq!(
+        parsed.rows[0].account_id.as_str(),
+        "a1b2c3d4-e5f6-4a7b-8c9d-0e1f2a3b4c5d"
+    );
+    for bad_date in ["2024-13-45", "2024-02-30", "1/3/2024", ""] {
+        assert!(input(bad_date, account_id).is_err(), "{:?}", bad_date);
+    }
+    for bad_id in ["", " ", "1930", "not-a-uuid"] {
+        let err = input("2024-03-01", bad_id).unwrap_err().to_string();
+        assert!(err.contains("Invalid id"), "{:?}: {}", bad_id, err);
+    }
+}
+
+#[test]
+fn period_locks_compare_dates_not_text() {
+    let mut conn = open_books();
+    // A lock stored without zero padding sorts "2024-9-1" after "2024-09-15".
+    conn.execute(
+        "INSERT INTO period_locks (id, company_id, period_start, period_end, locked_at, locked_by)
+         SELECT 'legacy', id, '2024-9-1', '2024-9-30', '2024-10-01T00:00:00Z', 'tester' FROM companies",
+        [],
+    )
+    .unwrap();
+    // Written by an older version; the migration pads it to YYYY-MM-DD.
+    conn.pragma_update(None, "user_version", 4).unwrap();
+    db::migrate(&conn).unwrap();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+
+    let result =
+        books.create_voucher(demo.voucher("2024-09-15", "Late", &[(1930, 100, 0), (3010, 0, 100)]));
+    assert_eq!(result.unwrap_err(), "Period is locked");
+    books
+        .create_voucher(demo.voucher("2024-10-01", "Open", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+}
+
+#[test]
+fn vouchers_are_numbered_without_gaps() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+
+    let first = books
+        .create_voucher(demo.voucher("2024-03-01", "First", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+    // A rejected voucher must not use up a number.
+    books
+        .create_voucher(demo.voucher("2024-03-02", "Unbalanced", &[(1930, 100, 0)]))
+        .unwrap_err();
+    let second = books
+        .create_voucher(demo.voucher("2024-02-01", "Second", &[(1930, 200, 0), (3010, 0, 200)]))
+        .unwrap();
+    assert_eq!((first.voucher_number, second.voucher_number), (1, 2));
+
+    let series = books
+        .list_voucher_series(CompanyIdInput {
+            company_id: demo.company_id.clone(),
+        })
+        .unwrap();
+    assert_eq!(series[0].next_number, 3);
+}
+
+#[test]
+fn locked_period_rejects_new_vouchers() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    books
+        .lock_period(LockPeriodInput {
+            company_id: demo.company_id.clone(),
+            period_start: date("2024-01-01"),
+            period_end: date("2024-03-31"),
+        })
+        .unwrap();
+
+    for date in ["2024-01-01", "2024-02-15", "2024-03-31"] {
+        let result =
+            books.create_voucher(demo.voucher(date, "Late", &[(1930, 100, 0), (3010, 0, 100)]));
+        assert_eq!(result.unwrap_err(), "Period is locked", "{}", date);
+    }
+    let voucher = books
+        .create_voucher(demo.voucher("2024-04-01", "Open", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+    assert_eq!(voucher.voucher_number, 1);
+}
+
+#[test]
+fn locked_period_rejects_posting_and_corrections() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let posted = books
+        .create_voucher(demo.voucher("2024-03-01", "Posted", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+    books
+        .post_voucher(VoucherIdInput {
+            voucher_id: posted.id.clone(),
+        })
+        .unwrap();
+    let draft = books
+        .create_voucher(demo.voucher("2024-03-02", "Draft", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+    books
+        .lock_period(LockPeriodInput {
+            company_id: demo.company_id.clone(),
+            period_start: date("2024-03-01"),
+            period_end: date("2024-03-31"),
+        })
+        .unwrap();
+
+    let result = books.post_voucher(VoucherIdInput {
+        voucher_id: draft.id.clone(),
+    });
+    assert_eq!(result.unwrap_err(), "Period is locked");
+    let result = books.create_correction_voucher(CreateCorrectionInput {
+        original_voucher_id: posted.id.clone(),
+        date: date("2024-03-15"),
+        description: "Wrong amount".to_string(),
+    });
+    assert_eq!(result.unwrap_err(), "Period is locked");
+
+    // The correction goes into the next open period instead.
+    let correction = books
+        .create_correction_voucher(CreateCorrectionInput {
+            original_voucher_id: posted.id,
+            date: date("2024-04-01"),
+            description: "Wrong amount".to_string(),
+        })
+        .unwrap();
+    assert_eq!(correction.date, date("2024-04-01"));
+}
+
+#[test]
+fn posted_voucher_cannot_be_posted_again() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let voucher = books
+        .create_voucher(demo.voucher("2024-03-01", "Sale", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+
+    let posted = books
+        .post_voucher(VoucherIdInput {
+            voucher_id: voucher.id.clone(),
+        })
+        .unwrap();
+    assert!(posted.posted_at.is_some());
+    let result = books.post_voucher(VoucherIdInput {
+        voucher_id: voucher.id,
+    });
+    assert_eq!(result.unwrap_err(), "Voucher is already posted");
+}
+
+#[test]
+fn voucher_must_be_approved_by_another_user_before_posting() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    books
+        .set_approval_requirement(SetApprovalRequirementInput {
+            company_id: demo.company_id.clone(),
+            requires_approval: true,
+        })
+        .unwrap();
+    let voucher = books
+        .create_voucher(demo.voucher("2024-03-01", "Sale", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+
+    let result = books.post_voucher(VoucherIdInput {
+        voucher_id: voucher.id.clone(),
+    });
+    assert_eq!(
+        result.unwrap_err(),
+        "Voucher must be approved before posting"
+    );
+    let result = books.approve_voucher(ApproveVoucherInput {
+        voucher_id: voucher.id.clone(),
+        comment: None,
+    });
+    assert_eq!(
+        result.unwrap_err(),
+        "A voucher must be approved by someone other than its creator"
+    );
+
+    let mut approver = Books::new(&mut conn, "approver", Path::new("documents"));
+    approver
+        .approve_voucher(ApproveVoucherInput {
+            voucher_id: voucher.id.clone(),
+            comment: None,
+        })
+        .unwrap();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let posted = books
+        .post_voucher(VoucherIdInput {
+            voucher_id: voucher.id,
+        })
+        .unwrap();
+    assert!(posted.posted_at.is_some());
+}
+
+#[test]
+fn rejected_voucher_cannot_be_posted_and_leaves_the_queue() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let mut created = Vec::new();
+    for description in ["Rejected", "Approved", "Waiting"] {
+        let voucher = books
+            .create_voucher(demo.voucher(
+                "2024-03-01",
+                description,
+                &[(1930, 100, 0), (3010, 0, 100)],
+            ))
+            .unwrap();
+        created.push(voucher.id);
+    }
+
+    let mut approver = Books::new(&mut conn, "approver", Path::new("documents"));
+    let result = approver.reject_voucher(RejectVoucherInput {
+        voucher_id: created[0].clone(),
+        comment: " ".to_string(),
+    });
+    assert_eq!(
+        result.unwrap_err(),
+        "A comment is required when rejecting a voucher"
+    );
+    approver
+        .reject_voucher(RejectVoucherInput {
+            voucher_id: created[0].clone(),
+            comment: "Wrong account".to_string(),
+        })
+        .unwrap();
+    approver
+        .approve_voucher(ApproveVoucherInput {
+            voucher_id: created[1].clone(),
+            comment: None,
+        })
+        .unwrap();
+    let result = approver.approve_voucher(ApproveVoucherInput {
+        voucher_id: created[0].clone(),
+        comment: None,
+    });
+    assert_eq!(result.unwrap_err(), "Voucher has already been rejected");
+
+    let pending = approver
+        .list_pending_approvals(CompanyIdInput {
+            company_id: demo.company_id.clone(),
+        })
+        .unwrap();
+    let pending: Vec<_> = pending
+        .into_iter()
+        .map(|voucher| voucher.voucher_id)
+        .collect();
+    assert_eq!(pending, [created[2].clone()]);
+
+    // Rejection blocks posting even when approval is not required.
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let result = books.post_voucher(VoucherIdInput {
+        voucher_id: created[0].clone(),
+    });
+    assert_eq!(result.unwrap_err(), "Voucher has been rejected");
+}
+
+#[test]
+fn correction_reverses_every_row() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let original = books
+        .create_voucher(demo.voucher(
+            "2024-03-01",
+            "Purchase",
+            &[(4010, 80000, 0), (2641, 20000, 0), (1930, 0, 100000)],
+        ))
+        .unwrap();
+
+    let correction = books
+        .create_correction_voucher(CreateCorrectionInput {
+            original_voucher_id: original.id.clone(),
+            date: date("2024-03-05"),
+            description: "Duplicate".to_string(),
+        })
+        .unwrap();
+    assert_eq!(correction.voucher_number, 2);
+    assert_eq!(correction.description, "Duplicate (Correction of 1)");
+
+    let mut net: HashMap<AccountId, i64> = HashMap::new();
+    for row in original
+        .rows
+        .unwrap()
+        .iter()
+        .chain(correction.rows.as_ref().unwrap())
+    {
+        *net.entry(row.account_id.clone()).or_default() += row.debit.cents() - row.credit.cents();
+    }
+    assert_eq!(net.len(), 3);
+    assert!(net.values().all(|&cents| cents == 0), "{:?}", net);
+    assert_eq!(
+        correction.rows.unwrap()[0].credit.cents(),
+        80000,
+        "rows keep their order with debit and credit swapped"
+    );
+}
+
+#[test]
+fn voucher_with_inactive_counterparty_can_be_corrected() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let counterparty = |id, is_active| UpsertCounterpartyInput {
+        id,
+        company_id: demo.company_id.clone(),
+        name: "Leverantören AB".to_string(),
+        org_number: None,
+        vat_number: None,
+        address: None,
+        default_revenue_account_id: None,
+        default_expense_account_id: None,
+        is_active: Some(is_active),
+    };
+    let counterparty_id = books
+        .upsert_counterparty(counterparty(None, true))
+        .unwrap()
+        .id;
+    let purchase = || {
+        let mut input = demo.voucher("2024-03-01", "Goods", &[(4010, 5000, 0), (1930, 0, 5000)]);
+        input.counterparty_id = Some(counterparty_id.clone());
+        input
+    };
+    let original = books.create_voucher(purchase()).unwrap();
+    books
+        .upsert_counterparty(counterparty(Some(counterparty_id.clone()), false))
+        .unwrap();
+
+    let error = books.create_voucher(purchase()).unwrap_err();
+    assert!(error.contains("inactive"), "{}", error);
+    let correction = books
+        .create_correction_voucher(CreateCorrectionInput {
+            original_voucher_id: original.id,
+            date: date("2024-03-05"),
+            description: "Wrong supplier".to_string(),
+        })
+        .unwrap();
+    assert_eq!(correction.counterparty_id, Some(counterparty_id));
+    assert_eq!(correction.counterparty.as_deref(), Some("Leverantören AB"));
+}
+
+#[test]
+fn counterparty_cannot_move_to_another_company() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let other = other_company(&mut books);
+    let counterparty = |id, company_id| UpsertCounterpartyInput {
+        id,
+        company_id,
+        name: "Leverantören AB".to_string(),
+        org_number: None,
+        vat_number: None,
+        address: None,
+        default_revenue_account_id: None,
+        default_expense_account_id: None,
+        is_active: None,
+    };
+    let created = books
+        .upsert_counterparty(counterparty(None, demo.company_id.clone()))
+        .unwrap();
+
+    let result = books.upsert_counterparty(counterparty(Some(created.id), other));
+    assert_eq!(
+        result.unwrap_err(),
+        "Counterparty does not belong to company"
+    );
+}
+
+#[test]
+fn editing_a_schedule_does_not_rerun_past_months() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let template_row = |number, side: &str| VoucherTemplateRowInput {
+        account_id: demo.account(number),
+        description: None,
+        side: side.to_string(),
+        percent_basis_points: Some(10000),
+        fixed_amount: None,
+        vat_code: None,
+    };
+    let template = books
+        .upsert_voucher_template(UpsertVoucherTemplateInput {
+            id: None,
+            company_id: demo.company_id.clone(),
+            name: "Rent".to_string(),
+            description: "Rent".to_string(),
+            is_active: true,
+            rows: vec![template_row(4010, "debit"), template_row(1930, "credit")],
+        })
+        .unwrap();
+    let schedule = |id, total, day_of_month| UpsertRecurringScheduleInput {
+        id,
+        company_id: demo.company_id.clone(),
+        template_id: template.id.clone(),
+        series_id: demo.series_id.clone(),
+        total: Money::from_cents(total),
+        day_of_month,
+        start_date: date("2024-01-01"),
+        end_date: None,
+        is_active: true,
+    };
+    let run = |books: &mut Books<'_>| {
+        books
+            .run_recurring_schedules(RunRecurringSchedulesInput {
+                company_id: demo.company_id.clone(),
+                as_of_date: Some(date("2024-03-20")),
+            })
+            .unwrap()
+            .created
+            .len()
+    };
+    let id = books
+        .upsert_recurring_schedule(schedule(None, 1000000, 15))
+        .unwrap()
+        .id;
+    assert_eq!(run(&mut books), 3);
+
+    let edited = books
+        .upsert_recurring_schedule(schedule(Some(id.clone()), 1200000, 15))
+        .unwrap();
+    assert_eq!(edited.next_run_date, date("2024-04-15"));
+    assert_eq!(run(&mut books), 0);
+
+    let moved = books
+        .upsert_recurring_schedule(schedule(Some(id), 1200000, 28))
+        .unwrap();
+    assert_eq!(moved.next_run_date, date("2024-04-28"));
+    assert_eq!(run(&mut books), 0);
+}
+
+#[test]
+fn templates_and_schedules_cannot_move_to_another_company() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let other = other_company(&mut books);
+    let mut other_account = |number, name: &str| {
+        books
+            .upsert_account(UpsertAccountInput {
+                id: None,
+                company_id: other.clone(),
+                number,
+                name: name.to_string(),
+                account_type: "Expense".to_string(),
+                vat_code: None,
+                is_active: true,
+            })
+            .unwrap()
+            .id
+    };
+    let other_accounts = [
+        other_account(5010, "Lokalhyra"),
+        other_account(1930, "Bank"),
+    ];
+    let template = |id, company_id: &CompanyId, accounts: [AccountId; 2]| {
+        let [debit, credit] = accounts;
+        let row = |account_id, side: &str| VoucherTemplateRowInput {
+            account_id,
+            description: None,
+            side: side.to_string(),
+            percent_basis_points: Some(10000),
+            fixed_amount: None,
+            vat_code: None,
+        };
+        UpsertVoucherTemplateInput {
+            id,
+            company_id: company_id.clone(),
+            name: "Rent".to_string(),
+            description: "Rent".to_string(),
+            is_active: true,
+            rows: vec![row(debit, "debit"), row(credit, "credit")],
+        }
+    };
+    let demo_accounts = [demo.account(2641), demo.account(1930)];
+    let created = books
+        .upsert_voucher_template(template(None, &demo.company_id, demo_accounts))
+        .unwrap();
+    let result = books.upsert_voucher_template(template(
+        Some(created.id.clone()),
+        &other,
+        other_accounts.clone(),
+    ));
+    assert_eq!(result.unwrap_err(), "Template does not belong to company");
+
+    let other_template = books
+        .upsert_voucher_template(template(None, &other, other_accounts.clone()))
+        .unwrap();
+    let other_series = books
+        .list_voucher_series(CompanyIdInput {
+            company_id: other.clone(),
+        })
+        .unwrap()
+        .remove(0)
+        .id;
+    let schedule =
+        |id, company_id: &CompanyId, template_id, series_id| UpsertRecurringScheduleInput {
+            id,
+            company_id: company_id.clone(),
+            template_id,
+            series_id,
+            total: Money::from_cents(100000),
+            day_of_month: 1,
+            start_date: date("2024-01-01"),
+            end_date: None,
+            is_active: true,
+        };
+    let created = books
+        .upsert_recurring_schedule(schedule(
+            None,
+            &demo.company_id,
+            created.id,
+            demo.series_id.clone(),
+        ))
+        .unwrap();
+    let result = books.upsert_recurring_schedule(schedule(
+        Some(created.id),
+        &other,
+        other_template.id,
+        other_series,
+    ));
+    assert_eq!(result.unwrap_err(), "Schedule does not belong to company");
+}
+
+#[test]
+fn template_rows_need_accounts_of_the_company() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let other = other_company(&mut books);
+    let other_bank = books
+        .upsert_account(UpsertAccountInput {
+            id: None,
+            company_id: other,
+            number: 1930,
+            name: "Bank".to_string(),
+            account_type: "Asset".to_string(),
+            vat_code: None,
+            is_active: true,
+        })
+        .unwrap()
+        .id;
+    let row = |account_id, side: &str| VoucherTemplateRowInput {
+        account_id,
+        description: None,
+        side: side.to_string(),
+        percent_basis_points: Some(10000),
+        fixed_amount: None,
+        vat_code: None,
+    };
+
+    let result = books.upsert_voucher_template(UpsertVoucherTemplateInput {
+        id: None,
+        company_id: demo.company_id.clone(),
+        name: "Rent".to_string(),
+        description: "Rent".to_string(),
+        is_active: true,
+        rows: vec![row(demo.account(4010), "debit"), row(other_bank, "credit")],
+    });
+    assert_eq!(
+        result.unwrap_err(),
+        "Row 2: Account does not belong to company"
+    );
+    let templates = books
+        .list_voucher_templates(CompanyIdInput {
+            company_id: demo.company_id.clone(),
+        })
+        .unwrap();
+    assert!(templates.is_empty());
+}
+
+#[test]
+fn ledger_has_running_balance_in_date_order() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    for (date, debit, credit) in [
+        ("2024-03-10", 0, 30000),
+        ("2024-01-15", 100000, 0),
+        ("2024-02-20", 25000, 0),
+    ] {
+        books
+            .create_voucher(demo.voucher(
+                date,
+                "Bank",
+                &[(1930, debit, credit), (3010, credit, debit)],
+            ))
+            .unwrap();
+    }
+
+    let ledger = books
+        .report_ledger_for_account(ReportLedgerInput {
+            company_id: demo.company_id.clone(),
+            account_id: demo.account(1930),
+            from_date: None,
+            to_date: None,
+        })
+        .unwrap();
+    let balances: Vec<(NaiveDate, i64, i64)> = ledger
+        .iter()
+        .map(|row| (row.date, row.voucher_number, row.balance.cents()))
+        .collect();
+    assert_eq!(
+        balances,
+        [
+            (date("2024-01-15"), 2, 100000),
+            (date("2024-02-20"), 3, 125000),
+            (date("2024-03-10"), 1, 95000),
+        ]
+    );
+}
+
+#[test]
+fn vouchers_are_listed_in_pages() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    for date in [
+        "2024-01-15",
+        "2024-03-10",
+        "2024-01-15",
+        "2024-02-20",
+        "2024-03-10",
+    ] {
+        books
+            .create_voucher(demo.voucher(date, "Bank", &[(1930, 100, 0), (3010, 0, 100)]))
+            .unwrap();
+    }
+    let mut page = |limit, after| {
+        books.list_vouchers(ListVouchersInput {
+            company_id: demo.company_id.clone(),
+            from_date: None,
+            to_date: None,
+            limit,
+            after,
+        })
+    };
+
+    let all = page(None, None).unwrap();
+    let mut paged = Vec::new();
+    let mut after = None;
+    loop {
+        let vouchers = page(Some(2), after).unwrap();
+        assert!(vouchers.len() <= 2);
+        let Some(last) = vouchers.last() else {
+            break;
+        };
+        after = Some(last.id.clone());
+        paged.extend(vouchers);
+    }
+
+    let numbers = |vouchers: &[Voucher]| -> Vec<(NaiveDate, i64, usize)> {
+        vouchers
+            .iter()
+            .map(|voucher| {
+                let rows = voucher.rows.as_ref().map_or(0, Vec::len);
+                (voucher.date, voucher.voucher_number, rows)
+            })
+            .collect()
+    };
+    assert_eq!(numbers(&paged), numbers(&all));
+    assert_eq!(
+        numbers(&all),
+        [
+            (date("2024-03-10"), 5, 2),
+            (date("2024-03-10"), 2, 2),
+            (date("2024-02-20"), 4, 2),
+            (date("2024-01-15"), 3, 2),
+            (date("2024-01-15"), 1, 2),
+        ]
+    );
+    assert_eq!(
+        page(Some(0), None).unwrap_err(),
+        "limit: must be at least 1"
+    );
+}
+
+#[test]
+fn csv_export_matches_golden_files() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    book_sample_year(&mut books, &demo);
+    let target = tempfile::tempdir().unwrap();
+
+    books
+        .export_csv(ExportInput {
+            company_id: demo.company_id.clone(),
+            target_path: target.path().to_string_lossy().into_owned(),
+        })
+        .unwrap();
+
+    let replacements = stable_names(&mut books, &demo);
+    for name in ["vouchers.csv", "voucher_rows.csv"] {
+        let output = fs::read_to_string(target.path().join(name)).unwrap();
+        assert_golden(name, &normalize(&output, &replacements));
+    }
+}
+
+#[test]
+fn sie_export_matches_golden_file() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    book_sample_year(&mut books, &demo);
+    let target = tempfile::tempdir().unwrap();
+
+    books
+        .export_sie_stub(ExportInput {
+            company_id: demo.company_id.clone(),
+            target_path: target.path().to_string_lossy().into_owned(),
+        })
+        .unwrap();
+
+    let replacements = stable_names(&mut books, &demo);
+    let output = fs::read_to_string(target.path().join("export.sie")).unwrap();
+    assert_golden("export.sie", &normalize(&output, &replacements));
+}
+
+#[test]
+fn archive_export_fails_when_an_attached_file_is_missing() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, dir.path());
+    let demo = Demo::load(&mut books);
+    let missing = dir.path().join("kvitto.pdf").to_string_lossy().into_owned();
+    let mut input = demo.voucher("2024-03-01", "Sale", &[(1930, 100, 0), (3010, 0, 100)]);
+    input.attachments = Some(vec![CreateAttachmentInput {
+        ref_type: "Fil".to_string(),
+        ref_value: missing.clone(),
+        note: None,
+    }]);
+    let voucher = books.create_voucher(input).unwrap();
+    books
+        .post_voucher(VoucherIdInput {
+            voucher_id: voucher.id,
+        })
+        .unwrap();
+    books
+        .lock_period(LockPeriodInput {
+            company_id: demo.company_id.clone(),
+            period_start: date("2024-01-01"),
+            period_end: date("2024-12-31"),
+        })
+        .unwrap();
+
+    let result = books.export_archive(ExportArchiveInput {
+        company_id: demo.company_id.clone(),
+        fiscal_year_start: date("2024-01-01"),
+        target_path: dir.path().to_string_lossy().into_owned(),
+    });
+    let error = result.unwrap_err();
+    assert!(error.contains(&format!("A1: {}", missing)), "{}", error);
+}
+
+#[test]
+fn readers_see_committed_vouchers_and_cannot_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(db::DATABASE_FILE);
+    let mut writer = db::open_database(&path, None).unwrap();
+    let mut reader = db::open_reader(&path, None).unwrap();
+    let mut books = Books::new(&mut writer, USER, dir.path());
+    let demo = Demo::load(&mut books);
+    book_sample_year(&mut books, &demo);
+
+    let mut read_books = Books::new(&mut reader, USER, dir.path());
+    let vouchers = read_books
+        .list_vouchers(ListVouchersInput {
+            company_id: demo.company_id.clone(),
+            from_date: None,
+            to_date: None,
+            limit: None,
+            after: None,
+        })
+        .unwrap();
+    assert_eq!(vouchers.len(), 3);
+    read_books
+        .export_csv(ExportInput {
+            company_id: demo.company_id.clone(),
+            target_path: dir.path().join("export").to_string_lossy().into_owned(),
+        })
+        .unwrap();
+
+    let result = read_books.create_voucher(demo.voucher(
+        "2024-03-01",
+        "Sale",
+        &[(1930, 100, 0), (3010, 0, 100)],
+    ));
+    let error = result.unwrap_err();
+    assert!(error.contains("readonly"), "{}", error);
+}
+
+#[test]
+fn archive_is_written_on_a_reader_and_recorded_on_the_writer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(db::DATABASE_FILE);
+    let mut writer = db::open_database(&path, None).unwrap();
+    let mut reader = db::open_reader(&path, None).unwrap();
+    let mut books = Books::new(&mut writer, USER, dir.path());
+    let demo = Demo::load(&mut books);
+    book_sample_year(&mut books, &demo);
+    let receipt = dir.path().join("kvitto.pdf");
+    let contents = vec![7u8; 200_000];
+    fs::write(&receipt, &contents).unwrap();
+    let mut input = demo.voucher("2024-03-01", "Sale", &[(1930, 100, 0), (3010, 0, 100)]);
+    input.attachments = Some(vec![CreateAttachmentInput {
+        ref_type: "Fil".to_string(),
+        ref_value: receipt.to_string_lossy().into_owned(),
+        note: None,
+    }]);
+    books.create_voucher(input).unwrap();
+    let vouchers = books
+        .list_vouchers(ListVouchersInput {
+            company_id: demo.company_id.clone(),
+            from_date: None,
+            to_date: None,
+            limit: None,
+            after: None,
+        })
+        .unwrap();
+    for voucher in vouchers
+        .into_iter()
+        .filter(|voucher| voucher.posted_at.is_none())
+    {
+        books
+            .post_voucher(VoucherIdInput {
+                voucher_id: voucher.id,
+            })
+            .unwrap();
+    }
+    books
+        .lock_period(LockPeriodInput {
+            company_id: demo.company_id.clone(),
+            period_start: date("2024-01-01"),
+            period_end: date("2024-12-31"),
+        })
+        .unwrap();
+
+    let archive = Books::new(&mut reader, USER, dir.path())
+        .write_archive(ExportArchiveInput {
+            company_id: demo.company_id.clone(),
+            fiscal_year_start: date("2024-01-01"),
+            target_path: dir.path().to_string_lossy().into_owned(),
+        })
+        .unwrap();
+    assert!(Path::new(&archive.path).is_file());
+    assert_eq!(archive.voucher_count, 4);
+    assert_eq!(archive.attachment_count, 1);
+    let attached = archive
+        .files
+        .iter()
+        .find(|file| file.path.ends_with("kvitto.pdf"))
+        .unwrap();
+    assert_eq!(attached.size_bytes, contents.len() as i64);
+    let expected: String = Sha256::digest(&contents)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert_eq!(attached.sha256, expected);
+
+    Books::new(&mut writer, USER, dir.path())
+        .record_archive_export(&demo.company_id, &archive)
+        .unwrap();
+    let sha256: String = writer
+        .query_row(
+            "SELECT json_extract(payload_json, '$.sha256') FROM audit_log
+             WHERE entity_type = 'fiscal_year' AND action = 'export_archive'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(sha256, archive.sha256);
+}
+
+/// A sale, a purchase and the correction of a posted voucher.
+fn book_sample_year(books: &mut Books<'_>, demo: &Demo) {
+    let sale = books
+        .create_voucher(demo.voucher(
+            "2024-01-10",
+            "Sale, invoice 1001",
+            &[(1930, 125000, 0), (3010, 0, 100000), (2611, 0, 25000)],
+        ))
+        .unwrap();
+    books
+        .post_voucher(VoucherIdInput {
+            voucher_id: sale.id,
+        })
+        .unwrap();
+    let purchase = books
+        .create_voucher(demo.voucher(
+            "2024-02-03",
+            "Goods",
+            &[(4010, 40000, 0), (2641, 10000, 0), (1930, 0, 50000)],
+        ))
+        .unwrap();
+    books
+        .post_voucher(VoucherIdInput {
+            voucher_id: purchase.id.clone(),
+        })
+        .unwrap();
+    books
+        .create_correction_voucher(CreateCorrectionInput {
+            original_voucher_id: purchase.id,
+            date: date("2024-02-05"),
+            description: "Booked twice".to_string(),
+        })
+        .unwrap();
+}
+
+/// Ids and timestamps differ between runs; they are replaced by names made
+/// from voucher and account numbers.
+fn stable_names(books: &mut Books<'_>, demo: &Demo) -> Vec<(String, String)> {
+    let vouchers = books
+        .list_vouchers(ListVouchersInput {
+            company_id: demo.company_id.clone(),
+            from_date: None,
+            to_date: None,
+            limit: None,
+            after: None,
+        })
+        .unwrap();
+    let mut replacements = Vec::new();
+    for voucher in vouchers {
+        let number = voucher.voucher_number;
+        replacements.push((voucher.id.to_string(), format!("voucher-{}", number)));
+        replacements.push((voucher.created_at, format!("created-{}", number)));
+        if let Some(posted_at) = voucher.posted_at {
+            replacements.push((posted_at, format!("posted-{}", number)));
+        }
+    }
+    for (number, id) in &demo.accounts {
+        replacements.push((id.to_string(), format!("account-{}", number)));
+    }
+    replacements
+}
+
+fn normalize(output: &str, replacements: &[(String, String)]) -> String {
+    replacements
+        .iter()
+        .fold(output.to_string(), |text, (from, to)| {
+            text.replace(from, to)
+        })
+}
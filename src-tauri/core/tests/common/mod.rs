@@ -0,0 +1,129 @@
+//! Setup shared by the integration tests: a migrated in-memory database with
+//! the seeded demo company.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use bokforing_core::db;
+use bokforing_core::ids::{AccountId, CompanyId, SeriesId};
+use bokforing_core::models::{
+    CompanyIdInput, CreateCompanyInput, CreateVoucherInput, CreateVoucherRowInput,
+};
+use bokforing_core::money::Money;
+use bokforing_core::Books;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+
+pub const USER: &str = "tester";
+
+pub fn open_books() -> Connection {
+    let mut conn = Connection::open_in_memory().expect("in-memory database");
+    db::init_schema(&mut conn).expect("schema");
+    conn
+}
+
+/// The seeded demo company: series A and the accounts by number.
+pub struct Demo {
+    pub company_id: CompanyId,
+    pub series_id: SeriesId,
+    pub accounts: HashMap<i64, AccountId>,
+}
+
+impl Demo {
+    pub fn load(books: &mut Books<'_>) -> Demo {
+        let company_id = books.list_companies().unwrap().remove(0).id;
+        let series_id = books
+            .list_voucher_series(CompanyIdInput {
+                company_id: company_id.clone(),
+            })
+            .unwrap()
+            .remove(0)
+            .id;
+        let accounts = books
+            .list_accounts(CompanyIdInput {
+                company_id: company_id.clone(),
+            })
+            .unwrap()
+            .into_iter()
+            .map(|account| (account.number, account.id))
+            .collect();
+        Demo {
+            company_id,
+            series_id,
+            accounts,
+        }
+    }
+
+    pub fn account(&self, number: i64) -> AccountId {
+        self.accounts[&number].clone()
+    }
+
+    /// A voucher in series A with rows of (account number, debit, credit) in
+    /// öre.
+    pub fn voucher(
+        &self,
+        value: &str,
+        description: &str,
+        rows: &[(i64, i64, i64)],
+    ) -> CreateVoucherInput {
+        CreateVoucherInput {
+            company_id: self.company_id.clone(),
+            series_id: self.series_id.clone(),
+            date: date(value),
+            description: description.to_string(),
+            counterparty: None,
+            counterparty_id: None,
+            rows: rows
+                .iter()
+                .map(|&(number, debit, credit)| CreateVoucherRowInput {
+                    account_id: self.account(number),
+                    description: None,
+                    debit: Money::from_cents(debit),
+                    credit: Money::from_cents(credit),
+                    vat_code: None,
+                })
+                .collect(),
+            attachments: None,
+        }
+    }
+}
+
+/// A second company next to the demo company.
+pub fn other_company(books: &mut Books<'_>) -> CompanyId {
+    books
+        .create_company(CreateCompanyInput {
+            name: "Andra AB".to_string(),
+            org_number: None,
+            accounting_method: None,
+        })
+        .unwrap()
+        .id
+}
+
+/// A date in `YYYY-MM-DD` form.
+pub fn date(value: &str) -> NaiveDate {
+    value.parse().expect("valid date")
+}
+
+/// Compares `actual` with `tests/golden/<name>`. Run with `UPDATE_GOLDEN=1`
+/// to rewrite the file after an intended change of output.
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("Cannot read {}: {}", path.display(), err));
+    assert_eq!(
+        actual, expected,
+        "{} differs from the golden file; rerun with UPDATE_GOLDEN=1 if intended",
+        name
+    );
+}
@@ -0,0 +1,231 @@
+//! Opening and replacing the database file.
+
+mod common;
+
+use std::fs;
+
+use bokforing_core::backup;
+use bokforing_core::db::{self, DbError};
+use bokforing_core::models::{ListVouchersInput, LockPeriodInput, RestoreBackupInput};
+use bokforing_core::Books;
+use common::{date, Demo, USER};
+
+#[test]
+fn failed_swap_keeps_the_original_database_open() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(db::DATABASE_FILE);
+    let replacement = dir.path().join("replacement.sqlite");
+    let previous = dir.path().join("previous.sqlite");
+    let mut conn = db::open_database(&path, None).unwrap();
+    fs::write(&replacement, b"not a database").unwrap();
+
+    // The replacement cannot be opened, so the swap fails after the files
+    // were moved.
+    let result = db::swap_database(
+        &mut conn,
+        &path,
+        &replacement,
+        Some(&previous),
+        Some("passphrase"),
+        None,
+    );
+    assert!(
+        matches!(result, Err(DbError::WrongPassphrase)),
+        "{:?}",
+        result.err()
+    );
+
+    assert!(replacement.exists());
+    assert!(!previous.exists());
+    let mut books = Books::new(&mut conn, USER, dir.path());
+    let demo = Demo::load(&mut books);
+    books
+        .create_voucher(demo.voucher("2024-03-01", "Sale", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+    drop(conn);
+    let mut reopened = db::open_database(&path, None).unwrap();
+    let mut books = Books::new(&mut reopened, USER, dir.path());
+    let vouchers = books
+        .list_vouchers(ListVouchersInput {
+            company_id: demo.company_id,
+            from_date: None,
+            to_date: None,
+            limit: None,
+            after: None,
+        })
+        .unwrap();
+    assert_eq!(vouchers.len(), 1);
+}
+
+#[test]
+fn backups_in_the_same_second_get_their_own_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(db::DATABASE_FILE);
+    let backups = dir.path().join("backups");
+    let conn = db::open_database(&path, None).unwrap();
+
+    let first = backup::backup_path(&backups, false);
+    let snapshot = backup::snapshot_database(&conn, None, &first).unwrap();
+    backup::write_backup_archive(snapshot, dir.path(), &first, false).unwrap();
+    let second = backup::backup_path(&backups, false);
+    assert_ne!(first, second);
+    let snapshot = backup::snapshot_database(&conn, None, &second).unwrap();
+    backup::write_backup_archive(snapshot, dir.path(), &second, false).unwrap();
+
+    let result = backup::snapshot_database(&conn, None, &first);
+    assert!(result.unwrap_err().contains("already exists"));
+    let listed: Vec<String> = backup::list_backups(&backups)
+        .unwrap()
+        .into_iter()
+        .map(|backup| backup.path)
+        .collect();
+    assert_eq!(
+        listed,
+        [second, first].map(|path| path.to_string_lossy().into_owned())
+    );
+}
+
+#[test]
+fn failed_snapshot_leaves_no_copy_behind() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(db::DATABASE_FILE);
+    let conn = db::open_database(&path, None).unwrap();
+    let target = backup::backup_path(&dir.path().join("backups"), false);
+
+    // SQLCipher cannot copy a plain database into an encrypted one.
+    let result = backup::snapshot_database(&conn, Some("passphrase"), &target);
+    assert!(result.is_err());
+    let left: Vec<_> = fs::read_dir(dir.path().join("backups"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert!(left.is_empty(), "{:?}", left);
+}
+
+#[test]
+fn backup_is_taken_on_a_read_only_connection() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(db::DATABASE_FILE);
+    let documents_dir = dir.path().join("documents");
+    let mut conn = db::open_database(&path, None).unwrap();
+    let mut books = Books::new(&mut conn, USER, &documents_dir);
+    let demo = Demo::load(&mut books);
+    books
+        .create_voucher(demo.voucher("2024-03-01", "Sale", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+
+    let reader = db::open_reader(&path, None).unwrap();
+    let target = backup::backup_path(&dir.path().join("backups"), false);
+    let snapshot = backup::snapshot_database(&reader, None, &target).unwrap();
+    drop(reader);
+    // The writer is free while the archive is packed.
+    let mut books = Books::new(&mut conn, USER, &documents_dir);
+    books
+        .create_voucher(demo.voucher("2024-03-02", "Later", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+    backup::write_backup_archive(snapshot, &documents_dir, &target, false).unwrap();
+
+    backup::restore_backup(
+        &mut conn,
+        &path,
+        &documents_dir,
+        RestoreBackupInput {
+            path: target.to_string_lossy().into_owned(),
+            passphrase: None,
+        },
+        None,
+    )
+    .unwrap();
+    let mut books = Books::new(&mut conn, USER, &documents_dir);
+    let vouchers = books
+        .list_vouchers(ListVouchersInput {
+            company_id: demo.company_id,
+            from_date: None,
+            to_date: None,
+            limit: None,
+            after: None,
+        })
+        .unwrap();
+    assert_eq!(vouchers.len(), 1);
+    assert_eq!(vouchers[0].description, "Sale");
+}
+
+#[test]
+fn migration_rewrites_stored_dates() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(db::DATABASE_FILE);
+    let mut conn = db::open_database(&path, None).unwrap();
+    let mut books = Books::new(&mut conn, USER, dir.path());
+    let demo = Demo::load(&mut books);
+    let lock = books
+        .lock_period(LockPeriodInput {
+            company_id: demo.company_id.clone(),
+            period_start: date("2024-09-01"),
+            period_end: date("2024-09-30"),
+        })
+        .unwrap();
+    // Written by an older version without zero padding.
+    conn.execute(
+        "UPDATE period_locks SET period_start = '2024-9-1', period_end = '2024-9-30' WHERE id = ?1",
+        [&lock.id],
+    )
+    .unwrap();
+    conn.pragma_update(None, "user_version", 4).unwrap();
+    drop(conn);
+
+    let conn = db::open_database(&path, None).unwrap();
+    let (start, end): (String, String) = conn
+        .query_row(
+            "SELECT period_start, period_end FROM period_locks WHERE id = ?1",
+            [&lock.id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((start.as_str(), end.as_str()), ("2024-09-01", "2024-09-30"));
+    assert!(db::is_period_locked(&conn, demo.company_id.as_str(), date("2024-09-15")).unwrap());
+    assert!(!db::is_period_locked(&conn, demo.company_id.as_str(), date("2024-10-01")).unwrap());
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(version, db::SCHEMA_VERSION);
+}
+
+#[test]
+fn database_with_invalid_dates_is_not_opened() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(db::DATABASE_FILE);
+    let mut conn = db::open_database(&path, None).unwrap();
+    let mut books = Books::new(&mut conn, USER, dir.path());
+    let demo = Demo::load(&mut books);
+    let voucher = books
+        .create_voucher(demo.voucher("2024-03-01", "Sale", &[(1930, 100, 0), (3010, 0, 100)]))
+        .unwrap();
+    conn.execute(
+        "UPDATE vouchers SET date = '2024-13-45' WHERE id = ?1",
+        [&voucher.id],
+    )
+    .unwrap();
+    conn.execute("UPDATE companies SET fiscal_year_end = '2024-12-1'", [])
+        .unwrap();
+    conn.pragma_update(None, "user_version", 4).unwrap();
+    drop(conn);
+
+    let message = match db::open_database(&path, None) {
+        Err(DbError::Invalid(message)) => message,
+        other => panic!("{:?}", other.map(|_| ())),
+    };
+    assert!(message.contains("vouchers.date row"), "{}", message);
+    assert!(message.contains("'2024-13-45'"), "{}", message);
+    // Nothing is rewritten and the migration runs again on the next open.
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let end: String = conn
+        .query_row("SELECT fiscal_year_end FROM companies", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(end, "2024-12-1");
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(version, 4);
+}
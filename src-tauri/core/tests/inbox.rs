@@ -0,0 +1,54 @@
+//! Importing receipts into the document inbox.
+
+mod common;
+
+use std::fs;
+
+use bokforing_core::models::{ImportInboxDocumentsInput, ListInboxDocumentsInput};
+use bokforing_core::Books;
+use common::{open_books, Demo, USER};
+
+#[test]
+fn failed_import_leaves_no_copied_files() {
+    let mut conn = open_books();
+    let dir = tempfile::tempdir().unwrap();
+    let documents_dir = dir.path().join("documents");
+    let mut books = Books::new(&mut conn, USER, &documents_dir);
+    let demo = Demo::load(&mut books);
+    let receipt = dir.path().join("kvitto.pdf");
+    fs::write(&receipt, "receipt").unwrap();
+
+    let result = books.import_inbox_documents(ImportInboxDocumentsInput {
+        company_id: demo.company_id.clone(),
+        file_paths: vec![
+            receipt.to_string_lossy().into_owned(),
+            dir.path()
+                .join("missing.pdf")
+                .to_string_lossy()
+                .into_owned(),
+        ],
+        note: None,
+    });
+    assert!(result.unwrap_err().contains("missing.pdf"));
+    let company_dir = documents_dir.join(demo.company_id.as_str());
+    assert_eq!(fs::read_dir(company_dir).unwrap().count(), 0);
+
+    let documents = books
+        .import_inbox_documents(ImportInboxDocumentsInput {
+            company_id: demo.company_id.clone(),
+            file_paths: vec![receipt.to_string_lossy().into_owned()],
+            note: None,
+        })
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(&documents[0].stored_path).unwrap(),
+        "receipt"
+    );
+    let listed = books
+        .list_inbox_documents(ListInboxDocumentsInput {
+            company_id: demo.company_id.clone(),
+            include_linked: None,
+        })
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+}
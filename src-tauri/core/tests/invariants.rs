@@ -0,0 +1,245 @@
+//! Invariants of the ledger under random sequences of voucher creations,
+//! postings, corrections and period locks. Rejected operations are part of
+//! the sequences too: whatever the books refuse must leave no trace.
+
+mod common;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use bokforing_core::ids::{AccountId, VoucherId};
+use bokforing_core::models::{
+    CreateCorrectionInput, ListVouchersInput, LockPeriodInput, ReportTrialBalanceInput, Voucher,
+    VoucherIdInput,
+};
+use bokforing_core::money::Money;
+use bokforing_core::Books;
+use chrono::{Duration, NaiveDate};
+use common::{open_books, Demo, USER};
+use proptest::prelude::*;
+
+/// The accounts of the seeded demo company.
+const ACCOUNTS: [i64; 5] = [1930, 2611, 2641, 3010, 4010];
+const YEAR: i32 = 2024;
+
+#[derive(Debug, Clone)]
+enum Op {
+    /// Rows of (index into `ACCOUNTS`, debit, credit), dated `day` days into
+    /// the year.
+    Create {
+        day: i64,
+        rows: Vec<(usize, i64, i64)>,
+    },
+    /// Indexes wrap around the vouchers created so far.
+    Post {
+        voucher: usize,
+    },
+    Correct {
+        voucher: usize,
+        day: i64,
+    },
+    Lock {
+        month: u32,
+    },
+}
+
+fn balanced_rows() -> impl Strategy<Value = Vec<(usize, i64, i64)>> {
+    (
+        prop::collection::vec((0..ACCOUNTS.len(), 1..1_000_000i64, any::<bool>()), 1..5),
+        0..ACCOUNTS.len(),
+    )
+        .prop_map(|(amounts, balancing_account)| {
+            let mut rows: Vec<_> = amounts
+                .into_iter()
+                .map(|(account, cents, debit)| {
+                    if debit {
+                        (account, cents, 0)
+                    } else {
+                        (account, 0, cents)
+                    }
+                })
+                .collect();
+            let difference: i64 = rows.iter().map(|(_, debit, credit)| debit - credit).sum();
+            if difference != 0 {
+                rows.push((balancing_account, (-difference).max(0), difference.max(0)));
+            }
+            rows
+        })
+}
+
+fn any_rows() -> impl Strategy<Value = Vec<(usize, i64, i64)>> {
+    prop::collection::vec((0..ACCOUNTS.len(), 0..1_000_000i64, 0..1_000_000i64), 0..5)
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (0..366i64, balanced_rows()).prop_map(|(day, rows)| Op::Create { day, rows }),
+        1 => (0..366i64, any_rows()).prop_map(|(day, rows)| Op::Create { day, rows }),
+        2 => any::<usize>().prop_map(|voucher| Op::Post { voucher }),
+        2 => (any::<usize>(), 0..366i64).prop_map(|(voucher, day)| Op::Correct { voucher, day }),
+        1 => (1..=12u32).prop_map(|month| Op::Lock { month }),
+    ]
+}
+
+fn date(day: i64) -> NaiveDate {
+    NaiveDate::from_ymd_opt(YEAR, 1, 1).unwrap() + Duration::days(day)
+}
+
+fn month(month: u32) -> (NaiveDate, NaiveDate) {
+    let start = NaiveDate::from_ymd_opt(YEAR, month, 1).unwrap();
+    let next = if month == 12 {
+        NaiveDate::from_ymd_opt(YEAR + 1, 1, 1).unwrap()
+    } else {
+        NaiveDate::from_ymd_opt(YEAR, month + 1, 1).unwrap()
+    };
+    (start, next - Duration::days(1))
+}
+
+/// What the sequence did, for the checks afterwards.
+#[derive(Default)]
+struct History {
+    created: Vec<VoucherId>,
+    /// Pairs of original and correction.
+    corrections: Vec<(VoucherId, VoucherId)>,
+    /// Locked periods with the number of vouchers in them when locked.
+    locks: Vec<(NaiveDate, NaiveDate, usize)>,
+}
+
+fn run(books: &mut Books<'_>, demo: &Demo, ops: Vec<Op>) -> History {
+    let mut history = History::default();
+    for op in ops {
+        match op {
+            Op::Create { day, rows } => {
+                let rows: Vec<_> = rows
+                    .into_iter()
+                    .map(|(account, debit, credit)| (ACCOUNTS[account], debit, credit))
+                    .collect();
+                if let Ok(voucher) =
+                    books.create_voucher(demo.voucher(&date(day).to_string(), "Random", &rows))
+                {
+                    history.created.push(voucher.id);
+                }
+            }
+            Op::Post { voucher } if !history.created.is_empty() => {
+                let voucher_id = history.created[voucher % history.created.len()].clone();
+                let _ = books.post_voucher(VoucherIdInput { voucher_id });
+            }
+            Op::Correct { voucher, day } if !history.created.is_empty() => {
+                let original = history.created[voucher % history.created.len()].clone();
+                if let Ok(correction) = books.create_correction_voucher(CreateCorrectionInput {
+                    original_voucher_id: original.clone(),
+                    date: date(day),
+                    description: "Correction".to_string(),
+                }) {
+                    history.created.push(correction.id.clone());
+                    history.corrections.push((original, correction.id));
+                }
+            }
+            Op::Lock { month: number } => {
+                let (start, end) = month(number);
+                let existing = vouchers_between(books, demo, start, end).len();
+                if books
+                    .lock_period(LockPeriodInput {
+                        company_id: demo.company_id.clone(),
+                        period_start: start,
+                        period_end: end,
+                    })
+                    .is_ok()
+                {
+                    history.locks.push((start, end, existing));
+                }
+            }
+            Op::Post { .. } | Op::Correct { .. } => {}
+        }
+    }
+    history
+}
+
+fn vouchers_between(
+    books: &mut Books<'_>,
+    demo: &Demo,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<Voucher> {
+    books
+        .list_vouchers(ListVouchersInput {
+            company_id: demo.company_id.clone(),
+            from_date: Some(from),
+            to_date: Some(to),
+            limit: None,
+            after: None,
+        })
+        .unwrap()
+}
+
+fn net_per_account(vouchers: &[&Voucher]) -> HashMap<AccountId, i64> {
+    let mut net = HashMap::new();
+    for voucher in vouchers {
+        for row in voucher.rows.as_deref().unwrap_or_default() {
+            *net.entry(row.account_id.clone()).or_default() +=
+                row.debit.cents() - row.credit.cents();
+        }
+    }
+    net
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn ledger_invariants_hold(ops in prop::collection::vec(op(), 1..40)) {
+        let mut conn = open_books();
+        let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+        let demo = Demo::load(&mut books);
+        let history = run(&mut books, &demo, ops);
+
+        let vouchers = books
+            .list_vouchers(ListVouchersInput {
+                company_id: demo.company_id.clone(),
+                from_date: None,
+                to_date: None,
+                limit: None,
+                after: None,
+            })
+            .unwrap();
+        prop_assert_eq!(vouchers.len(), history.created.len());
+        let by_id: HashMap<&VoucherId, &Voucher> =
+            vouchers.iter().map(|voucher| (&voucher.id, voucher)).collect();
+
+        // Every voucher balances.
+        for voucher in &vouchers {
+            let net = net_per_account(&[voucher]);
+            prop_assert_eq!(net.values().sum::<i64>(), 0, "voucher {}", voucher.voucher_number);
+        }
+
+        // The trial balance sums to zero.
+        let balance = books
+            .report_trial_balance(ReportTrialBalanceInput {
+                company_id: demo.company_id.clone(),
+                from_date: None,
+                to_date: None,
+            })
+            .unwrap();
+        prop_assert!(Money::checked_sum(balance.iter().map(|row| row.closing))
+            .unwrap()
+            .is_zero());
+
+        // A voucher and its correction net to zero on every account.
+        for (original, correction) in &history.corrections {
+            let net = net_per_account(&[by_id[original], by_id[correction]]);
+            prop_assert!(net.values().all(|&cents| cents == 0), "{:?}", net);
+        }
+
+        // Locked periods never gain vouchers.
+        for (start, end, existing) in &history.locks {
+            let count = vouchers_between(&mut books, &demo, *start, *end).len();
+            prop_assert_eq!(count, *existing, "{} to {}", start, end);
+        }
+
+        // Series A is numbered 1, 2, 3, ... without gaps or duplicates.
+        let mut numbers: Vec<i64> = vouchers.iter().map(|voucher| voucher.voucher_number).collect();
+        numbers.sort_unstable();
+        let expected: Vec<i64> = (1..=vouchers.len() as i64).collect();
+        prop_assert_eq!(numbers, expected);
+    }
+}
@@ -0,0 +1,261 @@
+//! Customers, suppliers, their invoices and the cash method year end.
+
+mod common;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use bokforing_core::ids::AccountId;
+use bokforing_core::models::{
+    CashYearEndInput, CreateCustomerInvoiceInput, CreateSupplierInvoiceInput,
+    CustomerInvoiceLineInput, SetAccountingMethodInput, SupplierInvoiceLineInput,
+    UpsertAccountInput, UpsertCustomerInput, UpsertSupplierInput, Voucher,
+};
+use bokforing_core::money::Money;
+use bokforing_core::Books;
+use common::{date, open_books, other_company, Demo, USER};
+
+#[test]
+fn customer_cannot_move_to_another_company() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let other = other_company(&mut books);
+    let customer = |id, company_id| UpsertCustomerInput {
+        id,
+        company_id,
+        name: "Kunden AB".to_string(),
+        org_number: None,
+        email: None,
+        address: None,
+        payment_terms_days: None,
+    };
+    let created = books
+        .upsert_customer(customer(None, demo.company_id.clone()))
+        .unwrap();
+
+    let result = books.upsert_customer(customer(Some(created.id), other));
+    assert_eq!(result.unwrap_err(), "Customer does not belong to company");
+}
+
+#[test]
+fn supplier_cannot_move_to_another_company() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let other = other_company(&mut books);
+    let supplier = |id, company_id| UpsertSupplierInput {
+        id,
+        company_id,
+        name: "Leverantören AB".to_string(),
+        org_number: None,
+        bankgiro: None,
+        default_expense_account_id: None,
+        payment_terms_days: None,
+    };
+    let created = books
+        .upsert_supplier(supplier(None, demo.company_id.clone()))
+        .unwrap();
+
+    let result = books.upsert_supplier(supplier(Some(created.id), other));
+    assert_eq!(result.unwrap_err(), "Supplier does not belong to company");
+}
+
+#[test]
+fn customer_payment_terms_are_capped() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let customer = |payment_terms_days| UpsertCustomerInput {
+        id: None,
+        company_id: demo.company_id.clone(),
+        name: "Kunden AB".to_string(),
+        org_number: None,
+        email: None,
+        address: None,
+        payment_terms_days: Some(payment_terms_days),
+    };
+
+    let result = books.upsert_customer(customer(i64::MAX));
+    assert_eq!(result.unwrap_err(), "Payment terms cannot exceed 365 days");
+    assert!(books.upsert_customer(customer(365)).is_ok());
+}
+
+#[test]
+fn supplier_payment_terms_are_capped() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let supplier = |payment_terms_days| UpsertSupplierInput {
+        id: None,
+        company_id: demo.company_id.clone(),
+        name: "Leverantören AB".to_string(),
+        org_number: None,
+        bankgiro: None,
+        default_expense_account_id: None,
+        payment_terms_days: Some(payment_terms_days),
+    };
+
+    let result = books.upsert_supplier(supplier(366));
+    assert_eq!(result.unwrap_err(), "Payment terms cannot exceed 365 days");
+    assert!(books.upsert_supplier(supplier(0)).is_ok());
+}
+
+#[test]
+fn invoice_lines_need_a_revenue_account_of_the_company() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    let other = other_company(&mut books);
+    let other_revenue = books
+        .upsert_account(UpsertAccountInput {
+            id: None,
+            company_id: other,
+            number: 3010,
+            name: "Försäljning".to_string(),
+            account_type: "Income".to_string(),
+            vat_code: None,
+            is_active: true,
+        })
+        .unwrap()
+        .id;
+    let customer = books
+        .upsert_customer(UpsertCustomerInput {
+            id: None,
+            company_id: demo.company_id.clone(),
+            name: "Kunden AB".to_string(),
+            org_number: None,
+            email: None,
+            address: None,
+            payment_terms_days: None,
+        })
+        .unwrap();
+    let invoice = |revenue_account_id: AccountId| CreateCustomerInvoiceInput {
+        company_id: demo.company_id.clone(),
+        customer_id: customer.id.clone(),
+        series_id: None,
+        invoice_date: date("2024-03-01"),
+        due_date: None,
+        lines: vec![CustomerInvoiceLineInput {
+            description: "Konsulttimmar".to_string(),
+            quantity: 2.0,
+            unit_price: Money::from_cents(50000),
+            vat_rate_percent: 25,
+            revenue_account_id,
+        }],
+    };
+
+    let result = books.create_customer_invoice(invoice(demo.account(1930)));
+    assert_eq!(
+        result.unwrap_err(),
+        "Row 1: Account 1930 is not a revenue account (3000-3999)"
+    );
+    let result = books.create_customer_invoice(invoice(other_revenue));
+    assert_eq!(result.unwrap_err(), "Row 1: Account not found");
+    let created = books
+        .create_customer_invoice(invoice(demo.account(3010)))
+        .unwrap();
+    assert_eq!(created.total, Money::from_cents(125000));
+}
+
+#[test]
+fn cash_method_year_end_is_reversed_the_next_day_and_booked_once() {
+    let mut conn = open_books();
+    let mut books = Books::new(&mut conn, USER, Path::new("documents"));
+    let demo = Demo::load(&mut books);
+    books
+        .set_accounting_method(SetAccountingMethodInput {
+            company_id: demo.company_id.clone(),
+            accounting_method: "cash".to_string(),
+        })
+        .unwrap();
+    let customer = books
+        .upsert_customer(UpsertCustomerInput {
+            id: None,
+            company_id: demo.company_id.clone(),
+            name: "Kunden AB".to_string(),
+            org_number: None,
+            email: None,
+            address: None,
+            payment_terms_days: None,
+        })
+        .unwrap();
+    books
+        .create_customer_invoice(CreateCustomerInvoiceInput {
+            company_id: demo.company_id.clone(),
+            customer_id: customer.id,
+            series_id: None,
+            invoice_date: date("2024-12-01"),
+            due_date: None,
+            lines: vec![CustomerInvoiceLineInput {
+                description: "Konsulttimmar".to_string(),
+                quantity: 2.0,
+                unit_price: Money::from_cents(50000),
+                vat_rate_percent: 25,
+                revenue_account_id: demo.account(3010),
+            }],
+        })
+        .unwrap();
+    let supplier = books
+        .upsert_supplier(UpsertSupplierInput {
+            id: None,
+            company_id: demo.company_id.clone(),
+            name: "Leverantören AB".to_string(),
+            org_number: None,
+            bankgiro: None,
+            default_expense_account_id: None,
+            payment_terms_days: None,
+        })
+        .unwrap();
+    books
+        .create_supplier_invoice(CreateSupplierInvoiceInput {
+            company_id: demo.company_id.clone(),
+            supplier_id: supplier.id,
+            series_id: None,
+            invoice_number: "L-17".to_string(),
+            invoice_date: date("2024-12-10"),
+            due_date: None,
+            payment_reference: None,
+            lines: vec![SupplierInvoiceLineInput {
+                description: None,
+                expense_account_id: Some(demo.account(4010)),
+                net: Money::from_cents(40000),
+                vat: Money::from_cents(10000),
+            }],
+        })
+        .unwrap();
+    let close = || CashYearEndInput {
+        company_id: demo.company_id.clone(),
+        year_end_date: date("2024-12-31"),
+        series_id: None,
+    };
+
+    let result = books.close_cash_method_year(close()).unwrap();
+    assert_eq!(result.booking.receivables, Money::from_cents(125000));
+    assert_eq!(result.booking.payables, Money::from_cents(50000));
+    assert_eq!(result.voucher.date, date("2024-12-31"));
+    assert_eq!(result.reversal.date, date("2025-01-01"));
+    // 1510 and 2440 are added to the chart of accounts by the booking.
+    let accounts = Demo::load(&mut books);
+    let booked = balances(&result.voucher);
+    assert_eq!(booked[&accounts.account(1510)], 125000);
+    assert_eq!(booked[&accounts.account(2440)], -50000);
+    let mut net = booked;
+    for (account_id, amount) in balances(&result.reversal) {
+        *net.entry(account_id).or_default() += amount;
+    }
+    assert!(net.values().all(|amount| *amount == 0), "{:?}", net);
+
+    let result = books.close_cash_method_year(close());
+    assert_eq!(result.unwrap_err(), "Year end 2024-12-31 is already booked");
+}
+
+/// Debit minus credit in cents per account.
+fn balances(voucher: &Voucher) -> HashMap<AccountId, i64> {
+    let mut balances = HashMap::new();
+    for row in voucher.rows.iter().flatten() {
+        *balances.entry(row.account_id.clone()).or_default() +=
+            row.debit.cents() - row.credit.cents();
+    }
+    balances
+}
@@ -0,0 +1,115 @@
+//! Parsing, formatting, rounding and overflow of amounts.
+
+use bokforing_core::models::CreateVoucherRowInput;
+use bokforing_core::money::Money;
+
+fn money(cents: i64) -> Money {
+    Money::from_cents(cents)
+}
+
+#[test]
+fn parses_decimal_and_swedish_notation() {
+    for (input, cents) in [
+        ("1234.50", 123450),
+        ("1234,5", 123450),
+        ("1 234,50", 123450),
+        ("1\u{a0}234,50", 123450),
+        ("-12", -1200),
+        ("0.07", 7),
+        (",50", 50),
+        (" 100 ", 10000),
+    ] {
+        assert_eq!(input.parse::<Money>(), Ok(money(cents)), "{:?}", input);
+    }
+    for input in [
+        "",
+        "-",
+        "1.234",
+        "12.3.4",
+        "1e3",
+        "abc",
+        "--5",
+        "99999999999999999999",
+    ] {
+        assert_eq!(
+            input.parse::<Money>(),
+            Err(format!("Invalid amount: {}", input)),
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn displays_as_decimal_and_swedish() {
+    assert_eq!(money(123450).to_string(), "1234.50");
+    assert_eq!(money(-5).to_string(), "-0.05");
+    assert_eq!(Money::ZERO.to_string(), "0.00");
+    assert_eq!(money(123456789).to_swedish(), "1 234 567,89");
+    assert_eq!(money(-100000).to_swedish(), "-1 000,00");
+    assert_eq!(money(99).to_swedish(), "0,99");
+}
+
+#[test]
+fn rounds_half_away_from_zero() {
+    assert_eq!(money(10).percent(25), Some(money(3)));
+    assert_eq!(money(-10).percent(25), Some(money(-3)));
+    assert_eq!(money(12345).percent(12), Some(money(1481)));
+    assert_eq!(money(12550).round_to_krona(), Some(money(12600)));
+    assert_eq!(money(12549).round_to_krona(), Some(money(12500)));
+    assert_eq!(money(-12550).round_to_krona(), Some(money(-12600)));
+    assert_eq!(money(100).checked_mul_ratio(1, 3), Some(money(33)));
+    assert_eq!(money(100).checked_mul_ratio(1, 0), None);
+}
+
+#[test]
+fn checked_arithmetic_reports_overflow() {
+    let max = money(i64::MAX);
+    assert_eq!(max.checked_add(money(1)), None);
+    assert_eq!(money(i64::MIN).checked_sub(money(1)), None);
+    assert_eq!(
+        Money::checked_sum([max, money(1)]),
+        Err("Amount is too large".to_string())
+    );
+    assert_eq!(Money::checked_sum([money(150), money(-50)]), Ok(money(100)));
+    assert_eq!(max.checked_mul_ratio(2, 1), None);
+    assert_eq!(max.percent(200), None);
+    assert_eq!(money(i64::MIN).checked_abs(), None);
+    assert_eq!(money(i64::MIN).checked_neg(), None);
+    assert_eq!(money(-5).checked_abs(), Some(money(5)));
+    assert_eq!(
+        money(100).checked_post(max, money(0)),
+        Err("Amount is too large".to_string())
+    );
+    assert_eq!(
+        money(100).checked_post(money(50), money(30)),
+        Ok(money(120))
+    );
+    assert_eq!(money(1050).checked_mul_quantity(2.5), Some(money(2625)));
+    assert_eq!(money(-3).checked_mul_quantity(0.5), Some(money(-2)));
+    assert_eq!(max.checked_mul_quantity(2.0), None);
+    assert_eq!(money(100).checked_mul_quantity(f64::NAN), None);
+    assert_eq!(money(100).checked_mul_quantity(1e300), None);
+}
+
+#[test]
+fn serializes_as_decimal_string() {
+    let row: CreateVoucherRowInput = serde_json::from_str(
+        r#"{"accountId": "a1b2c3d4-e5f6-4a7b-8c9d-0e1f2a3b4c5d", "debit": "125.00", "credit": "0"}"#,
+    )
+    .unwrap();
+    assert_eq!((row.debit, row.credit), (money(12500), Money::ZERO));
+    assert_eq!(
+        serde_json::to_string(&money(-12345)).unwrap(),
+        r#""-123.45""#
+    );
+
+    // Numbers are refused: a float cannot be trusted to hold öre exactly.
+    let result: Result<Money, _> = serde_json::from_str("125.5");
+    assert!(result.is_err());
+    let result: Result<Money, _> = serde_json::from_str(r#""12.345""#);
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Invalid amount: 12.345"));
+}
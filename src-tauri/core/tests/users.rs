@@ -0,0 +1,145 @@
+//! Users, roles, API tokens and the checks of callers without a login
+//! session.
+
+mod common;
+
+use std::path::Path;
+
+use bokforing_core::db::Role;
+use bokforing_core::models::{
+    ApiTokenIdInput, CreateApiTokenInput, CreateUserInput, SetUserRoleInput, User,
+};
+use bokforing_core::{api_tokens, users, Books};
+use common::open_books;
+use rusqlite::Connection;
+
+const PASSWORD: &str = "correct horse";
+
+fn user_input(username: &str, role: Option<&str>) -> CreateUserInput {
+    CreateUserInput {
+        username: username.to_string(),
+        display_name: username.to_string(),
+        password: PASSWORD.to_string(),
+        role: role.map(str::to_string),
+    }
+}
+
+/// The first admin, who then adds a user with each of `roles`.
+fn create_users(conn: &mut Connection, roles: &[(&str, &str)]) -> User {
+    let admin = users::create_first_admin(conn, &user_input("admin", None)).unwrap();
+    let mut books = Books::new(conn, "admin", Path::new("documents"));
+    for (username, role) in roles {
+        books.create_user(user_input(username, Some(role))).unwrap();
+    }
+    admin
+}
+
+#[test]
+fn roles_include_the_permissions_of_lower_roles() {
+    assert!(Role::Viewer < Role::Bookkeeper);
+    assert!(Role::Bookkeeper < Role::Approver);
+    assert!(Role::Approver < Role::Admin);
+
+    let mut conn = open_books();
+    create_users(&mut conn, &[("bo", "bookkeeper"), ("ann", "approver")]);
+    let bookkeeper = users::authenticate(&conn, "bo", PASSWORD).unwrap();
+    let approver = users::authenticate(&conn, "ann", PASSWORD).unwrap();
+    let admin = users::authenticate(&conn, "admin", PASSWORD).unwrap();
+
+    users::authorize_user(&conn, &bookkeeper, Role::Viewer, "list_companies").unwrap();
+    users::authorize_user(&conn, &bookkeeper, Role::Bookkeeper, "create_voucher").unwrap();
+    users::authorize_user(&conn, &approver, Role::Approver, "post_voucher").unwrap();
+    users::authorize_user(&conn, &admin, Role::Approver, "lock_period").unwrap();
+    let result = users::authorize_user(&conn, &bookkeeper, Role::Approver, "post_voucher");
+    assert_eq!(
+        result.unwrap_err(),
+        "Permission denied: post_voucher requires the approver role"
+    );
+    let result = users::authorize_user(&conn, &approver, Role::Admin, "create_user");
+    assert_eq!(
+        result.unwrap_err(),
+        "Permission denied: create_user requires the admin role"
+    );
+
+    let result = users::authenticate(&conn, "bo", "wrong password");
+    assert_eq!(result.unwrap_err(), "Invalid username or password");
+}
+
+#[test]
+fn denied_commands_are_recorded() {
+    let mut conn = open_books();
+    create_users(&mut conn, &[("vera", "viewer")]);
+    let viewer = users::authenticate(&conn, "vera", PASSWORD).unwrap();
+
+    users::authorize_user(&conn, &viewer, Role::Viewer, "report_trial_balance").unwrap();
+    users::authorize_user(&conn, &viewer, Role::Bookkeeper, "create_voucher").unwrap_err();
+
+    let mut books = Books::new(&mut conn, "admin", Path::new("documents"));
+    let denials = books.list_permission_denials().unwrap();
+    assert_eq!(denials.len(), 1);
+    assert_eq!(denials[0].username, "vera");
+    assert_eq!(denials[0].role, "viewer");
+    assert_eq!(denials[0].command, "create_voucher");
+    assert_eq!(denials[0].required_role, "bookkeeper");
+}
+
+#[test]
+fn last_admin_cannot_be_demoted() {
+    let mut conn = open_books();
+    let admin = create_users(&mut conn, &[]);
+    let mut books = Books::new(&mut conn, "admin", Path::new("documents"));
+
+    let result = books.set_user_role(SetUserRoleInput {
+        user_id: admin.id.clone(),
+        role: "approver".to_string(),
+    });
+    assert_eq!(result.unwrap_err(), "Cannot remove the last admin");
+
+    let second = books
+        .create_user(user_input("second", Some("admin")))
+        .unwrap();
+    let demoted = books
+        .set_user_role(SetUserRoleInput {
+            user_id: admin.id,
+            role: "viewer".to_string(),
+        })
+        .unwrap();
+    assert_eq!(demoted.role, "viewer");
+    let result = books.set_user_role(SetUserRoleInput {
+        user_id: second.id,
+        role: "bookkeeper".to_string(),
+    });
+    assert_eq!(result.unwrap_err(), "Cannot remove the last admin");
+}
+
+#[test]
+fn revoked_and_unknown_tokens_have_no_user() {
+    let mut conn = open_books();
+    create_users(&mut conn, &[("shop", "bookkeeper")]);
+    let mut books = Books::new(&mut conn, "admin", Path::new("documents"));
+    let created = books
+        .create_api_token(CreateApiTokenInput {
+            username: "shop".to_string(),
+            name: "Webshop".to_string(),
+        })
+        .unwrap();
+
+    let user = api_tokens::token_user(&conn, &created.secret)
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.username, "shop");
+    users::authorize_user(&conn, &user, Role::Bookkeeper, "create_voucher").unwrap();
+    let unknown = format!("{}x", created.secret);
+    assert!(api_tokens::token_user(&conn, &unknown).unwrap().is_none());
+    assert!(api_tokens::token_user(&conn, "").unwrap().is_none());
+
+    let mut books = Books::new(&mut conn, "admin", Path::new("documents"));
+    books
+        .revoke_api_token(ApiTokenIdInput {
+            token_id: created.token.id,
+        })
+        .unwrap();
+    assert!(api_tokens::token_user(&conn, &created.secret)
+        .unwrap()
+        .is_none());
+}
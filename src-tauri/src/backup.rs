@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use bokforing_core::backup;
+use bokforing_core::db::Role;
+use bokforing_core::models::{
+    BackupFile, BackupResult, BackupSettings, CreateBackupInput, RestoreBackupInput, RestoreResult,
+    UpdateBackupSettingsInput,
+};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::DbState;
+
+/// How often the scheduler checks whether an automatic backup is due.
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+#[tauri::command(async)]
+pub fn create_backup(
+    state: State<DbState>,
+    payload: CreateBackupInput,
+) -> Result<BackupResult, String> {
+    state.authorize(Role::Bookkeeper, "create_backup")?;
+    let directory = match payload.directory {
+        Some(directory) => PathBuf::from(directory),
+        None => backup::default_backup_dir(&state.database_path),
+    };
+    let target = backup::backup_path(&directory, false);
+    write_backup(&state, &target, payload.include_attachments)
+}
+
+#[tauri::command(async)]
+pub fn list_backups(state: State<DbState>) -> Result<Vec<BackupFile>, String> {
+    let settings = state.read_books(Role::Viewer, "list_backups", |books| {
+        books.get_backup_settings()
+    })?;
+    backup::list_backups(&backup_dir(&state, settings))
+}
+
+#[tauri::command(async)]
+pub fn get_backup_settings(state: State<DbState>) -> Result<BackupSettings, String> {
+    state.read_books(Role::Viewer, "get_backup_settings", |books| {
+        books.get_backup_settings()
+    })
+}
+
+#[tauri::command(async)]
+pub fn update_backup_settings(
+    state: State<DbState>,
+    payload: UpdateBackupSettingsInput,
+) -> Result<BackupSettings, String> {
+    state.books(Role::Admin, "update_backup_settings", |books| {
+        books.update_backup_settings(payload)
+    })
+}
+
+/// Replaces the database with the one in a backup archive. Everyone is
+/// logged out afterwards since the restored database has its own users.
+#[tauri::command(async)]
+pub fn restore_backup(
+    state: State<DbState>,
+    payload: RestoreBackupInput,
+) -> Result<RestoreResult, String> {
+    state.authorize(Role::Admin, "restore_backup")?;
+    let current = state.passphrase.lock().map_err(|_| "Lock error")?.clone();
+    let (restored, passphrase) = state.replace_database(|conn| {
+        backup::restore_backup(
+            conn,
+            &state.database_path,
+            &state.documents_dir,
+            payload,
+            current,
+        )
+    })?;
+    *state.passphrase.lock().map_err(|_| "Lock error")? = passphrase;
+    *state.session.lock().map_err(|_| "Lock error")? = None;
+    Ok(restored)
+}
+
+/// Starts the thread that makes the automatic backups configured in
+/// `backup_settings`. It does nothing until the database is open, so an
+/// encrypted database is backed up once it has been unlocked.
+pub fn spawn_scheduler(app: AppHandle) {
+    thread::spawn(move || loop {
+        if let Some(state) = app.try_state::<DbState>() {
+            // Failures are stored in `backup_settings.last_error` and retried
+            // at the next check.
+            let _ = run_scheduled_backup(&state);
+        }
+        thread::sleep(SCHEDULER_INTERVAL);
+    });
+}
+
+fn run_scheduled_backup(state: &DbState) -> Result<(), String> {
+    let settings = state.reader(|conn| backup::fetch_settings(conn))?;
+    if !backup::backup_due(&settings)? {
+        return Ok(());
+    }
+
+    let retention_count = settings.retention_count;
+    let include_attachments = settings.include_attachments;
+    let directory = backup_dir(state, settings);
+    let target = backup::backup_path(&directory, true);
+    let outcome = write_backup(state, &target, include_attachments).and_then(|result| {
+        backup::prune_backups(&directory, retention_count)?;
+        Ok(result)
+    });
+
+    let conn = state.writer.lock().map_err(|_| "Lock error")?;
+    backup::record_backup_outcome(&conn, &outcome)?;
+    outcome.map(|_| ())
+}
+
+/// Takes the snapshot on a pooled reader and packs the archive without
+/// holding any lock, so commands keep running during a long backup.
+fn write_backup(
+    state: &DbState,
+    target: &Path,
+    include_attachments: bool,
+) -> Result<BackupResult, String> {
+    let snapshot = state.reader(|conn| {
+        let passphrase = state.passphrase.lock().map_err(|_| "Lock error")?.clone();
+        backup::snapshot_database(conn, passphrase.as_deref(), target)
+    })?;
+    backup::write_backup_archive(snapshot, &state.documents_dir, target, include_attachments)
+}
+
+fn backup_dir(state: &DbState, settings: BackupSettings) -> PathBuf {
+    settings
+        .directory
+        .map(PathBuf::from)
+        .unwrap_or_else(|| backup::default_backup_dir(&state.database_path))
+}
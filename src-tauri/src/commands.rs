@@ -0,0 +1,684 @@
+//! Tauri commands for the bookkeeping itself, each a permission check in
+//! front of the matching [`Books`](bokforing_core::Books) method.
+
+use bokforing_core::db::Role;
+use bokforing_core::models::{
+    Account, AddAttachmentInput, AgingReport, AgingReportInput, ApproveVoucherInput,
+    ArchiveExportResult, Attachment, BankImportResult, BankMatchSuggestions, BankReconciliation,
+    BankReconciliationReport, BankRule, BankTransaction, BankTransactionIdInput,
+    CashYearEndBooking, CashYearEndInput, CashYearEndResult, Company, CompanyIdInput, Counterparty,
+    CounterpartyReport, CounterpartyReportInput, CreateCompanyInput, CreateCorrectionInput,
+    CreateCustomerInvoiceInput, CreateSupplierInvoiceInput, CreateVoucherFromBankRuleInput,
+    CreateVoucherFromInboxInput, CreateVoucherInput, Customer, CustomerInvoice, ExportArchiveInput,
+    ExportInput, ImportBankStatementInput, ImportInboxDocumentsInput, InboxDocument,
+    InstantiateTemplateInput, InvoiceIdInput, LedgerRow, ListBankTransactionsInput,
+    ListInboxDocumentsInput, ListInvoicesInput, ListVouchersInput, LockPeriodInput,
+    MatchBankTransactionInput, PendingApproval, PeriodLock, PeriodLockIdInput, ReconcileBankInput,
+    RecurringRunResult, RecurringSchedule, RegisterPaymentInput, RejectVoucherInput,
+    ReportLedgerInput, ReportVoucherListInput, RunRecurringSchedulesInput,
+    SearchCounterpartiesInput, SetAccountingMethodInput, SetApprovalRequirementInput,
+    SetZeroRowAllowanceInput, SignOffReconciliationInput, Supplier, SupplierInvoice,
+    UnlinkedDocumentItem, UpsertAccountInput, UpsertBankRuleInput, UpsertCounterpartyInput,
+    UpsertCustomerInput, UpsertRecurringScheduleInput, UpsertSupplierInput,
+    UpsertVoucherTemplateInput, Voucher, VoucherApproval, VoucherIdInput, VoucherListItem,
+    VoucherSeries, VoucherTemplate,
+};
+use tauri::State;
+
+use crate::db::DbState;
+
+#[tauri::command(async)]
+pub fn list_companies(state: State<DbState>) -> Result<Vec<Company>, String> {
+    state.read_books(Role::Viewer, "list_companies", |books| {
+        books.list_companies()
+    })
+}
+
+#[tauri::command(async)]
+pub fn create_company(
+    state: State<DbState>,
+    payload: CreateCompanyInput,
+) -> Result<Company, String> {
+    state.books(Role::Admin, "create_company", |books| {
+        books.create_company(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn set_accounting_method(
+    state: State<DbState>,
+    payload: SetAccountingMethodInput,
+) -> Result<Company, String> {
+    state.books(Role::Admin, "set_accounting_method", |books| {
+        books.set_accounting_method(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn set_approval_requirement(
+    state: State<DbState>,
+    payload: SetApprovalRequirementInput,
+) -> Result<Company, String> {
+    state.books(Role::Admin, "set_approval_requirement", |books| {
+        books.set_approval_requirement(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn set_zero_row_allowance(
+    state: State<DbState>,
+    payload: SetZeroRowAllowanceInput,
+) -> Result<Company, String> {
+    state.books(Role::Admin, "set_zero_row_allowance", |books| {
+        books.set_zero_row_allowance(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_accounts(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<Account>, String> {
+    state.read_books(Role::Viewer, "list_accounts", |books| {
+        books.list_accounts(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn upsert_account(
+    state: State<DbState>,
+    payload: UpsertAccountInput,
+) -> Result<Account, String> {
+    state.books(Role::Admin, "upsert_account", |books| {
+        books.upsert_account(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_voucher_series(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<VoucherSeries>, String> {
+    state.read_books(Role::Viewer, "list_voucher_series", |books| {
+        books.list_voucher_series(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_vouchers(
+    state: State<DbState>,
+    payload: ListVouchersInput,
+) -> Result<Vec<Voucher>, String> {
+    state.read_books(Role::Viewer, "list_vouchers", |books| {
+        books.list_vouchers(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn get_voucher(state: State<DbState>, payload: VoucherIdInput) -> Result<Voucher, String> {
+    state.read_books(Role::Viewer, "get_voucher", |books| {
+        books.get_voucher(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn create_voucher(
+    state: State<DbState>,
+    payload: CreateVoucherInput,
+) -> Result<Voucher, String> {
+    state.books(Role::Bookkeeper, "create_voucher", |books| {
+        books.create_voucher(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn post_voucher(state: State<DbState>, payload: VoucherIdInput) -> Result<Voucher, String> {
+    state.books(Role::Approver, "post_voucher", |books| {
+        books.post_voucher(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_pending_approvals(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<PendingApproval>, String> {
+    state.read_books(Role::Viewer, "list_pending_approvals", |books| {
+        books.list_pending_approvals(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn approve_voucher(
+    state: State<DbState>,
+    payload: ApproveVoucherInput,
+) -> Result<VoucherApproval, String> {
+    state.books(Role::Approver, "approve_voucher", |books| {
+        books.approve_voucher(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn reject_voucher(
+    state: State<DbState>,
+    payload: RejectVoucherInput,
+) -> Result<VoucherApproval, String> {
+    state.books(Role::Approver, "reject_voucher", |books| {
+        books.reject_voucher(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn get_voucher_approval(
+    state: State<DbState>,
+    payload: VoucherIdInput,
+) -> Result<Option<VoucherApproval>, String> {
+    state.read_books(Role::Viewer, "get_voucher_approval", |books| {
+        books.get_voucher_approval(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn create_correction_voucher(
+    state: State<DbState>,
+    payload: CreateCorrectionInput,
+) -> Result<Voucher, String> {
+    state.books(Role::Bookkeeper, "create_correction_voucher", |books| {
+        books.create_correction_voucher(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_attachments(
+    state: State<DbState>,
+    payload: VoucherIdInput,
+) -> Result<Vec<Attachment>, String> {
+    state.read_books(Role::Viewer, "list_attachments", |books| {
+        books.list_attachments(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn add_attachment_to_voucher(
+    state: State<DbState>,
+    payload: AddAttachmentInput,
+) -> Result<Attachment, String> {
+    state.books(Role::Bookkeeper, "add_attachment_to_voucher", |books| {
+        books.add_attachment_to_voucher(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn import_inbox_documents(
+    state: State<DbState>,
+    payload: ImportInboxDocumentsInput,
+) -> Result<Vec<InboxDocument>, String> {
+    state.books(Role::Bookkeeper, "import_inbox_documents", |books| {
+        books.import_inbox_documents(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_inbox_documents(
+    state: State<DbState>,
+    payload: ListInboxDocumentsInput,
+) -> Result<Vec<InboxDocument>, String> {
+    state.read_books(Role::Viewer, "list_inbox_documents", |books| {
+        books.list_inbox_documents(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn create_voucher_from_inbox(
+    state: State<DbState>,
+    payload: CreateVoucherFromInboxInput,
+) -> Result<Voucher, String> {
+    state.books(Role::Bookkeeper, "create_voucher_from_inbox", |books| {
+        books.create_voucher_from_inbox(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn report_unlinked_documents(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<UnlinkedDocumentItem>, String> {
+    state.read_books(Role::Viewer, "report_unlinked_documents", |books| {
+        books.report_unlinked_documents(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn import_bank_statement(
+    state: State<DbState>,
+    payload: ImportBankStatementInput,
+) -> Result<BankImportResult, String> {
+    state.books(Role::Bookkeeper, "import_bank_statement", |books| {
+        books.import_bank_statement(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_bank_transactions(
+    state: State<DbState>,
+    payload: ListBankTransactionsInput,
+) -> Result<Vec<BankTransaction>, String> {
+    state.read_books(Role::Viewer, "list_bank_transactions", |books| {
+        books.list_bank_transactions(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn suggest_bank_matches(
+    state: State<DbState>,
+    payload: BankTransactionIdInput,
+) -> Result<BankMatchSuggestions, String> {
+    state.read_books(Role::Viewer, "suggest_bank_matches", |books| {
+        books.suggest_bank_matches(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn match_bank_transaction(
+    state: State<DbState>,
+    payload: MatchBankTransactionInput,
+) -> Result<BankTransaction, String> {
+    state.books(Role::Bookkeeper, "match_bank_transaction", |books| {
+        books.match_bank_transaction(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_bank_rules(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<BankRule>, String> {
+    state.read_books(Role::Viewer, "list_bank_rules", |books| {
+        books.list_bank_rules(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn upsert_bank_rule(
+    state: State<DbState>,
+    payload: UpsertBankRuleInput,
+) -> Result<BankRule, String> {
+    state.books(Role::Bookkeeper, "upsert_bank_rule", |books| {
+        books.upsert_bank_rule(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn create_voucher_from_bank_rule(
+    state: State<DbState>,
+    payload: CreateVoucherFromBankRuleInput,
+) -> Result<Voucher, String> {
+    state.books(Role::Bookkeeper, "create_voucher_from_bank_rule", |books| {
+        books.create_voucher_from_bank_rule(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn report_bank_reconciliation(
+    state: State<DbState>,
+    payload: ReconcileBankInput,
+) -> Result<BankReconciliationReport, String> {
+    state.read_books(Role::Viewer, "report_bank_reconciliation", |books| {
+        books.report_bank_reconciliation(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn sign_off_bank_reconciliation(
+    state: State<DbState>,
+    payload: SignOffReconciliationInput,
+) -> Result<BankReconciliation, String> {
+    state.books(Role::Approver, "sign_off_bank_reconciliation", |books| {
+        books.sign_off_bank_reconciliation(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_bank_reconciliations(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<BankReconciliation>, String> {
+    state.read_books(Role::Viewer, "list_bank_reconciliations", |books| {
+        books.list_bank_reconciliations(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_voucher_templates(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<VoucherTemplate>, String> {
+    state.read_books(Role::Viewer, "list_voucher_templates", |books| {
+        books.list_voucher_templates(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn upsert_voucher_template(
+    state: State<DbState>,
+    payload: UpsertVoucherTemplateInput,
+) -> Result<VoucherTemplate, String> {
+    state.books(Role::Bookkeeper, "upsert_voucher_template", |books| {
+        books.upsert_voucher_template(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn instantiate_voucher_template(
+    state: State<DbState>,
+    payload: InstantiateTemplateInput,
+) -> Result<Voucher, String> {
+    state.books(Role::Bookkeeper, "instantiate_voucher_template", |books| {
+        books.instantiate_voucher_template(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_recurring_schedules(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<RecurringSchedule>, String> {
+    state.read_books(Role::Viewer, "list_recurring_schedules", |books| {
+        books.list_recurring_schedules(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn upsert_recurring_schedule(
+    state: State<DbState>,
+    payload: UpsertRecurringScheduleInput,
+) -> Result<RecurringSchedule, String> {
+    state.books(Role::Bookkeeper, "upsert_recurring_schedule", |books| {
+        books.upsert_recurring_schedule(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn run_recurring_schedules(
+    state: State<DbState>,
+    payload: RunRecurringSchedulesInput,
+) -> Result<RecurringRunResult, String> {
+    state.books(Role::Bookkeeper, "run_recurring_schedules", |books| {
+        books.run_recurring_schedules(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_customers(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<Customer>, String> {
+    state.read_books(Role::Viewer, "list_customers", |books| {
+        books.list_customers(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn upsert_customer(
+    state: State<DbState>,
+    payload: UpsertCustomerInput,
+) -> Result<Customer, String> {
+    state.books(Role::Bookkeeper, "upsert_customer", |books| {
+        books.upsert_customer(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn create_customer_invoice(
+    state: State<DbState>,
+    payload: CreateCustomerInvoiceInput,
+) -> Result<CustomerInvoice, String> {
+    state.books(Role::Bookkeeper, "create_customer_invoice", |books| {
+        books.create_customer_invoice(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_customer_invoices(
+    state: State<DbState>,
+    payload: ListInvoicesInput,
+) -> Result<Vec<CustomerInvoice>, String> {
+    state.read_books(Role::Viewer, "list_customer_invoices", |books| {
+        books.list_customer_invoices(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn get_customer_invoice(
+    state: State<DbState>,
+    payload: InvoiceIdInput,
+) -> Result<CustomerInvoice, String> {
+    state.read_books(Role::Viewer, "get_customer_invoice", |books| {
+        books.get_customer_invoice(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn register_customer_payment(
+    state: State<DbState>,
+    payload: RegisterPaymentInput,
+) -> Result<CustomerInvoice, String> {
+    state.books(Role::Bookkeeper, "register_customer_payment", |books| {
+        books.register_customer_payment(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn report_accounts_receivable(
+    state: State<DbState>,
+    payload: AgingReportInput,
+) -> Result<AgingReport, String> {
+    state.read_books(Role::Viewer, "report_accounts_receivable", |books| {
+        books.report_accounts_receivable(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_suppliers(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<Supplier>, String> {
+    state.read_books(Role::Viewer, "list_suppliers", |books| {
+        books.list_suppliers(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn upsert_supplier(
+    state: State<DbState>,
+    payload: UpsertSupplierInput,
+) -> Result<Supplier, String> {
+    state.books(Role::Bookkeeper, "upsert_supplier", |books| {
+        books.upsert_supplier(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn create_supplier_invoice(
+    state: State<DbState>,
+    payload: CreateSupplierInvoiceInput,
+) -> Result<SupplierInvoice, String> {
+    state.books(Role::Bookkeeper, "create_supplier_invoice", |books| {
+        books.create_supplier_invoice(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_supplier_invoices(
+    state: State<DbState>,
+    payload: ListInvoicesInput,
+) -> Result<Vec<SupplierInvoice>, String> {
+    state.read_books(Role::Viewer, "list_supplier_invoices", |books| {
+        books.list_supplier_invoices(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn get_supplier_invoice(
+    state: State<DbState>,
+    payload: InvoiceIdInput,
+) -> Result<SupplierInvoice, String> {
+    state.read_books(Role::Viewer, "get_supplier_invoice", |books| {
+        books.get_supplier_invoice(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn register_supplier_payment(
+    state: State<DbState>,
+    payload: RegisterPaymentInput,
+) -> Result<SupplierInvoice, String> {
+    state.books(Role::Bookkeeper, "register_supplier_payment", |books| {
+        books.register_supplier_payment(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn report_accounts_payable(
+    state: State<DbState>,
+    payload: AgingReportInput,
+) -> Result<AgingReport, String> {
+    state.read_books(Role::Viewer, "report_accounts_payable", |books| {
+        books.report_accounts_payable(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn close_cash_method_year(
+    state: State<DbState>,
+    payload: CashYearEndInput,
+) -> Result<CashYearEndResult, String> {
+    state.books(Role::Approver, "close_cash_method_year", |books| {
+        books.close_cash_method_year(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_cash_year_end_bookings(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<CashYearEndBooking>, String> {
+    state.read_books(Role::Viewer, "list_cash_year_end_bookings", |books| {
+        books.list_cash_year_end_bookings(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_counterparties(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<Counterparty>, String> {
+    state.read_books(Role::Viewer, "list_counterparties", |books| {
+        books.list_counterparties(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn upsert_counterparty(
+    state: State<DbState>,
+    payload: UpsertCounterpartyInput,
+) -> Result<Counterparty, String> {
+    state.books(Role::Bookkeeper, "upsert_counterparty", |books| {
+        books.upsert_counterparty(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn search_counterparties(
+    state: State<DbState>,
+    payload: SearchCounterpartiesInput,
+) -> Result<Vec<Counterparty>, String> {
+    state.read_books(Role::Viewer, "search_counterparties", |books| {
+        books.search_counterparties(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn report_vouchers_by_counterparty(
+    state: State<DbState>,
+    payload: CounterpartyReportInput,
+) -> Result<CounterpartyReport, String> {
+    state.read_books(Role::Viewer, "report_vouchers_by_counterparty", |books| {
+        books.report_vouchers_by_counterparty(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_period_locks(
+    state: State<DbState>,
+    payload: CompanyIdInput,
+) -> Result<Vec<PeriodLock>, String> {
+    state.read_books(Role::Viewer, "list_period_locks", |books| {
+        books.list_period_locks(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn lock_period(state: State<DbState>, payload: LockPeriodInput) -> Result<PeriodLock, String> {
+    state.books(Role::Approver, "lock_period", |books| {
+        books.lock_period(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn unlock_period(state: State<DbState>, payload: PeriodLockIdInput) -> Result<(), String> {
+    state.books(Role::Admin, "unlock_period", |books| {
+        books.unlock_period(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn report_voucher_list(
+    state: State<DbState>,
+    payload: ReportVoucherListInput,
+) -> Result<Vec<VoucherListItem>, String> {
+    state.read_books(Role::Viewer, "report_voucher_list", |books| {
+        books.report_voucher_list(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn report_ledger_for_account(
+    state: State<DbState>,
+    payload: ReportLedgerInput,
+) -> Result<Vec<LedgerRow>, String> {
+    state.read_books(Role::Viewer, "report_ledger_for_account", |books| {
+        books.report_ledger_for_account(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn export_csv(state: State<DbState>, payload: ExportInput) -> Result<String, String> {
+    state.read_books(Role::Viewer, "export_csv", |books| {
+        books.export_csv(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn export_sie_stub(state: State<DbState>, payload: ExportInput) -> Result<String, String> {
+    state.read_books(Role::Viewer, "export_sie_stub", |books| {
+        books.export_sie_stub(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn export_archive(
+    state: State<DbState>,
+    payload: ExportArchiveInput,
+) -> Result<ArchiveExportResult, String> {
+    // Written on a reader so that a large archive does not hold the writer;
+    // only the audit entry needs it.
+    let company_id = payload.company_id.clone();
+    let archive = state.read_books(Role::Viewer, "export_archive", |books| {
+        books.write_archive(payload)
+    })?;
+    state.books(Role::Viewer, "export_archive", |books| {
+        books.record_archive_export(&company_id, &archive)
+    })?;
+    Ok(archive)
+}
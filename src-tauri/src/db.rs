@@ -1,224 +1,163 @@
 use std::fs;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
 
-use chrono::Utc;
-use rusqlite::{params, Connection, OptionalExtension};
-use tauri::AppHandle;
-use thiserror::Error;
+use bokforing_core::db::{
+    documents_dir, is_encrypted, open_database, open_reader, DbError, Role, DATABASE_FILE,
+};
+use bokforing_core::users::{record_permission_denial, Session};
+use bokforing_core::Books;
+use rusqlite::Connection;
+use tauri::{AppHandle, Manager};
 
-const DEFAULT_USER: &str = "local";
+use crate::map_error;
 
-const MIGRATIONS: &str = r#"
-CREATE TABLE IF NOT EXISTS companies (
-  id TEXT PRIMARY KEY,
-  name TEXT NOT NULL,
-  org_number TEXT,
-  fiscal_year_start TEXT NOT NULL,
-  fiscal_year_end TEXT NOT NULL,
-  created_at TEXT NOT NULL
-);
+/// Idle read-only connections kept for reuse. More are opened when several
+/// commands read at once.
+const MAX_IDLE_READERS: usize = 4;
 
-CREATE TABLE IF NOT EXISTS accounts (
-  id TEXT PRIMARY KEY,
-  company_id TEXT NOT NULL,
-  number INTEGER NOT NULL,
-  name TEXT NOT NULL,
-  type TEXT NOT NULL,
-  vat_code TEXT,
-  is_active INTEGER NOT NULL,
-  created_at TEXT NOT NULL,
-  FOREIGN KEY(company_id) REFERENCES companies(id)
-);
-
-CREATE TABLE IF NOT EXISTS voucher_series (
-  id TEXT PRIMARY KEY,
-  company_id TEXT NOT NULL,
-  code TEXT NOT NULL,
-  description TEXT NOT NULL,
-  next_number INTEGER NOT NULL,
-  FOREIGN KEY(company_id) REFERENCES companies(id)
-);
-
-CREATE TABLE IF NOT EXISTS vouchers (
-  id TEXT PRIMARY KEY,
-  company_id TEXT NOT NULL,
-  series_id TEXT NOT NULL,
-  voucher_number INTEGER NOT NULL,
-  date TEXT NOT NULL,
-  description TEXT NOT NULL,
-  counterparty TEXT,
-  created_at TEXT NOT NULL,
-  created_by TEXT NOT NULL,
-  posted_at TEXT,
-  corrected_voucher_id TEXT,
-  FOREIGN KEY(company_id) REFERENCES companies(id),
-  FOREIGN KEY(series_id) REFERENCES voucher_series(id)
-);
+/// The database is opened once for writing and any number of times for
+/// reading. Writes are serialized on `writer`; reads run on their own
+/// connections, so a long export does not hold up other commands.
+pub struct DbState {
+    pub writer: Mutex<Connection>,
+    pub readers: ReaderPool,
+    pub database_path: PathBuf,
+    /// SQLCipher key of the open database, needed to write encrypted backups.
+    pub passphrase: Mutex<Option<String>>,
+    /// Directory where imported documents are stored next to the database.
+    pub documents_dir: PathBuf,
+    /// The logged-in user, if any.
+    pub session: Mutex<Option<Session>>,
+}
 
-CREATE TABLE IF NOT EXISTS voucher_rows (
-  id TEXT PRIMARY KEY,
-  voucher_id TEXT NOT NULL,
-  account_id TEXT NOT NULL,
-  description TEXT,
-  debit_cents INTEGER NOT NULL,
-  credit_cents INTEGER NOT NULL,
-  vat_code TEXT,
-  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
-);
+impl DbState {
+    /// Checks that the logged-in user has at least `required` and returns the
+    /// username, recorded as `created_by`, `locked_by` etc. on everything the
+    /// user writes. Denials are logged in `permission_denials`.
+    ///
+    /// Must be called before locking the connection.
+    pub fn authorize(&self, required: Role, command: &str) -> Result<String, String> {
+        let session = self
+            .session
+            .lock()
+            .map_err(|_| "Lock error")?
+            .clone()
+            .ok_or_else(|| "Not logged in".to_string())?;
+        if session.role >= required {
+            return Ok(session.username);
+        }
+
+        let conn = self.writer.lock().map_err(|_| "Lock error")?;
+        record_permission_denial(&conn, &session.username, session.role, command, required)?;
+        Err(format!(
+            "Permission denied: {} requires the {} role",
+            command,
+            required.as_str()
+        ))
+    }
 
-CREATE TABLE IF NOT EXISTS attachments (
-  id TEXT PRIMARY KEY,
-  voucher_id TEXT NOT NULL,
-  ref_type TEXT NOT NULL,
-  ref_value TEXT NOT NULL,
-  note TEXT,
-  created_at TEXT NOT NULL,
-  FOREIGN KEY(voucher_id) REFERENCES vouchers(id)
-);
+    /// Runs `operation` on the books as the logged-in user, who must have at
+    /// least the `required` role.
+    pub fn books<T>(
+        &self,
+        required: Role,
+        command: &str,
+        operation: impl FnOnce(&mut Books<'_>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let user = self.authorize(required, command)?;
+        let mut conn = self.writer.lock().map_err(|_| "Lock error")?;
+        operation(&mut Books::new(&mut conn, &user, &self.documents_dir))
+    }
 
-CREATE TABLE IF NOT EXISTS period_locks (
-  id TEXT PRIMARY KEY,
-  company_id TEXT NOT NULL,
-  period_start TEXT NOT NULL,
-  period_end TEXT NOT NULL,
-  locked_at TEXT NOT NULL,
-  locked_by TEXT NOT NULL,
-  FOREIGN KEY(company_id) REFERENCES companies(id)
-);
+    /// Like [`books`](Self::books) but on a read-only connection, for
+    /// commands that only query. Any write fails.
+    pub fn read_books<T>(
+        &self,
+        required: Role,
+        command: &str,
+        operation: impl FnOnce(&mut Books<'_>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let user = self.authorize(required, command)?;
+        self.reader(|conn| operation(&mut Books::new(conn, &user, &self.documents_dir)))
+    }
 
-CREATE TABLE IF NOT EXISTS audit_log (
-  id TEXT PRIMARY KEY,
-  company_id TEXT NOT NULL,
-  entity_type TEXT NOT NULL,
-  entity_id TEXT NOT NULL,
-  action TEXT NOT NULL,
-  payload_json TEXT NOT NULL,
-  created_at TEXT NOT NULL,
-  created_by TEXT NOT NULL,
-  FOREIGN KEY(company_id) REFERENCES companies(id)
-);
-"#;
+    /// Runs `operation` on a read-only connection from the pool without
+    /// checking the session, for work the app does on its own behalf.
+    pub fn reader<T>(
+        &self,
+        operation: impl FnOnce(&mut Connection) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let _gate = self.readers.gate.read().map_err(|_| "Lock error")?;
+        let idle = self.readers.idle.lock().map_err(|_| "Lock error")?.pop();
+        let mut conn = match idle {
+            Some(conn) => conn,
+            None => {
+                let passphrase = self.passphrase.lock().map_err(|_| "Lock error")?.clone();
+                open_reader(&self.database_path, passphrase.as_deref()).map_err(map_error)?
+            }
+        };
+        let result = operation(&mut conn);
+        let mut idle = self.readers.idle.lock().map_err(|_| "Lock error")?;
+        if idle.len() < MAX_IDLE_READERS {
+            idle.push(conn);
+        }
+        result
+    }
 
-#[derive(Debug, Error)]
-pub enum DbError {
-    #[error("Database error: {0}")]
-    Sqlite(#[from] rusqlite::Error),
-    #[error("IO error: {0}")]
-    Io(#[from] std::io::Error),
-    #[error("Invalid operation: {0}")]
-    Invalid(String),
+    /// Runs `operation`, which replaces or rekeys the database file, on the
+    /// writer once every reader has finished. The idle readers are closed
+    /// first and new ones are opened on the new file afterwards.
+    pub fn replace_database<T>(
+        &self,
+        operation: impl FnOnce(&mut Connection) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut conn = self.writer.lock().map_err(|_| "Lock error")?;
+        let _gate = self.readers.gate.write().map_err(|_| "Lock error")?;
+        self.readers.idle.lock().map_err(|_| "Lock error")?.clear();
+        operation(&mut conn)
+    }
 }
 
-pub struct DbState {
-    pub connection: Mutex<Connection>,
+#[derive(Default)]
+pub struct ReaderPool {
+    /// Held shared by every read and exclusively by
+    /// [`DbState::replace_database`]: the WAL is only checkpointed into the
+    /// file when the last connection closes, so no reader may be open then.
+    gate: RwLock<()>,
+    idle: Mutex<Vec<Connection>>,
 }
 
-pub fn init_db(app: &AppHandle) -> Result<DbState, DbError> {
-    let db_path = database_path(app)?;
-    if let Some(parent) = db_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    let conn = Connection::open(db_path)?;
-    conn.execute_batch(MIGRATIONS)?;
-    conn.pragma_update(None, "journal_mode", "WAL")?;
-    seed_if_needed(&conn)?;
+/// Opens the database in the app data directory. `passphrase` is only used,
+/// and required, when the file is encrypted.
+pub fn init_db(app: &AppHandle, passphrase: Option<&str>) -> Result<DbState, DbError> {
+    let base_dir = app_data_dir(app)?;
+    fs::create_dir_all(&base_dir)?;
+    let database_path = base_dir.join(DATABASE_FILE);
+    let documents_dir = documents_dir(&database_path);
+    fs::create_dir_all(&documents_dir)?;
+    let passphrase = if is_encrypted(&database_path)? {
+        Some(passphrase.ok_or(DbError::PassphraseRequired)?)
+    } else {
+        None
+    };
+    let conn = open_database(&database_path, passphrase)?;
     Ok(DbState {
-        connection: Mutex::new(conn),
+        writer: Mutex::new(conn),
+        readers: ReaderPool::default(),
+        database_path,
+        passphrase: Mutex::new(passphrase.map(str::to_string)),
+        documents_dir,
+        session: Mutex::new(None),
     })
 }
 
-fn database_path(app: &AppHandle) -> Result<PathBuf, DbError> {
-    let base_dir = app.path().app_data_dir()?;
-    Ok(base_dir.join("bokforing.sqlite"))
-}
-
-fn seed_if_needed(conn: &Connection) -> Result<(), DbError> {
-    let count: i64 = conn
-        .query_row("SELECT COUNT(*) FROM companies", [], |row| row.get(0))?;
-    if count > 0 {
-        return Ok(());
-    }
-
-    let now = Utc::now().to_rfc3339();
-    let company_id = uuid::Uuid::new_v4().to_string();
-    let tx = conn.transaction()?;
-    tx.execute(
-        "INSERT INTO companies (id, name, org_number, fiscal_year_start, fiscal_year_end, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![
-            company_id,
-            "Demo AB",
-            Option::<String>::None,
-            "2024-01-01",
-            "2024-12-31",
-            now
-        ],
-    )?;
-
-    tx.execute(
-        "INSERT INTO voucher_series (id, company_id, code, description, next_number)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![
-            uuid::Uuid::new_v4().to_string(),
-            company_id,
-            "A",
-            "Main series",
-            1
-        ],
-    )?;
-
-    let accounts = vec![
-        ("1930", "Bankkonto", "Asset"),
-        ("3010", "Försäljning", "Income"),
-        ("4010", "Varuinköp", "Expense"),
-        ("2641", "Ingående moms", "Asset"),
-        ("2611", "Utgående moms", "Liability"),
-    ];
-
-    for (number, name, account_type) in accounts {
-        tx.execute(
-            "INSERT INTO accounts (id, company_id, number, name, type, vat_code, is_active, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![
-                uuid::Uuid::new_v4().to_string(),
-                company_id,
-                number.parse::<i64>().unwrap_or_default(),
-                name,
-                account_type,
-                Option::<String>::None,
-                1,
-                now
-            ],
-        )?;
-    }
-
-    tx.execute(
-        "INSERT INTO audit_log (id, company_id, entity_type, entity_id, action, payload_json, created_at, created_by)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![
-            uuid::Uuid::new_v4().to_string(),
-            company_id,
-            "company",
-            company_id,
-            "seed",
-            "{}",
-            now,
-            DEFAULT_USER
-        ],
-    )?;
-
-    tx.commit()?;
-    Ok(())
+pub fn database_path(app: &AppHandle) -> Result<PathBuf, DbError> {
+    Ok(app_data_dir(app)?.join(DATABASE_FILE))
 }
 
-pub fn is_period_locked(conn: &Connection, company_id: &str, date: &str) -> Result<bool, DbError> {
-    let locked: Option<i64> = conn
-        .query_row(
-            "SELECT 1 FROM period_locks WHERE company_id = ?1 AND ?2 BETWEEN period_start AND period_end LIMIT 1",
-            params![company_id, date],
-            |row| row.get(0),
-        )
-        .optional()?;
-    Ok(locked.is_some())
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, DbError> {
+    app.path()
+        .app_data_dir()
+        .map_err(|err| DbError::Invalid(err.to_string()))
 }
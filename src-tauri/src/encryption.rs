@@ -0,0 +1,51 @@
+use bokforing_core::db::{is_encrypted, Role};
+use bokforing_core::encryption;
+use bokforing_core::models::{ChangeDatabasePassphraseInput, DatabaseStatus, UnlockDatabaseInput};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::{self, DbState};
+use crate::map_error;
+
+/// Whether the database is encrypted and, if so, whether it has been unlocked
+/// yet. Commands that use the database fail until it is unlocked.
+#[tauri::command]
+pub fn database_status(app: AppHandle) -> Result<DatabaseStatus, String> {
+    let path = db::database_path(&app).map_err(map_error)?;
+    Ok(DatabaseStatus {
+        encrypted: is_encrypted(&path).map_err(map_error)?,
+        unlocked: app.try_state::<DbState>().is_some(),
+    })
+}
+
+#[tauri::command(async)]
+pub fn unlock_database(
+    app: AppHandle,
+    payload: UnlockDatabaseInput,
+) -> Result<DatabaseStatus, String> {
+    if app.try_state::<DbState>().is_none() {
+        let state = db::init_db(&app, Some(&payload.passphrase)).map_err(map_error)?;
+        app.manage(state);
+    }
+    database_status(app)
+}
+
+/// Encrypts the database, changes its passphrase or, without a new
+/// passphrase, decrypts it.
+#[tauri::command(async)]
+pub fn change_database_passphrase(
+    state: State<DbState>,
+    payload: ChangeDatabasePassphraseInput,
+) -> Result<DatabaseStatus, String> {
+    state.authorize(Role::Admin, "change_database_passphrase")?;
+    let encrypted = state.replace_database(|conn| {
+        let passphrase = encryption::change_passphrase(conn, &state.database_path, &payload)?;
+        let encrypted = passphrase.is_some();
+        *state.passphrase.lock().map_err(|_| "Lock error")? = passphrase;
+        Ok(encrypted)
+    })?;
+
+    Ok(DatabaseStatus {
+        encrypted,
+        unlocked: true,
+    })
+}
@@ -0,0 +1,128 @@
+use bokforing_core::db::Role;
+use bokforing_core::models::{
+    ApiToken, ApiTokenIdInput, AuthStatus, ChangePasswordInput, CreateApiTokenInput,
+    CreateUserInput, CreatedApiToken, LoginInput, PermissionDenial, SetUserRoleInput, User,
+};
+use bokforing_core::users;
+use tauri::State;
+
+use crate::db::DbState;
+
+/// Whether any users exist (if not, the first one can be created without
+/// logging in) and who is logged in.
+#[tauri::command(async)]
+pub fn auth_status(state: State<DbState>) -> Result<AuthStatus, String> {
+    let conn = state.writer.lock().map_err(|_| "Lock error")?;
+    let user_count = users::user_count(&conn)?;
+    let session = state.session.lock().map_err(|_| "Lock error")?.clone();
+    let user = match &session {
+        Some(session) => Some(users::fetch_user(&conn, &session.user_id)?),
+        None => None,
+    };
+    Ok(AuthStatus {
+        has_users: user_count > 0,
+        user,
+        logged_in_at: session.map(|session| session.logged_in_at),
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_users(state: State<DbState>) -> Result<Vec<User>, String> {
+    state.read_books(Role::Admin, "list_users", |books| books.list_users())
+}
+
+/// Creates a user. The very first user is created without a session and
+/// becomes admin; after that only admins can add users.
+#[tauri::command(async)]
+pub fn create_user(state: State<DbState>, payload: CreateUserInput) -> Result<User, String> {
+    {
+        let conn = state.writer.lock().map_err(|_| "Lock error")?;
+        if users::user_count(&conn)? == 0 {
+            return users::create_first_admin(&conn, &payload);
+        }
+    }
+    state.books(Role::Admin, "create_user", |books| {
+        books.create_user(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn login(state: State<DbState>, payload: LoginInput) -> Result<User, String> {
+    let conn = state.writer.lock().map_err(|_| "Lock error")?;
+    let user = users::authenticate(&conn, &payload.username, &payload.password)?;
+    let session = users::start_session(&conn, &user)?;
+    let previous = state
+        .session
+        .lock()
+        .map_err(|_| "Lock error")?
+        .replace(session);
+    if let Some(previous) = previous {
+        users::end_session(&conn, &previous)?;
+    }
+    Ok(user)
+}
+
+#[tauri::command(async)]
+pub fn logout(state: State<DbState>) -> Result<(), String> {
+    let session = state.session.lock().map_err(|_| "Lock error")?.take();
+    if let Some(session) = session {
+        let conn = state.writer.lock().map_err(|_| "Lock error")?;
+        users::end_session(&conn, &session)?;
+    }
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub fn change_password(state: State<DbState>, payload: ChangePasswordInput) -> Result<(), String> {
+    let session = state
+        .session
+        .lock()
+        .map_err(|_| "Lock error")?
+        .clone()
+        .ok_or_else(|| "Not logged in".to_string())?;
+    let conn = state.writer.lock().map_err(|_| "Lock error")?;
+    users::change_password(&conn, &session.user_id, &payload)
+}
+
+#[tauri::command(async)]
+pub fn set_user_role(state: State<DbState>, payload: SetUserRoleInput) -> Result<User, String> {
+    state.books(Role::Admin, "set_user_role", |books| {
+        books.set_user_role(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_permission_denials(state: State<DbState>) -> Result<Vec<PermissionDenial>, String> {
+    state.read_books(Role::Admin, "list_permission_denials", |books| {
+        books.list_permission_denials()
+    })
+}
+
+#[tauri::command(async)]
+pub fn list_api_tokens(state: State<DbState>) -> Result<Vec<ApiToken>, String> {
+    state.read_books(Role::Admin, "list_api_tokens", |books| {
+        books.list_api_tokens()
+    })
+}
+
+/// Issues a token for the local API server (`bokforing serve`). The secret is
+/// only returned here.
+#[tauri::command(async)]
+pub fn create_api_token(
+    state: State<DbState>,
+    payload: CreateApiTokenInput,
+) -> Result<CreatedApiToken, String> {
+    state.books(Role::Admin, "create_api_token", |books| {
+        books.create_api_token(payload)
+    })
+}
+
+#[tauri::command(async)]
+pub fn revoke_api_token(
+    state: State<DbState>,
+    payload: ApiTokenIdInput,
+) -> Result<ApiToken, String> {
+    state.books(Role::Admin, "revoke_api_token", |books| {
+        books.revoke_api_token(payload)
+    })
+}
@@ -1,4 +1,7 @@
+import { useEffect, useState } from "react";
 import { NavLink, Route, Routes } from "react-router-dom";
+import { api } from "./api/client";
+import { AuthStatus, DatabaseStatus } from "./api/types";
 import Dashboard from "./views/Dashboard";
 import VouchersList from "./views/VouchersList";
 import NewVoucher from "./views/NewVoucher";
@@ -7,8 +10,61 @@ import Accounts from "./views/Accounts";
 import PeriodLocks from "./views/PeriodLocks";
 import Reports from "./views/Reports";
 import ExportView from "./views/ExportView";
+import Login from "./views/Login";
+import Unlock from "./views/Unlock";
 
 const App = () => {
+  const [database, setDatabase] = useState<DatabaseStatus | null>(null);
+  const [auth, setAuth] = useState<AuthStatus | null>(null);
+
+  useEffect(() => {
+    void api.databaseStatus().then(setDatabase);
+  }, []);
+
+  useEffect(() => {
+    if (database?.unlocked) {
+      void api.authStatus().then(setAuth);
+    }
+  }, [database]);
+
+  const handleLogout = async () => {
+    await api.logout();
+    setAuth(await api.authStatus());
+  };
+
+  if (database && !database.unlocked) {
+    return (
+      <div className="app">
+        <header className="topbar">
+          <h1>Bokföringsprogram</h1>
+        </header>
+        <main className="content">
+          <Unlock onUnlock={() => void api.databaseStatus().then(setDatabase)} />
+        </main>
+      </div>
+    );
+  }
+
+  if (!auth) {
+    return null;
+  }
+
+  if (!auth.user) {
+    return (
+      <div className="app">
+        <header className="topbar">
+          <h1>Bokföringsprogram</h1>
+        </header>
+        <main className="content">
+          <Login
+            hasUsers={auth.hasUsers}
+            onLogin={(user) => setAuth({ hasUsers: true, user, loggedInAt: null })}
+          />
+        </main>
+      </div>
+    );
+  }
+
   return (
     <div className="app">
       <header className="topbar">
@@ -20,6 +76,8 @@ const App = () => {
           <NavLink to="/period-locks">Periodlås</NavLink>
           <NavLink to="/reports">Rapporter</NavLink>
           <NavLink to="/export">Export</NavLink>
+          <span>{auth.user.displayName}</span>
+          <button onClick={handleLogout}>Logga ut</button>
         </nav>
       </header>
       <main className="content">
@@ -1,20 +1,113 @@
 import { invoke } from "@tauri-apps/api/core";
 import {
   Account,
+  AccountingMethod,
+  AuthStatus,
+  AgingReport,
+  ApiToken,
+  ArchiveExportResult,
+  Attachment,
+  BackupFile,
+  BackupResult,
+  BackupSettings,
+  BankImportResult,
+  BankMatchSuggestions,
+  BankReconciliation,
+  BankReconciliationReport,
+  BankRule,
+  BankTransaction,
+  CashYearEndBooking,
+  CashYearEndResult,
   Company,
+  Counterparty,
+  CounterpartyReport,
   CreateCorrectionInput,
+  CreatedApiToken,
   CreateVoucherInput,
+  Customer,
+  CustomerInvoice,
+  CustomerInvoiceLineInput,
+  DatabaseStatus,
+  InboxDocument,
   LedgerRow,
+  Money,
+  PendingApproval,
   PeriodLock,
+  PermissionDenial,
+  RecurringRunResult,
+  RecurringSchedule,
+  RestoreResult,
+  Role,
+  Supplier,
+  SupplierInvoice,
+  SupplierInvoiceLineInput,
+  UnlinkedDocumentItem,
+  User,
   Voucher,
+  VoucherApproval,
   VoucherListItem,
   VoucherSeries,
+  VoucherTemplate,
+  VoucherTemplateRowInput,
 } from "./types";
 
 export const api = {
+  databaseStatus: () => invoke<DatabaseStatus>("database_status"),
+  unlockDatabase: (passphrase: string) =>
+    invoke<DatabaseStatus>("unlock_database", { passphrase }),
+  changeDatabasePassphrase: (
+    currentPassphrase: string | null,
+    newPassphrase: string | null
+  ) =>
+    invoke<DatabaseStatus>("change_database_passphrase", {
+      currentPassphrase,
+      newPassphrase,
+    }),
+  createBackup: (payload: { directory?: string | null; includeAttachments: boolean }) =>
+    invoke<BackupResult>("create_backup", payload),
+  listBackups: () => invoke<BackupFile[]>("list_backups"),
+  getBackupSettings: () => invoke<BackupSettings>("get_backup_settings"),
+  updateBackupSettings: (payload: {
+    enabled: boolean;
+    intervalHours: number;
+    retentionCount: number;
+    directory?: string | null;
+    includeAttachments: boolean;
+  }) => invoke<BackupSettings>("update_backup_settings", payload),
+  restoreBackup: (path: string, passphrase?: string | null) =>
+    invoke<RestoreResult>("restore_backup", { path, passphrase }),
+  authStatus: () => invoke<AuthStatus>("auth_status"),
+  listUsers: () => invoke<User[]>("list_users"),
+  createUser: (payload: {
+    username: string;
+    displayName: string;
+    password: string;
+    role?: Role | null;
+  }) => invoke<User>("create_user", payload),
+  setUserRole: (userId: string, role: Role) =>
+    invoke<User>("set_user_role", { userId, role }),
+  listPermissionDenials: () => invoke<PermissionDenial[]>("list_permission_denials"),
+  listApiTokens: () => invoke<ApiToken[]>("list_api_tokens"),
+  createApiToken: (username: string, name: string) =>
+    invoke<CreatedApiToken>("create_api_token", { username, name }),
+  revokeApiToken: (tokenId: string) => invoke<ApiToken>("revoke_api_token", { tokenId }),
+  login: (username: string, password: string) =>
+    invoke<User>("login", { username, password }),
+  logout: () => invoke<void>("logout"),
+  changePassword: (currentPassword: string, newPassword: string) =>
+    invoke<void>("change_password", { currentPassword, newPassword }),
   listCompanies: () => invoke<Company[]>("list_companies"),
-  createCompany: (payload: { name: string; orgNumber?: string | null }) =>
-    invoke<Company>("create_company", payload),
+  createCompany: (payload: {
+    name: string;
+    orgNumber?: string | null;
+    accountingMethod?: AccountingMethod | null;
+  }) => invoke<Company>("create_company", payload),
+  setAccountingMethod: (companyId: string, accountingMethod: AccountingMethod) =>
+    invoke<Company>("set_accounting_method", { companyId, accountingMethod }),
+  setApprovalRequirement: (companyId: string, requiresApproval: boolean) =>
+    invoke<Company>("set_approval_requirement", { companyId, requiresApproval }),
+  setZeroRowAllowance: (companyId: string, allowZeroRows: boolean) =>
+    invoke<Company>("set_zero_row_allowance", { companyId, allowZeroRows }),
   listAccounts: (companyId: string) =>
     invoke<Account[]>("list_accounts", { companyId }),
   upsertAccount: (payload: {
@@ -28,16 +121,229 @@ export const api = {
   }) => invoke<Account>("upsert_account", payload),
   listVoucherSeries: (companyId: string) =>
     invoke<VoucherSeries[]>("list_voucher_series", { companyId }),
-  listVouchers: (companyId: string, fromDate?: string, toDate?: string) =>
-    invoke<Voucher[]>("list_vouchers", { companyId, fromDate, toDate }),
+  listVouchers: (
+    companyId: string,
+    fromDate?: string,
+    toDate?: string,
+    limit?: number,
+    after?: string
+  ) =>
+    invoke<Voucher[]>("list_vouchers", {
+      companyId,
+      fromDate,
+      toDate,
+      limit,
+      after,
+    }),
   getVoucher: (voucherId: string) =>
     invoke<Voucher>("get_voucher", { voucherId }),
   createVoucher: (payload: CreateVoucherInput) =>
     invoke<Voucher>("create_voucher", payload),
   postVoucher: (voucherId: string) =>
     invoke<Voucher>("post_voucher", { voucherId }),
+  listPendingApprovals: (companyId: string) =>
+    invoke<PendingApproval[]>("list_pending_approvals", { companyId }),
+  approveVoucher: (voucherId: string, comment?: string | null) =>
+    invoke<VoucherApproval>("approve_voucher", { voucherId, comment }),
+  rejectVoucher: (voucherId: string, comment: string) =>
+    invoke<VoucherApproval>("reject_voucher", { voucherId, comment }),
+  getVoucherApproval: (voucherId: string) =>
+    invoke<VoucherApproval | null>("get_voucher_approval", { voucherId }),
   createCorrectionVoucher: (payload: CreateCorrectionInput) =>
     invoke<Voucher>("create_correction_voucher", payload),
+  listAttachments: (voucherId: string) =>
+    invoke<Attachment[]>("list_attachments", { voucherId }),
+  addAttachmentToVoucher: (payload: {
+    voucherId: string;
+    refType: string;
+    refValue: string;
+    note?: string | null;
+  }) => invoke<Attachment>("add_attachment_to_voucher", payload),
+  importInboxDocuments: (payload: {
+    companyId: string;
+    filePaths: string[];
+    note?: string | null;
+  }) => invoke<InboxDocument[]>("import_inbox_documents", payload),
+  listInboxDocuments: (companyId: string, includeLinked?: boolean) =>
+    invoke<InboxDocument[]>("list_inbox_documents", { companyId, includeLinked }),
+  createVoucherFromInbox: (payload: {
+    inboxDocumentId: string;
+    voucher: CreateVoucherInput;
+  }) => invoke<Voucher>("create_voucher_from_inbox", payload),
+  reportUnlinkedDocuments: (companyId: string) =>
+    invoke<UnlinkedDocumentItem[]>("report_unlinked_documents", { companyId }),
+  importBankStatement: (payload: {
+    companyId: string;
+    filePath: string;
+    format?: "csv" | "camt053" | null;
+  }) => invoke<BankImportResult>("import_bank_statement", payload),
+  listBankTransactions: (payload: {
+    companyId: string;
+    unmatchedOnly?: boolean;
+    fromDate?: string;
+    toDate?: string;
+  }) => invoke<BankTransaction[]>("list_bank_transactions", payload),
+  suggestBankMatches: (bankTransactionId: string) =>
+    invoke<BankMatchSuggestions>("suggest_bank_matches", { bankTransactionId }),
+  matchBankTransaction: (payload: {
+    bankTransactionId: string;
+    voucherId: string;
+  }) => invoke<BankTransaction>("match_bank_transaction", payload),
+  listBankRules: (companyId: string) =>
+    invoke<BankRule[]>("list_bank_rules", { companyId }),
+  upsertBankRule: (payload: {
+    id?: string | null;
+    companyId: string;
+    name: string;
+    matchText: string;
+    counterAccountId: string;
+    vatAccountId?: string | null;
+    vatRatePercent?: number | null;
+    isActive: boolean;
+  }) => invoke<BankRule>("upsert_bank_rule", payload),
+  createVoucherFromBankRule: (payload: {
+    bankTransactionId: string;
+    ruleId: string;
+    seriesId: string;
+    description?: string | null;
+  }) => invoke<Voucher>("create_voucher_from_bank_rule", payload),
+  reportBankReconciliation: (payload: {
+    companyId: string;
+    statementDate: string;
+    statementBalance: Money;
+  }) => invoke<BankReconciliationReport>("report_bank_reconciliation", payload),
+  signOffBankReconciliation: (payload: {
+    companyId: string;
+    period: string;
+    statementBalance: Money;
+    note?: string | null;
+  }) => invoke<BankReconciliation>("sign_off_bank_reconciliation", payload),
+  listBankReconciliations: (companyId: string) =>
+    invoke<BankReconciliation[]>("list_bank_reconciliations", { companyId }),
+  listVoucherTemplates: (companyId: string) =>
+    invoke<VoucherTemplate[]>("list_voucher_templates", { companyId }),
+  upsertVoucherTemplate: (payload: {
+    id?: string | null;
+    companyId: string;
+    name: string;
+    description: string;
+    isActive: boolean;
+    rows: VoucherTemplateRowInput[];
+  }) => invoke<VoucherTemplate>("upsert_voucher_template", payload),
+  instantiateVoucherTemplate: (payload: {
+    templateId: string;
+    seriesId: string;
+    date: string;
+    total: Money;
+    description?: string | null;
+  }) => invoke<Voucher>("instantiate_voucher_template", payload),
+  listRecurringSchedules: (companyId: string) =>
+    invoke<RecurringSchedule[]>("list_recurring_schedules", { companyId }),
+  upsertRecurringSchedule: (payload: {
+    id?: string | null;
+    companyId: string;
+    templateId: string;
+    seriesId: string;
+    total: Money;
+    dayOfMonth: number;
+    startDate: string;
+    endDate?: string | null;
+    isActive: boolean;
+  }) => invoke<RecurringSchedule>("upsert_recurring_schedule", payload),
+  runRecurringSchedules: (companyId: string, asOfDate?: string) =>
+    invoke<RecurringRunResult>("run_recurring_schedules", { companyId, asOfDate }),
+  listCustomers: (companyId: string) =>
+    invoke<Customer[]>("list_customers", { companyId }),
+  upsertCustomer: (payload: {
+    id?: string | null;
+    companyId: string;
+    name: string;
+    orgNumber?: string | null;
+    email?: string | null;
+    address?: string | null;
+    paymentTermsDays?: number | null;
+  }) => invoke<Customer>("upsert_customer", payload),
+  createCustomerInvoice: (payload: {
+    companyId: string;
+    customerId: string;
+    seriesId?: string | null;
+    invoiceDate: string;
+    dueDate?: string | null;
+    lines: CustomerInvoiceLineInput[];
+  }) => invoke<CustomerInvoice>("create_customer_invoice", payload),
+  listCustomerInvoices: (companyId: string, openOnly?: boolean) =>
+    invoke<CustomerInvoice[]>("list_customer_invoices", { companyId, openOnly }),
+  getCustomerInvoice: (invoiceId: string) =>
+    invoke<CustomerInvoice>("get_customer_invoice", { invoiceId }),
+  registerCustomerPayment: (payload: {
+    invoiceId: string;
+    seriesId?: string | null;
+    paymentDate: string;
+    amount: Money;
+  }) => invoke<CustomerInvoice>("register_customer_payment", payload),
+  reportAccountsReceivable: (companyId: string, asOfDate?: string) =>
+    invoke<AgingReport>("report_accounts_receivable", { companyId, asOfDate }),
+  listSuppliers: (companyId: string) =>
+    invoke<Supplier[]>("list_suppliers", { companyId }),
+  upsertSupplier: (payload: {
+    id?: string | null;
+    companyId: string;
+    name: string;
+    orgNumber?: string | null;
+    bankgiro?: string | null;
+    defaultExpenseAccountId?: string | null;
+    paymentTermsDays?: number | null;
+  }) => invoke<Supplier>("upsert_supplier", payload),
+  createSupplierInvoice: (payload: {
+    companyId: string;
+    supplierId: string;
+    seriesId?: string | null;
+    invoiceNumber: string;
+    invoiceDate: string;
+    dueDate?: string | null;
+    paymentReference?: string | null;
+    lines: SupplierInvoiceLineInput[];
+  }) => invoke<SupplierInvoice>("create_supplier_invoice", payload),
+  listSupplierInvoices: (companyId: string, openOnly?: boolean) =>
+    invoke<SupplierInvoice[]>("list_supplier_invoices", { companyId, openOnly }),
+  getSupplierInvoice: (invoiceId: string) =>
+    invoke<SupplierInvoice>("get_supplier_invoice", { invoiceId }),
+  registerSupplierPayment: (payload: {
+    invoiceId: string;
+    seriesId?: string | null;
+    paymentDate: string;
+    amount: Money;
+  }) => invoke<SupplierInvoice>("register_supplier_payment", payload),
+  reportAccountsPayable: (companyId: string, asOfDate?: string) =>
+    invoke<AgingReport>("report_accounts_payable", { companyId, asOfDate }),
+  closeCashMethodYear: (payload: {
+    companyId: string;
+    yearEndDate: string;
+    seriesId?: string | null;
+  }) => invoke<CashYearEndResult>("close_cash_method_year", payload),
+  listCashYearEndBookings: (companyId: string) =>
+    invoke<CashYearEndBooking[]>("list_cash_year_end_bookings", { companyId }),
+  listCounterparties: (companyId: string) =>
+    invoke<Counterparty[]>("list_counterparties", { companyId }),
+  upsertCounterparty: (payload: {
+    id?: string | null;
+    companyId: string;
+    name: string;
+    orgNumber?: string | null;
+    vatNumber?: string | null;
+    address?: string | null;
+    defaultRevenueAccountId?: string | null;
+    defaultExpenseAccountId?: string | null;
+    isActive?: boolean | null;
+  }) => invoke<Counterparty>("upsert_counterparty", payload),
+  searchCounterparties: (companyId: string, query: string, limit?: number) =>
+    invoke<Counterparty[]>("search_counterparties", { companyId, query, limit }),
+  reportVouchersByCounterparty: (payload: {
+    companyId: string;
+    counterpartyId: string;
+    fromDate?: string;
+    toDate?: string;
+  }) => invoke<CounterpartyReport>("report_vouchers_by_counterparty", payload),
   listPeriodLocks: (companyId: string) =>
     invoke<PeriodLock[]>("list_period_locks", { companyId }),
   lockPeriod: (payload: {
@@ -45,6 +351,7 @@ export const api = {
     periodStart: string;
     periodEnd: string;
   }) => invoke<PeriodLock>("lock_period", payload),
+  unlockPeriod: (lockId: string) => invoke<void>("unlock_period", { lockId }),
   reportVoucherList: (payload: {
     companyId: string;
     fromDate?: string;
@@ -60,4 +367,9 @@ export const api = {
     invoke<string>("export_csv", payload),
   exportSie: (payload: { companyId: string; targetPath: string }) =>
     invoke<string>("export_sie_stub", payload),
+  exportArchive: (payload: {
+    companyId: string;
+    fiscalYearStart: string;
+    targetPath: string;
+  }) => invoke<ArchiveExportResult>("export_archive", payload),
 };
@@ -1,11 +1,115 @@
+/** An amount in kronor as a decimal string, e.g. "1234.50". */
+export type Money = string;
+
+export type DatabaseStatus = {
+  encrypted: boolean;
+  unlocked: boolean;
+};
+
+export type BackupResult = {
+  path: string;
+  createdAt: string;
+  sizeBytes: number;
+  encrypted: boolean;
+  attachmentCount: number;
+};
+
+export type BackupFile = {
+  path: string;
+  fileName: string;
+  sizeBytes: number;
+  automatic: boolean;
+};
+
+export type BackupSettings = {
+  enabled: boolean;
+  intervalHours: number;
+  retentionCount: number;
+  directory?: string | null;
+  includeAttachments: boolean;
+  lastBackupAt?: string | null;
+  lastError?: string | null;
+};
+
+export type ArchiveFile = {
+  path: string;
+  sizeBytes: number;
+  sha256: string;
+};
+
+export type ArchiveExportResult = {
+  path: string;
+  fiscalYearStart: string;
+  fiscalYearEnd: string;
+  voucherCount: number;
+  attachmentCount: number;
+  sha256: string;
+  files: ArchiveFile[];
+};
+
+export type RestoreResult = {
+  restoredFrom: string;
+  backupCreatedAt: string;
+  schemaVersion: number;
+  attachmentCount: number;
+  previousDatabasePath: string;
+};
+
+export type Role = "viewer" | "bookkeeper" | "approver" | "admin";
+
+export type User = {
+  id: string;
+  username: string;
+  displayName: string;
+  role: Role;
+  isActive: boolean;
+  createdAt: string;
+  createdBy: string;
+};
+
+export type AuthStatus = {
+  hasUsers: boolean;
+  user?: User | null;
+  loggedInAt?: string | null;
+};
+
+export type PermissionDenial = {
+  id: string;
+  username: string;
+  role: Role;
+  command: string;
+  requiredRole: Role;
+  createdAt: string;
+};
+
+export type ApiToken = {
+  id: string;
+  username: string;
+  name: string;
+  createdAt: string;
+  createdBy: string;
+  lastUsedAt?: string | null;
+  revokedAt?: string | null;
+};
+
+export type CreatedApiToken = {
+  token: ApiToken;
+  secret: string;
+};
+
 export type Company = {
   id: string;
   name: string;
   orgNumber?: string | null;
   fiscalYearStart: string;
   fiscalYearEnd: string;
+  accountingMethod: AccountingMethod;
+  requiresApproval: boolean;
+  allowZeroRows: boolean;
 };
 
+export type AccountingMethod = "accrual" | "cash";
+
 export type Account = {
   id: string;
   companyId: string;
@@ -29,8 +133,8 @@ export type VoucherRow = {
   voucherId: string;
   accountId: string;
   description?: string | null;
-  debitCents: number;
-  creditCents: number;
+  debit: Money;
+  credit: Money;
   vatCode?: string | null;
 };
 
@@ -42,6 +146,7 @@ export type Voucher = {
   date: string;
   description: string;
   counterparty?: string | null;
+  counterpartyId?: string | null;
   postedAt?: string | null;
   createdAt: string;
   createdBy: string;
@@ -58,6 +163,329 @@ export type Attachment = {
   createdAt: string;
 };
 
+export type InboxDocument = {
+  id: string;
+  companyId: string;
+  fileName: string;
+  storedPath: string;
+  sizeBytes: number;
+  note?: string | null;
+  receivedAt: string;
+  receivedBy: string;
+  voucherId?: string | null;
+  linkedAt?: string | null;
+};
+
+export type UnlinkedDocumentItem = {
+  id: string;
+  fileName: string;
+  storedPath: string;
+  note?: string | null;
+  receivedAt: string;
+  daysPending: number;
+};
+
+export type BankTransaction = {
+  id: string;
+  companyId: string;
+  accountId: string;
+  bookingDate: string;
+  valueDate?: string | null;
+  amount: Money;
+  description: string;
+  reference?: string | null;
+  counterparty?: string | null;
+  sourceFormat: string;
+  importedAt: string;
+  importedBy: string;
+  voucherId?: string | null;
+  matchedAt?: string | null;
+};
+
+export type BankImportResult = {
+  imported: BankTransaction[];
+  skippedDuplicates: number;
+};
+
+export type BankRule = {
+  id: string;
+  companyId: string;
+  name: string;
+  matchText: string;
+  counterAccountId: string;
+  vatAccountId?: string | null;
+  vatRatePercent?: number | null;
+  isActive: boolean;
+  createdAt: string;
+};
+
+export type BankVoucherMatch = {
+  voucherId: string;
+  voucherNumber: number;
+  date: string;
+  description: string;
+  amount: Money;
+  dayDifference: number;
+};
+
+export type BankMatchSuggestions = {
+  bankTransactionId: string;
+  vouchers: BankVoucherMatch[];
+  rules: BankRule[];
+};
+
+export type UnmatchedLedgerRow = {
+  voucherId: string;
+  voucherNumber: number;
+  date: string;
+  description: string;
+  amount: Money;
+};
+
+export type BankReconciliationReport = {
+  companyId: string;
+  accountId: string;
+  statementDate: string;
+  statementBalance: Money;
+  ledgerBalance: Money;
+  difference: Money;
+  unmatchedLedgerRows: UnmatchedLedgerRow[];
+  unmatchedStatementLines: BankTransaction[];
+};
+
+export type BankReconciliation = {
+  id: string;
+  companyId: string;
+  accountId: string;
+  period: string;
+  statementDate: string;
+  statementBalance: Money;
+  ledgerBalance: Money;
+  difference: Money;
+  unmatchedLedgerCount: number;
+  unmatchedStatementCount: number;
+  note?: string | null;
+  signedOffAt: string;
+  signedOffBy: string;
+};
+
+export type VoucherTemplateRow = {
+  id: string;
+  templateId: string;
+  accountId: string;
+  description?: string | null;
+  side: "debit" | "credit";
+  percentBasisPoints?: number | null;
+  fixedAmount?: Money | null;
+  vatCode?: string | null;
+};
+
+export type VoucherTemplate = {
+  id: string;
+  companyId: string;
+  name: string;
+  description: string;
+  isActive: boolean;
+  createdAt: string;
+  rows: VoucherTemplateRow[];
+};
+
+export type VoucherTemplateRowInput = Omit<VoucherTemplateRow, "id" | "templateId">;
+
+export type RecurringSchedule = {
+  id: string;
+  companyId: string;
+  templateId: string;
+  seriesId: string;
+  total: Money;
+  dayOfMonth: number;
+  nextRunDate: string;
+  endDate?: string | null;
+  isActive: boolean;
+  createdAt: string;
+};
+
+export type RecurringRunResult = {
+  created: Voucher[];
+  failed: { scheduleId: string; date: string; message: string }[];
+};
+
+export type Customer = {
+  id: string;
+  companyId: string;
+  customerNumber: number;
+  name: string;
+  orgNumber?: string | null;
+  email?: string | null;
+  address?: string | null;
+  paymentTermsDays: number;
+  createdAt: string;
+};
+
+export type CustomerInvoiceLine = {
+  id: string;
+  invoiceId: string;
+  description: string;
+  quantity: number;
+  unitPrice: Money;
+  vatRatePercent: number;
+  revenueAccountId: string;
+  net: Money;
+  vat: Money;
+};
+
+export type InvoicePayment = {
+  id: string;
+  invoiceId: string;
+  paymentDate: string;
+  amount: Money;
+  voucherId?: string | null;
+  createdAt: string;
+  createdBy: string;
+};
+
+export type CustomerInvoice = {
+  id: string;
+  companyId: string;
+  customerId: string;
+  customerName: string;
+  invoiceNumber: number;
+  invoiceDate: string;
+  dueDate: string;
+  ocrNumber: string;
+  net: Money;
+  vat: Money;
+  total: Money;
+  paid: Money;
+  open: Money;
+  voucherId?: string | null;
+  createdAt: string;
+  createdBy: string;
+  lines?: CustomerInvoiceLine[];
+  payments?: InvoicePayment[];
+};
+
+export type CustomerInvoiceLineInput = {
+  description: string;
+  quantity: number;
+  unitPrice: Money;
+  vatRatePercent: number;
+  revenueAccountId: string;
+};
+
+export type Supplier = {
+  id: string;
+  companyId: string;
+  supplierNumber: number;
+  name: string;
+  orgNumber?: string | null;
+  bankgiro?: string | null;
+  defaultExpenseAccountId?: string | null;
+  paymentTermsDays: number;
+  createdAt: string;
+};
+
+export type SupplierInvoiceLine = {
+  id: string;
+  invoiceId: string;
+  description?: string | null;
+  expenseAccountId: string;
+  net: Money;
+  vat: Money;
+};
+
+export type SupplierInvoice = {
+  id: string;
+  companyId: string;
+  supplierId: string;
+  supplierName: string;
+  invoiceNumber: string;
+  invoiceDate: string;
+  dueDate: string;
+  paymentReference?: string | null;
+  net: Money;
+  vat: Money;
+  total: Money;
+  paid: Money;
+  open: Money;
+  voucherId?: string | null;
+  createdAt: string;
+  createdBy: string;
+  lines?: SupplierInvoiceLine[];
+  payments?: InvoicePayment[];
+};
+
+export type SupplierInvoiceLineInput = {
+  description?: string | null;
+  expenseAccountId?: string | null;
+  net: Money;
+  vat: Money;
+};
+
+export type Counterparty = {
+  id: string;
+  companyId: string;
+  name: string;
+  orgNumber?: string | null;
+  vatNumber?: string | null;
+  address?: string | null;
+  defaultRevenueAccountId?: string | null;
+  defaultExpenseAccountId?: string | null;
+  isActive: boolean;
+  createdAt: string;
+};
+
+export type CounterpartyReport = {
+  counterparty: Counterparty;
+  vouchers: VoucherListItem[];
+  voucherCount: number;
+  total: Money;
+};
+
+export type CashYearEndBooking = {
+  id: string;
+  companyId: string;
+  yearEndDate: string;
+  voucherId: string;
+  reversalVoucherId: string;
+  receivables: Money;
+  payables: Money;
+  createdAt: string;
+  createdBy: string;
+};
+
+export type CashYearEndResult = {
+  booking: CashYearEndBooking;
+  voucher: Voucher;
+  reversal: Voucher;
+  customerInvoiceIds: string[];
+  supplierInvoiceIds: string[];
+};
+
+export type AgingRow = {
+  invoiceId: string;
+  partyId: string;
+  partyName: string;
+  invoiceNumber: string;
+  invoiceDate: string;
+  dueDate: string;
+  total: Money;
+  open: Money;
+  daysOverdue: number;
+  bucket: "not_due" | "1-30" | "31-60" | "61-90" | "over_90";
+};
+
+export type AgingReport = {
+  asOfDate: string;
+  rows: AgingRow[];
+  notDue: Money;
+  overdue130: Money;
+  overdue3160: Money;
+  overdue6190: Money;
+  overdueOver90: Money;
+  totalOpen: Money;
+};
+
 export type PeriodLock = {
   id: string;
   companyId: string;
@@ -72,23 +500,46 @@ export type VoucherListItem = {
   voucherNumber: number;
   date: string;
   description: string;
-  totalCents: number;
+  total: Money;
+};
+
+export type ApprovalDecision = "approved" | "rejected";
+
+export type VoucherApproval = {
+  id: string;
+  voucherId: string;
+  decision: ApprovalDecision;
+  comment?: string | null;
+  decidedAt: string;
+  decidedBy: string;
+};
+
+export type PendingApproval = {
+  voucherId: string;
+  seriesId: string;
+  voucherNumber: number;
+  date: string;
+  description: string;
+  counterparty?: string | null;
+  total: Money;
+  createdAt: string;
+  createdBy: string;
 };
 
 export type LedgerRow = {
   date: string;
   voucherNumber: number;
   description: string;
-  debitCents: number;
-  creditCents: number;
-  balanceCents: number;
+  debit: Money;
+  credit: Money;
+  balance: Money;
 };
 
 export type CreateVoucherRowInput = {
   accountId: string;
   description?: string | null;
-  debitCents: number;
-  creditCents: number;
+  debit: Money;
+  credit: Money;
   vatCode?: string | null;
 };
 
@@ -104,10 +555,17 @@ export type CreateVoucherInput = {
   date: string;
   description: string;
   counterparty?: string | null;
+  counterpartyId?: string | null;
   rows: CreateVoucherRowInput[];
   attachments?: CreateAttachmentInput[];
 };
 
+/** A rejected voucher's error is a JSON array of these. */
+export type FieldError = {
+  field: string;
+  message: string;
+};
+
 export type CreateCorrectionInput = {
   originalVoucherId: string;
   date: string;
@@ -1,3 +1,5 @@
+import { Money } from "../api/types";
+
 export const formatCents = (cents: number) => {
   const value = cents / 100;
   return new Intl.NumberFormat("sv-SE", {
@@ -12,3 +14,15 @@ export const parseCents = (value: string) => {
   const amount = Number.parseFloat(normalized || "0");
   return Math.round(amount * 100);
 };
+
+/** Amounts from the backend are decimal strings; sums are done in öre. */
+export const toCents = (amount: Money) => parseCents(amount);
+
+export const fromCents = (cents: number): Money => {
+  const sign = cents < 0 ? "-" : "";
+  const absolute = Math.abs(cents);
+  const ore = String(absolute % 100).padStart(2, "0");
+  return `${sign}${Math.floor(absolute / 100)}.${ore}`;
+};
+
+export const formatMoney = (amount: Money) => formatCents(toCents(amount));
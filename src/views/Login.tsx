@@ -0,0 +1,63 @@
+import { useState } from "react";
+import { api } from "../api/client";
+import { User } from "../api/types";
+
+type Props = {
+  hasUsers: boolean;
+  onLogin: (user: User) => void;
+};
+
+const Login = ({ hasUsers, onLogin }: Props) => {
+  const [username, setUsername] = useState("");
+  const [displayName, setDisplayName] = useState("");
+  const [password, setPassword] = useState("");
+  const [error, setError] = useState<string | null>(null);
+
+  const handleSubmit = async () => {
+    setError(null);
+    try {
+      if (!hasUsers) {
+        await api.createUser({ username, displayName, password });
+      }
+      const user = await api.login(username, password);
+      onLogin(user);
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  return (
+    <section>
+      <h2>{hasUsers ? "Logga in" : "Skapa första användaren"}</h2>
+      {error && <p className="error">{error}</p>}
+      <div className="card">
+        <div className="inline-form">
+          <label>
+            Användarnamn
+            <input value={username} onChange={(event) => setUsername(event.target.value)} />
+          </label>
+          {!hasUsers && (
+            <label>
+              Namn
+              <input
+                value={displayName}
+                onChange={(event) => setDisplayName(event.target.value)}
+              />
+            </label>
+          )}
+          <label>
+            Lösenord
+            <input
+              type="password"
+              value={password}
+              onChange={(event) => setPassword(event.target.value)}
+            />
+          </label>
+          <button onClick={handleSubmit}>{hasUsers ? "Logga in" : "Skapa"}</button>
+        </div>
+      </div>
+    </section>
+  );
+};
+
+export default Login;
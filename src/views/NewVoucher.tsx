@@ -5,16 +5,17 @@ import {
   Account,
   CreateAttachmentInput,
   CreateVoucherRowInput,
+  FieldError,
   VoucherSeries,
 } from "../api/types";
 import { getActiveCompanyId } from "../utils/company";
-import { formatCents, parseCents } from "../utils/money";
+import { formatCents, fromCents, toCents } from "../utils/money";
 
 const emptyRow = (): CreateVoucherRowInput => ({
   accountId: "",
   description: "",
-  debitCents: 0,
-  creditCents: 0,
+  debit: "",
+  credit: "",
   vatCode: null,
 });
 
@@ -24,6 +25,25 @@ const emptyAttachment = (): CreateAttachmentInput => ({
   note: "",
 });
 
+// Any other error is shown as a single general message.
+const parseFieldErrors = (error: string | null): FieldError[] => {
+  if (!error) {
+    return [];
+  }
+  try {
+    const parsed = JSON.parse(error);
+    if (Array.isArray(parsed)) {
+      return parsed as FieldError[];
+    }
+  } catch {
+    // Not a field error list.
+  }
+  return [{ field: "", message: error }];
+};
+
+const formatFieldError = (item: FieldError) =>
+  item.field ? `${item.field}: ${item.message}` : item.message;
+
 const NewVoucher = () => {
   const navigate = useNavigate();
   const [series, setSeries] = useState<VoucherSeries[]>([]);
@@ -59,8 +79,8 @@ const NewVoucher = () => {
   const totals = useMemo(() => {
     return rows.reduce(
       (acc, row) => {
-        acc.debit += row.debitCents;
-        acc.credit += row.creditCents;
+        acc.debit += toCents(row.debit);
+        acc.credit += toCents(row.credit);
         return acc;
       },
       { debit: 0, credit: 0 }
@@ -108,6 +128,15 @@ const NewVoucher = () => {
       setError("Välj ett företag i översikten.");
       return;
     }
+    // A row without an account is not a valid id, so the backend would reject
+    // the whole payload instead of naming the row.
+    const missingAccounts = rows
+      .map((row, index) => (row.accountId ? null : `rows[${index}].accountId: Välj ett konto`))
+      .filter((line): line is string => line !== null);
+    if (missingAccounts.length > 0) {
+      setError(missingAccounts.join("\n"));
+      return;
+    }
     try {
       const payload = {
         companyId,
@@ -115,20 +144,39 @@ const NewVoucher = () => {
         date,
         description,
         counterparty: counterparty || null,
-        rows,
+        rows: rows.map((row) => ({
+          ...row,
+          debit: fromCents(toCents(row.debit)),
+          credit: fromCents(toCents(row.credit)),
+        })),
         attachments: attachments.filter((item) => item.refValue.trim().length > 0),
       };
       const voucher = await api.createVoucher(payload);
       navigate(`/vouchers/${voucher.id}`);
     } catch (err) {
-      setError((err as Error).message);
+      setError(String(err));
     }
   };
 
+  // Field errors come as a JSON array of { field, message }; row errors are
+  // shown next to their row.
+  const fieldErrors = parseFieldErrors(error);
+  const rowErrors = (index: number) =>
+    fieldErrors
+      .filter((item) => item.field.startsWith(`rows[${index}]`))
+      .map(formatFieldError);
+  const generalErrors = fieldErrors
+    .filter((item) => !item.field.startsWith("rows["))
+    .map(formatFieldError);
+
   return (
     <section>
       <h2>Nytt verifikat</h2>
-      {error && <p className="error">{error}</p>}
+      {generalErrors.map((line) => (
+        <p key={line} className="error">
+          {line}
+        </p>
+      ))}
       <div className="card">
         <div className="grid">
           <label>
@@ -208,26 +256,27 @@ const NewVoucher = () => {
                 </td>
                 <td>
                   <input
-                    value={row.debitCents ? row.debitCents / 100 : ""}
+                    value={row.debit}
                     onChange={(event) =>
-                      updateRow(index, {
-                        debitCents: parseCents(event.target.value),
-                      })
+                      updateRow(index, { debit: event.target.value })
                     }
                   />
                 </td>
                 <td>
                   <input
-                    value={row.creditCents ? row.creditCents / 100 : ""}
+                    value={row.credit}
                     onChange={(event) =>
-                      updateRow(index, {
-                        creditCents: parseCents(event.target.value),
-                      })
+                      updateRow(index, { credit: event.target.value })
                     }
                   />
                 </td>
                 <td>
                   <button onClick={() => removeRow(index)}>Ta bort</button>
+                  {rowErrors(index).map((line) => (
+                    <p key={line} className="error">
+                      {line}
+                    </p>
+                  ))}
                 </td>
               </tr>
             ))}
@@ -2,7 +2,7 @@ import { useEffect, useState } from "react";
 import { api } from "../api/client";
 import { Account, LedgerRow, VoucherListItem } from "../api/types";
 import { getActiveCompanyId } from "../utils/company";
-import { formatCents } from "../utils/money";
+import { formatMoney } from "../utils/money";
 
 const Reports = () => {
   const [voucherList, setVoucherList] = useState<VoucherListItem[]>([]);
@@ -100,7 +100,7 @@ const Reports = () => {
                 <td>{row.voucherNumber}</td>
                 <td>{row.date}</td>
                 <td>{row.description}</td>
-                <td>{formatCents(row.totalCents)}</td>
+                <td>{formatMoney(row.total)}</td>
               </tr>
             ))}
           </tbody>
@@ -141,9 +141,9 @@ const Reports = () => {
                 <td>{row.date}</td>
                 <td>{row.voucherNumber}</td>
                 <td>{row.description}</td>
-                <td>{formatCents(row.debitCents)}</td>
-                <td>{formatCents(row.creditCents)}</td>
-                <td>{formatCents(row.balanceCents)}</td>
+                <td>{formatMoney(row.debit)}</td>
+                <td>{formatMoney(row.credit)}</td>
+                <td>{formatMoney(row.balance)}</td>
               </tr>
             ))}
           </tbody>
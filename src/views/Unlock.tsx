@@ -0,0 +1,43 @@
+import { useState } from "react";
+import { api } from "../api/client";
+
+type Props = {
+  onUnlock: () => void;
+};
+
+const Unlock = ({ onUnlock }: Props) => {
+  const [passphrase, setPassphrase] = useState("");
+  const [error, setError] = useState<string | null>(null);
+
+  const handleSubmit = async () => {
+    setError(null);
+    try {
+      await api.unlockDatabase(passphrase);
+      onUnlock();
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  return (
+    <section>
+      <h2>Lås upp databasen</h2>
+      {error && <p className="error">{error}</p>}
+      <div className="card">
+        <div className="inline-form">
+          <label>
+            Lösenfras
+            <input
+              type="password"
+              value={passphrase}
+              onChange={(event) => setPassphrase(event.target.value)}
+            />
+          </label>
+          <button onClick={handleSubmit}>Lås upp</button>
+        </div>
+      </div>
+    </section>
+  );
+};
+
+export default Unlock;
@@ -2,7 +2,7 @@ import { useEffect, useState } from "react";
 import { useNavigate, useParams } from "react-router-dom";
 import { api } from "../api/client";
 import { Voucher } from "../api/types";
-import { formatCents } from "../utils/money";
+import { formatCents, formatMoney, toCents } from "../utils/money";
 
 const VoucherDetail = () => {
   const { id } = useParams();
@@ -49,7 +49,7 @@ const VoucherDetail = () => {
   };
 
   const total =
-    voucher?.rows?.reduce((sum, row) => sum + row.debitCents, 0) ?? 0;
+    voucher?.rows?.reduce((sum, row) => sum + toCents(row.debit), 0) ?? 0;
 
   return (
     <section>
@@ -94,8 +94,8 @@ const VoucherDetail = () => {
                   <tr key={row.id}>
                     <td>{row.accountId}</td>
                     <td>{row.description}</td>
-                    <td>{formatCents(row.debitCents)}</td>
-                    <td>{formatCents(row.creditCents)}</td>
+                    <td>{formatMoney(row.debit)}</td>
+                    <td>{formatMoney(row.credit)}</td>
                   </tr>
                 ))}
               </tbody>
@@ -2,35 +2,46 @@ import { useEffect, useState } from "react";
 import { Link } from "react-router-dom";
 import { api } from "../api/client";
 import { Voucher } from "../api/types";
-import { formatCents } from "../utils/money";
+import { formatCents, toCents } from "../utils/money";
 import { getActiveCompanyId } from "../utils/company";
 
 const sumVoucher = (voucher: Voucher) => {
   if (!voucher.rows) {
     return 0;
   }
-  return voucher.rows.reduce((sum, row) => sum + row.debitCents, 0);
+  return voucher.rows.reduce((sum, row) => sum + toCents(row.debit), 0);
 };
 
+// Vouchers are fetched a page at a time, newest first.
+const PAGE_SIZE = 100;
+
 const VouchersList = () => {
   const [vouchers, setVouchers] = useState<Voucher[]>([]);
+  const [hasMore, setHasMore] = useState(false);
   const [fromDate, setFromDate] = useState("");
   const [toDate, setToDate] = useState("");
   const [error, setError] = useState<string | null>(null);
 
-  const load = async () => {
+  const load = async (after?: Voucher) => {
     const companyId = getActiveCompanyId();
     if (!companyId) {
       setError("Välj ett företag i översikten.");
       return;
     }
     setError(null);
-    const data = await api.listVouchers(
-      companyId,
-      fromDate || undefined,
-      toDate || undefined
-    );
-    setVouchers(data);
+    try {
+      const data = await api.listVouchers(
+        companyId,
+        fromDate || undefined,
+        toDate || undefined,
+        PAGE_SIZE,
+        after?.id
+      );
+      setVouchers((prev) => (after ? [...prev, ...data] : data));
+      setHasMore(data.length === PAGE_SIZE);
+    } catch (err) {
+      setError(String(err));
+    }
   };
 
   useEffect(() => {
@@ -65,7 +76,7 @@ const VouchersList = () => {
               onChange={(event) => setToDate(event.target.value)}
             />
           </label>
-          <button onClick={load}>Applicera</button>
+          <button onClick={() => load()}>Applicera</button>
         </div>
       </div>
       <div className="card">
@@ -95,6 +106,11 @@ const VouchersList = () => {
             ))}
           </tbody>
         </table>
+        {hasMore && (
+          <button onClick={() => load(vouchers[vouchers.length - 1])}>
+            Visa fler
+          </button>
+        )}
       </div>
     </section>
   );